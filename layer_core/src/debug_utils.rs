@@ -0,0 +1,155 @@
+//! Delivers the layer's diagnostics to the debug messengers of applications which enabled
+//! `XR_EXT_debug_utils`, so problems with their bindings show up in their own logs.
+
+use std::{
+    collections::HashMap,
+    ffi::{c_void, CString},
+    os::raw::c_char,
+};
+
+use log::{debug, error, info, warn};
+use openxr::sys as xr;
+use parking_lot::Mutex;
+
+const MESSAGE_ID: &[u8] = b"suinput_layer\0";
+
+#[derive(Clone, Copy)]
+struct Messenger {
+    severities: xr::DebugUtilsMessageSeverityFlagsEXT,
+    types: xr::DebugUtilsMessageTypeFlagsEXT,
+    callback: Option<xr::pfn::DebugUtilsMessengerCallbackEXT>,
+    user_data: *mut c_void,
+}
+
+//The user data belongs to the application, which must accept callbacks from any thread
+unsafe impl Send for Messenger {}
+unsafe impl Sync for Messenger {}
+
+/// The messengers an application created with `xrCreateDebugUtilsMessengerEXT` or chained to its
+/// `XrInstanceCreateInfo`
+#[derive(Default)]
+pub struct DebugMessengers {
+    messengers: Mutex<HashMap<xr::DebugUtilsMessengerEXT, Messenger>>,
+    /// Chained to the instance create info, these live as long as the instance
+    instance_messengers: Vec<Messenger>,
+}
+
+impl Messenger {
+    fn new(create_info: &xr::DebugUtilsMessengerCreateInfoEXT) -> Self {
+        Self {
+            severities: create_info.message_severities,
+            types: create_info.message_types,
+            callback: create_info.user_callback,
+            user_data: create_info.user_data,
+        }
+    }
+}
+
+impl DebugMessengers {
+    /// Takes the messengers chained to the `next` pointer of the instance create info
+    ///
+    /// # Safety
+    ///
+    /// The chain must consist of valid OpenXR structures
+    pub unsafe fn from_instance_create_info(create_info: &xr::InstanceCreateInfo) -> Self {
+        let mut instance_messengers = Vec::new();
+        let mut next = create_info.next as *const xr::BaseInStructure;
+        while !next.is_null() {
+            if (*next).ty == xr::StructureType::DEBUG_UTILS_MESSENGER_CREATE_INFO_EXT {
+                instance_messengers.push(Messenger::new(
+                    &*(next as *const xr::DebugUtilsMessengerCreateInfoEXT),
+                ));
+            }
+            next = (*next).next;
+        }
+        Self {
+            messengers: Default::default(),
+            instance_messengers,
+        }
+    }
+
+    pub fn insert(
+        &self,
+        handle: xr::DebugUtilsMessengerEXT,
+        create_info: &xr::DebugUtilsMessengerCreateInfoEXT,
+    ) {
+        self.messengers
+            .lock()
+            .insert(handle, Messenger::new(create_info));
+    }
+
+    pub fn remove(&self, handle: xr::DebugUtilsMessengerEXT) {
+        self.messengers.lock().remove(&handle);
+    }
+
+    pub fn contains(&self, handle: xr::DebugUtilsMessengerEXT) -> bool {
+        self.messengers.lock().contains_key(&handle)
+    }
+
+    /// Calls every messenger which accepts the severity. Errors are reported as validation
+    /// messages, everything else as general ones.
+    pub fn submit(
+        &self,
+        severity: xr::DebugUtilsMessageSeverityFlagsEXT,
+        function_name: &str,
+        message: &str,
+    ) {
+        let message_type = if severity == xr::DebugUtilsMessageSeverityFlagsEXT::ERROR {
+            xr::DebugUtilsMessageTypeFlagsEXT::VALIDATION
+        } else {
+            xr::DebugUtilsMessageTypeFlagsEXT::GENERAL
+        };
+
+        //Callbacks are run without holding the lock
+        let messengers = self
+            .messengers
+            .lock()
+            .values()
+            .chain(&self.instance_messengers)
+            .filter(|messenger| {
+                messenger.severities.contains(severity) && messenger.types.contains(message_type)
+            })
+            .copied()
+            .collect::<Vec<_>>();
+        if messengers.is_empty() {
+            return;
+        }
+
+        let function_name = c_string(function_name);
+        let message = c_string(message);
+        let data = xr::DebugUtilsMessengerCallbackDataEXT {
+            ty: xr::DebugUtilsMessengerCallbackDataEXT::TYPE,
+            next: std::ptr::null(),
+            message_id: MESSAGE_ID.as_ptr() as *const c_char,
+            function_name: function_name.as_ptr(),
+            message: message.as_ptr(),
+            object_count: 0,
+            objects: std::ptr::null_mut(),
+            session_label_count: 0,
+            session_labels: std::ptr::null_mut(),
+        };
+
+        for messenger in messengers {
+            if let Some(callback) = messenger.callback {
+                unsafe { callback(severity, message_type, &data, messenger.user_data) };
+            }
+        }
+    }
+}
+
+fn c_string(string: &str) -> CString {
+    CString::new(string.replace('\0', "")).unwrap_or_default()
+}
+
+/// Writes a message to the layer's log at the level matching its severity
+pub fn log(severity: xr::DebugUtilsMessageSeverityFlagsEXT, message: &str) {
+    if severity == xr::DebugUtilsMessageSeverityFlagsEXT::ERROR {
+        error!("{}", message);
+    } else if severity == xr::DebugUtilsMessageSeverityFlagsEXT::WARNING {
+        warn!("{}", message);
+    } else if severity == xr::DebugUtilsMessageSeverityFlagsEXT::INFO {
+        info!("{}", message);
+    } else {
+        debug!("{}", message);
+    }
+}
@@ -1,14 +1,30 @@
 use std::collections::HashMap;
+use std::ffi::{c_char, CStr};
 use std::ops::Deref;
+use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
+use crate::debug_utils::DebugMessengers;
+use crate::input::desktop::{self, DesktopInput};
+use crate::input::eye_gaze;
+use crate::input::gamepad::{self, GamepadInput};
+use crate::input::hand_tracking::{self, HandInput};
+use crate::input::interaction_profiles;
+use crate::input::layer_inputs::LayerInputs;
+use crate::input::native_actions::NativeActions;
+use crate::input::virtual_device::VirtualInput;
+use crate::input::{driver_hooks, input_sources::InputSources};
+use crate::ipc::IpcServer;
+use crate::openxr_overrides::call_enumerate;
+use crate::profile::RemapProfile;
+use crate::recording;
 use crate::wrappers::instance::{InnerInstance, InstanceWrapper, Runtime};
 use crate::wrappers::XrHandle;
 use crate::{str_from_bytes_until_nul, ToResult};
 use openxr::sys::loader_interfaces::*;
 
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 
 use openxr::{sys as xr, Instance};
 use openxr::{ExtensionSet, InstanceExtensions, Result};
@@ -50,18 +66,126 @@ fn create_instance(
         openxr::Entry::from_get_instance_proc_addr(next_info.next_get_instance_proc_addr)?
     };
 
+    let profile = RemapProfile::load_for_application(application_name);
+
+    let runtime_extensions = runtime_extensions(&entry);
+    //Extensions are tried when the runtime could not list them
+    let runtime_supports = |extension: &str| {
+        runtime_extensions.as_ref().map_or(true, |extensions| {
+            extensions.iter().any(|name| name == extension)
+        })
+    };
+
+    //Hand tracking input needs the extension even when the application does not enable it
+    let mut enable_hand_tracking =
+        profile.hand_tracking.is_some() && runtime_supports(hand_tracking::HAND_TRACKING_EXTENSION);
+    if profile.hand_tracking.is_some() && !enable_hand_tracking {
+        warn!("Hand tracking input needs XR_EXT_hand_tracking which the runtime does not support");
+    }
+
+    //Aiming with the eyes needs the eye tracker even when the application does not use it
+    let gaze_aim = profile
+        .eye_gaze
+        .as_ref()
+        .map_or(false, |eye_gaze| eye_gaze.aim.is_some());
+    let enable_eye_gaze = gaze_aim && runtime_supports(eye_gaze::EYE_GAZE_EXTENSION);
+    if gaze_aim && !enable_eye_gaze {
+        warn!("Aiming with the eyes needs XR_EXT_eye_gaze_interaction which the runtime does not support");
+    }
+
+    let mut extension_names = if instance_info.enabled_extension_count == 0 {
+        Vec::new()
+    } else {
+        unsafe {
+            std::slice::from_raw_parts(
+                instance_info.enabled_extension_names,
+                instance_info.enabled_extension_count as usize,
+            )
+        }
+        .to_vec()
+    };
+    //Extensions the layer implements are hidden from runtimes which do not have them
+    let mut emulated_extensions = Vec::new();
+    extension_names.retain(|name| {
+        let name = unsafe { CStr::from_ptr(*name) }.to_string_lossy();
+        match interaction_profiles::EMULATED_EXTENSIONS
+            .iter()
+            .find(|extension| **extension == name)
+        {
+            Some(extension) if !runtime_supports(extension) => {
+                info!(
+                    "Emulating `{}` which the runtime does not support",
+                    extension
+                );
+                emulated_extensions.push(*extension);
+                false
+            }
+            _ => true,
+        }
+    });
+    let application_extension_count = extension_names.len();
+    if enable_hand_tracking
+        && !extension_names.iter().any(|name| {
+            unsafe { CStr::from_ptr(*name) }.to_bytes_with_nul()
+                == xr::EXT_HAND_TRACKING_EXTENSION_NAME
+        })
+    {
+        extension_names.push(xr::EXT_HAND_TRACKING_EXTENSION_NAME.as_ptr() as *const c_char);
+    }
+    if enable_eye_gaze
+        && !extension_names.iter().any(|name| {
+            unsafe { CStr::from_ptr(*name) }.to_bytes_with_nul()
+                == xr::EXT_EYE_GAZE_INTERACTION_EXTENSION_NAME
+        })
+    {
+        extension_names.push(xr::EXT_EYE_GAZE_INTERACTION_EXTENSION_NAME.as_ptr() as *const c_char);
+    }
+
     //Initialize the layer bellow us
-    let result = unsafe {
-        let mut layer_info2 = *layer_info;
-        layer_info2.next_info = (*layer_info2.next_info).next;
-        (next_info.next_create_api_layer_instance)(instance_info, &layer_info2, instance).result()
+    let create_next = |extension_names: &[*const c_char]| {
+        let next_instance_info = xr::InstanceCreateInfo {
+            enabled_extension_count: extension_names.len() as u32,
+            enabled_extension_names: extension_names.as_ptr(),
+            ..*instance_info
+        };
+        unsafe {
+            let mut layer_info2 = *layer_info;
+            layer_info2.next_info = (*layer_info2.next_info).next;
+            (next_info.next_create_api_layer_instance)(&next_instance_info, &layer_info2, instance)
+                .result()
+        }
+    };
+    let result = match create_next(&extension_names) {
+        Err(xr::Result::ERROR_EXTENSION_NOT_PRESENT)
+            if runtime_extensions.is_none()
+                && extension_names.len() > application_extension_count =>
+        {
+            warn!(
+                "The runtime does not support the extensions for hand tracking or eye gaze input"
+            );
+            extension_names.truncate(application_extension_count);
+            enable_hand_tracking = extension_names.iter().any(|name| {
+                unsafe { CStr::from_ptr(*name) }.to_bytes_with_nul()
+                    == xr::EXT_HAND_TRACKING_EXTENSION_NAME
+            });
+            create_next(&extension_names)
+        }
+        result => result,
     }?;
 
+    let extensions = ExtensionSet {
+        ext_hand_tracking: enable_hand_tracking,
+        ext_debug_utils: extension_names.iter().any(|name| {
+            unsafe { CStr::from_ptr(*name) }.to_bytes_with_nul()
+                == xr::EXT_DEBUG_UTILS_EXTENSION_NAME
+        }),
+        ..Default::default()
+    };
     let inner = unsafe {
         InnerInstance {
             poison: AtomicBool::new(false),
             core: openxr::raw::Instance::load(&entry, *instance)?,
-            exts: InstanceExtensions::load(&entry, *instance, &ExtensionSet::default())?,
+            exts: InstanceExtensions::load(&entry, *instance, &extensions)?,
             instance: *instance,
         }
     };
@@ -83,13 +207,66 @@ fn create_instance(
         _ => Runtime::Other(runtime_name.to_string()),
     };
 
-    let (suinput_runtime, suinput_instance, suinput_driver) =
-        crate::input::create(unsafe { Instance::from_raw(entry, *instance, inner.exts).unwrap() });
+    let inner = Arc::new(inner);
+    let native_actions = NativeActions::default();
+    let input_sources = Arc::new(InputSources::default());
+    if let Some(path) = std::env::var_os(recording::REPLAY_ENV_VAR) {
+        let path = PathBuf::from(path);
+        match recording::Replay::load(&path) {
+            Ok(replay) => {
+                if replay.application_name() != application_name {
+                    warn!(
+                        "Replaying `{}` which was recorded from `{}`",
+                        path.display(),
+                        replay.application_name()
+                    );
+                }
+                info!("Replaying input from `{}`", path.display());
+                input_sources.start_replay(replay);
+            }
+            Err(err) => warn!("Failed to load replay `{}`: {}", path.display(), err),
+        }
+    }
+    if let Some(config) = &profile.desktop {
+        match desktop::system_source() {
+            Some(source) => input_sources.start_desktop(DesktopInput::new(config.clone(), source)),
+            None => warn!("Desktop input is not supported on this platform"),
+        }
+    }
+    if let Some(config) = &profile.gamepad {
+        match gamepad::system_backend() {
+            Some(backend) => {
+                input_sources.start_gamepad(GamepadInput::new(config.clone(), backend))
+            }
+            None => warn!("The layer was built without SDL2, gamepads are not supported"),
+        }
+    }
+    if let (Some(config), true) = (&profile.hand_tracking, enable_hand_tracking) {
+        input_sources.start_hands(HandInput::new(config.clone()));
+    }
+    if !profile.virtual_devices.is_empty() {
+        input_sources.start_virtual_input(VirtualInput::new(profile.virtual_devices.clone()));
+    }
+    let layer_inputs = LayerInputs::create(&profile, &inner, &native_actions)?;
+
+    //SuInput's driver reads the runtime through hooks which add the input sources
+    driver_hooks::register_instance(
+        next_info.next_get_instance_proc_addr,
+        inner.clone(),
+        input_sources.clone(),
+    );
+    let driver_entry = unsafe {
+        openxr::Entry::from_get_instance_proc_addr(driver_hooks::get_instance_proc_addr)?
+    };
+    let (suinput_runtime, suinput_instance, suinput_driver) = crate::input::create(unsafe {
+        Instance::from_raw(driver_entry, *instance, inner.exts).unwrap()
+    });
 
-    let wrapper = InstanceWrapper {
-        handle: *instance,
+    let handle = *instance;
+    let wrapper = Arc::new_cyclic(|weak| InstanceWrapper {
+        handle,
         application_info: (*instance_info).application_info,
-        inner: Arc::new(inner),
+        inner,
         systems: Default::default(),
         sessions: Default::default(),
         runtime,
@@ -97,11 +274,71 @@ fn create_instance(
         suinput_instance,
         suinput_driver: Mutex::new(suinput_driver),
         suggested_bindings: Mutex::new(HashMap::new()),
-    };
+        application_bindings: Mutex::new(HashMap::new()),
+        pending_bindings: Mutex::new(HashMap::new()),
+        profile,
+        native_actions,
+        input_sources,
+        layer_inputs,
+        input_capture: Default::default(),
+        ipc_server: Mutex::new(match IpcServer::start(weak.clone(), handle.into_raw()) {
+            Ok(server) => {
+                info!("Listening for IPC on `{}`", server.path().display());
+                Some(server)
+            }
+            Err(err) => {
+                warn!("Failed to start IPC server: {}", err);
+                None
+            }
+        }),
+        recorder: Default::default(),
+        injection_ring: Default::default(),
+        action_overrides: Default::default(),
+        debug_messengers: unsafe { DebugMessengers::from_instance_create_info(instance_info) },
+        emulated_extensions,
+    });
 
-    xr::Instance::all_wrappers().insert(*instance, Arc::new(wrapper));
+    if let Some(path) = std::env::var_os(recording::RECORD_ENV_VAR) {
+        if let Err(err) = wrapper.start_recording(Some(PathBuf::from(path))) {
+            warn!("Failed to start recording: {}", err);
+        }
+    }
+
+    xr::Instance::all_wrappers().insert(*instance, wrapper);
 
     info!("Instance created with name `{}`", application_name);
 
     Ok(result)
 }
+
+/// The names of the instance extensions supported by the runtime and the layers below us, `None`
+/// when they cannot be enumerated
+fn runtime_extensions(entry: &openxr::Entry) -> Option<Vec<String>> {
+    let properties = unsafe {
+        call_enumerate(
+            std::ptr::null::<c_char>(),
+            entry.fp().enumerate_instance_extension_properties,
+            xr::ExtensionProperties {
+                ty: xr::ExtensionProperties::TYPE,
+                next: std::ptr::null_mut(),
+                extension_name: [0; xr::MAX_EXTENSION_NAME_SIZE],
+                extension_version: 0,
+            },
+        )
+    };
+    match properties {
+        Ok(properties) => Some(
+            properties
+                .iter()
+                .filter_map(|properties| {
+                    str_from_bytes_until_nul(&properties.extension_name[..]).ok()
+                })
+                .map(str::to_owned)
+                .collect(),
+        ),
+        Err(err) => {
+            warn!("Failed to enumerate the runtime's extensions: {}", err);
+            None
+        }
+    }
+}
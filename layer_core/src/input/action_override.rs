@@ -0,0 +1,210 @@
+use openxr::sys as xr;
+use parking_lot::Mutex;
+
+use crate::ipc::protocol::OverrideState;
+
+/// States forced onto application actions over IPC for debugging. They replace whatever the
+/// bindings and the remapping produce until cleared or until they run out.
+#[derive(Default)]
+pub struct ActionOverrides {
+    overrides: Mutex<Vec<ActionOverride>>,
+}
+
+struct ActionOverride {
+    action: xr::Action,
+    /// NULL overrides every subaction path without an override of its own
+    sub_action_path: xr::Path,
+    state: OverrideState,
+    /// The number of syncs the override lasts, None lasts until cleared
+    frames: Option<u32>,
+    /// The number of syncs the override has been applied at
+    syncs: u32,
+    /// The time of the first sync the override was applied at
+    since: xr::Time,
+}
+
+/// The state an overridden action reports to the application
+#[derive(Debug, Clone, Copy)]
+pub struct OverriddenState {
+    pub state: OverrideState,
+    pub changed_since_last_sync: bool,
+    pub last_change_time: xr::Time,
+}
+
+impl ActionOverrides {
+    /// Overrides the action from the next sync on, replacing an earlier override of the same
+    /// subaction path
+    pub fn set(
+        &self,
+        action: xr::Action,
+        sub_action_path: xr::Path,
+        state: OverrideState,
+        frames: Option<u32>,
+    ) {
+        let mut overrides = self.overrides.lock();
+        overrides
+            .retain(|entry| !(entry.action == action && entry.sub_action_path == sub_action_path));
+        overrides.push(ActionOverride {
+            action,
+            sub_action_path,
+            state,
+            frames,
+            syncs: 0,
+            since: xr::Time::from_nanos(0),
+        });
+    }
+
+    /// Removes the overrides of the actions matching `filter` and returns how many there were
+    pub fn clear(&self, filter: impl Fn(xr::Action) -> bool) -> usize {
+        let mut overrides = self.overrides.lock();
+        let count = overrides.len();
+        overrides.retain(|entry| !filter(entry.action));
+        count - overrides.len()
+    }
+
+    /// Called every `xrSyncActions`, starts pending overrides and drops expired ones
+    pub fn sync(&self, time: xr::Time) {
+        let mut overrides = self.overrides.lock();
+        overrides.retain(|entry| entry.frames.map_or(true, |frames| entry.syncs < frames));
+        for entry in overrides.iter_mut() {
+            entry.syncs += 1;
+            if entry.syncs == 1 {
+                entry.since = time;
+            }
+        }
+    }
+
+    pub fn get(&self, action: xr::Action, sub_action_path: xr::Path) -> Option<OverriddenState> {
+        let overrides = self.overrides.lock();
+        let applied = |path: xr::Path| {
+            overrides.iter().find(|entry| {
+                entry.action == action && entry.sub_action_path == path && entry.syncs > 0
+            })
+        };
+        applied(sub_action_path)
+            .or_else(|| applied(xr::Path::NULL))
+            .map(|entry| OverriddenState {
+                state: entry.state,
+                changed_since_last_sync: entry.syncs == 1,
+                last_change_time: entry.since,
+            })
+    }
+
+    /// Whether the action is forced inactive, in which case its spaces cannot be located either
+    pub fn is_inactive(&self, action: xr::Action, sub_action_path: xr::Path) -> bool {
+        matches!(
+            self.get(action, sub_action_path),
+            Some(OverriddenState {
+                state: OverrideState::Inactive,
+                ..
+            })
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jump() -> xr::Action {
+        xr::Action::from_raw(1)
+    }
+
+    fn grab() -> xr::Action {
+        xr::Action::from_raw(2)
+    }
+
+    fn left_hand() -> xr::Path {
+        xr::Path::from_raw(1)
+    }
+
+    fn right_hand() -> xr::Path {
+        xr::Path::from_raw(2)
+    }
+
+    fn pressed(state: bool) -> OverrideState {
+        OverrideState::Boolean { state }
+    }
+
+    fn time(nanos: i64) -> xr::Time {
+        xr::Time::from_nanos(nanos)
+    }
+
+    #[test]
+    fn overrides_last_their_frames() {
+        let overrides = ActionOverrides::default();
+        overrides.set(jump(), xr::Path::NULL, pressed(true), Some(2));
+        //Overrides start with the next sync
+        assert!(overrides.get(jump(), xr::Path::NULL).is_none());
+
+        for (sync, changed) in [(1000, true), (2000, false)] {
+            overrides.sync(time(sync));
+            let state = overrides.get(jump(), xr::Path::NULL).unwrap();
+            assert_eq!(state.state, pressed(true));
+            assert_eq!(state.changed_since_last_sync, changed);
+            assert_eq!(state.last_change_time, time(1000));
+        }
+
+        overrides.sync(time(3000));
+        assert!(overrides.get(jump(), xr::Path::NULL).is_none());
+    }
+
+    #[test]
+    fn overrides_without_frames_last_until_cleared() {
+        let overrides = ActionOverrides::default();
+        overrides.set(jump(), xr::Path::NULL, pressed(true), None);
+        overrides.set(grab(), xr::Path::NULL, OverrideState::Inactive, None);
+        for sync in 1..=10 {
+            overrides.sync(time(sync));
+        }
+        assert!(overrides.get(jump(), xr::Path::NULL).is_some());
+        assert!(overrides.is_inactive(grab(), left_hand()));
+
+        assert_eq!(overrides.clear(|action| action == jump()), 1);
+        assert!(overrides.get(jump(), xr::Path::NULL).is_none());
+        assert!(overrides.get(grab(), xr::Path::NULL).is_some());
+    }
+
+    #[test]
+    fn null_subaction_path_is_the_fallback() {
+        let overrides = ActionOverrides::default();
+        overrides.set(jump(), xr::Path::NULL, pressed(false), None);
+        overrides.set(jump(), left_hand(), pressed(true), None);
+        overrides.sync(time(1000));
+
+        for (sub_action_path, state) in [
+            (left_hand(), pressed(true)),
+            (right_hand(), pressed(false)),
+            (xr::Path::NULL, pressed(false)),
+        ] {
+            assert_eq!(
+                overrides.get(jump(), sub_action_path).unwrap().state,
+                state,
+                "{:?}",
+                sub_action_path
+            );
+        }
+        assert!(overrides.get(grab(), left_hand()).is_none());
+    }
+
+    #[test]
+    fn replacing_an_override_restarts_it() {
+        let overrides = ActionOverrides::default();
+        overrides.set(jump(), left_hand(), pressed(true), None);
+        overrides.sync(time(1000));
+        overrides.sync(time(2000));
+        assert!(
+            !overrides
+                .get(jump(), left_hand())
+                .unwrap()
+                .changed_since_last_sync
+        );
+
+        overrides.set(jump(), left_hand(), pressed(false), Some(1));
+        overrides.sync(time(3000));
+        let state = overrides.get(jump(), left_hand()).unwrap();
+        assert_eq!(state.state, pressed(false));
+        assert!(state.changed_since_last_sync);
+        assert_eq!(state.last_change_time, time(3000));
+    }
+}
@@ -0,0 +1,331 @@
+use std::f32::consts::FRAC_PI_2;
+
+use openxr::sys as xr;
+use serde::{Deserialize, Serialize};
+
+use crate::ipc::protocol::BindingModification;
+
+/// A binding which drives a boolean action from an analog input or a combination of inputs.
+///
+/// The layer reads the physical inputs through its own actions and evaluates these itself, so
+/// they work on runtimes without `XR_EXT_dpad_binding` or `XR_VALVE_analog_threshold`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BindingKind {
+    /// Pressed once the float input `binding` reaches `on_threshold` until it falls below
+    /// `off_threshold`
+    Threshold {
+        binding: String,
+        on_threshold: f32,
+        off_threshold: f32,
+    },
+    /// Pressed while the stick or trackpad `binding` points into `direction`
+    Dpad {
+        binding: String,
+        direction: DpadDirection,
+        /// Radius of the center region, in which no direction is pressed
+        #[serde(default = "default_center_region")]
+        center_region: f32,
+        /// Angle of each direction's wedge in radians
+        #[serde(default = "default_wedge_angle")]
+        wedge_angle: f32,
+        /// Keep a direction pressed while the input leaves its wedge but not the outer region
+        #[serde(default)]
+        sticky: bool,
+        /// A boolean input which must be held as well, e.g. the trackpad click
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        activator: Option<String>,
+    },
+    /// Pressed while `binding` is pressed and `modifier` is held
+    Modifier { modifier: String, binding: String },
+}
+
+fn default_center_region() -> f32 {
+    0.5
+}
+
+fn default_wedge_angle() -> f32 {
+    FRAC_PI_2
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DpadDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+    Center,
+}
+
+impl DpadDirection {
+    /// The suffix of the `XR_EXT_dpad_binding` path for the direction
+    pub fn path_suffix(self) -> &'static str {
+        match self {
+            DpadDirection::Up => "dpad_up",
+            DpadDirection::Down => "dpad_down",
+            DpadDirection::Left => "dpad_left",
+            DpadDirection::Right => "dpad_right",
+            DpadDirection::Center => "dpad_center",
+        }
+    }
+
+    fn angle(self) -> f32 {
+        match self {
+            DpadDirection::Right => 0.,
+            DpadDirection::Up => FRAC_PI_2,
+            DpadDirection::Left => std::f32::consts::PI,
+            DpadDirection::Down => -FRAC_PI_2,
+            DpadDirection::Center => 0.,
+        }
+    }
+}
+
+impl BindingKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BindingKind::Threshold { .. } => "threshold",
+            BindingKind::Dpad { .. } => "dpad",
+            BindingKind::Modifier { .. } => "modifier",
+        }
+    }
+
+    /// The input which determines the subaction path the action receives the binding through
+    pub fn binding(&self) -> &str {
+        match self {
+            BindingKind::Threshold { binding, .. } => binding,
+            BindingKind::Dpad { binding, .. } => binding,
+            BindingKind::Modifier { binding, .. } => binding,
+        }
+    }
+
+    /// The binding path and modification an application would suggest to get the same input
+    pub fn exported(&self) -> (String, BindingModification) {
+        match self {
+            BindingKind::Threshold {
+                binding,
+                on_threshold,
+                off_threshold,
+            } => (
+                binding.clone(),
+                BindingModification::AnalogThreshold {
+                    on_threshold: *on_threshold,
+                    off_threshold: *off_threshold,
+                },
+            ),
+            BindingKind::Dpad {
+                binding,
+                direction,
+                center_region,
+                wedge_angle,
+                sticky,
+                activator,
+            } => (
+                format!("{}/{}", binding, direction.path_suffix()),
+                BindingModification::DpadBinding {
+                    center_region: *center_region,
+                    wedge_angle: *wedge_angle,
+                    is_sticky: *sticky,
+                    activator: activator.clone(),
+                },
+            ),
+            BindingKind::Modifier { modifier, binding } => (
+                binding.clone(),
+                BindingModification::Modifier {
+                    modifier: modifier.clone(),
+                },
+            ),
+        }
+    }
+
+    /// The physical inputs the binding reads
+    pub fn inputs(&self) -> Vec<&str> {
+        match self {
+            BindingKind::Threshold { binding, .. } => vec![binding],
+            BindingKind::Dpad {
+                binding, activator, ..
+            } => std::iter::once(binding.as_str())
+                .chain(activator.as_deref())
+                .collect(),
+            BindingKind::Modifier { modifier, binding } => vec![modifier, binding],
+        }
+    }
+
+    /// Short description of how the input is modified, e.g. for listing bindings
+    pub fn describe(&self) -> String {
+        match self {
+            BindingKind::Threshold {
+                on_threshold,
+                off_threshold,
+                ..
+            } => format!("threshold on={} off={}", on_threshold, off_threshold),
+            BindingKind::Dpad {
+                direction,
+                center_region,
+                wedge_angle,
+                sticky,
+                activator,
+                ..
+            } => {
+                let mut description = format!(
+                    "dpad {} center={} wedge={}",
+                    direction.path_suffix(),
+                    center_region,
+                    wedge_angle
+                );
+                if *sticky {
+                    description.push_str(" sticky");
+                }
+                if let Some(activator) = activator {
+                    description.push_str(&format!(" while {}", activator));
+                }
+                description
+            }
+            BindingKind::Modifier { modifier, .. } => format!("while {}", modifier),
+        }
+    }
+}
+
+/// The physical values read for a binding during `xrSyncActions`
+#[derive(Debug, Clone, Copy)]
+pub enum BindingInput {
+    Float(f32),
+    Vector2f(xr::Vector2f),
+    Boolean(bool),
+}
+
+#[derive(Debug, Default)]
+pub struct BindingKindState {
+    pressed: bool,
+}
+
+impl BindingKindState {
+    /// Updates the output from the values of `BindingKind::inputs`, in the same order
+    pub fn update(&mut self, kind: &BindingKind, inputs: &[BindingInput]) -> bool {
+        self.pressed = match (kind, inputs) {
+            (
+                BindingKind::Threshold {
+                    on_threshold,
+                    off_threshold,
+                    ..
+                },
+                [BindingInput::Float(value)],
+            ) => {
+                if self.pressed {
+                    *value >= *off_threshold
+                } else {
+                    *value >= *on_threshold
+                }
+            }
+            (
+                BindingKind::Dpad {
+                    direction,
+                    center_region,
+                    wedge_angle,
+                    sticky,
+                    ..
+                },
+                [BindingInput::Vector2f(position), activator @ ..],
+            ) => {
+                let activated = match activator {
+                    [BindingInput::Boolean(held)] => *held,
+                    _ => true,
+                };
+                activated
+                    && dpad_pressed(
+                        *direction,
+                        *position,
+                        *center_region,
+                        *wedge_angle,
+                        *sticky && self.pressed,
+                    )
+            }
+            (
+                BindingKind::Modifier { .. },
+                [BindingInput::Boolean(modifier), BindingInput::Boolean(pressed)],
+            ) => *modifier && *pressed,
+            _ => false,
+        };
+        self.pressed
+    }
+}
+
+fn dpad_pressed(
+    direction: DpadDirection,
+    position: xr::Vector2f,
+    center_region: f32,
+    wedge_angle: f32,
+    held: bool,
+) -> bool {
+    let radius = (position.x * position.x + position.y * position.y).sqrt();
+    if direction == DpadDirection::Center {
+        return radius < center_region;
+    }
+    if radius < center_region {
+        return false;
+    }
+    //A sticky direction stays pressed until the input returns to the center
+    if held {
+        return true;
+    }
+
+    let mut difference = position.y.atan2(position.x) - direction.angle();
+    if difference > std::f32::consts::PI {
+        difference -= std::f32::consts::TAU;
+    } else if difference < -std::f32::consts::PI {
+        difference += std::f32::consts::TAU;
+    }
+    difference.abs() <= wedge_angle / 2.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exported() {
+        for (kind, binding, modification) in [
+            (
+                BindingKind::Threshold {
+                    binding: "/user/hand/left/input/squeeze/value".to_owned(),
+                    on_threshold: 0.6,
+                    off_threshold: 0.4,
+                },
+                "/user/hand/left/input/squeeze/value",
+                BindingModification::AnalogThreshold {
+                    on_threshold: 0.6,
+                    off_threshold: 0.4,
+                },
+            ),
+            (
+                BindingKind::Dpad {
+                    binding: "/user/hand/right/input/trackpad".to_owned(),
+                    direction: DpadDirection::Left,
+                    center_region: 0.3,
+                    wedge_angle: FRAC_PI_2,
+                    sticky: true,
+                    activator: Some("/user/hand/right/input/trackpad/click".to_owned()),
+                },
+                "/user/hand/right/input/trackpad/dpad_left",
+                BindingModification::DpadBinding {
+                    center_region: 0.3,
+                    wedge_angle: FRAC_PI_2,
+                    is_sticky: true,
+                    activator: Some("/user/hand/right/input/trackpad/click".to_owned()),
+                },
+            ),
+            (
+                BindingKind::Modifier {
+                    modifier: "/user/hand/left/input/x/click".to_owned(),
+                    binding: "/user/hand/right/input/a/click".to_owned(),
+                },
+                "/user/hand/right/input/a/click",
+                BindingModification::Modifier {
+                    modifier: "/user/hand/left/input/x/click".to_owned(),
+                },
+            ),
+        ] {
+            assert_eq!(kind.exported(), (binding.to_owned(), modification));
+        }
+    }
+}
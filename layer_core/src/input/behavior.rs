@@ -0,0 +1,230 @@
+use openxr::sys as xr;
+use serde::{Deserialize, Serialize};
+
+/// A time dependent transformation applied to the state of a boolean action.
+///
+/// All timing is driven by the predicted display time the application received from `xrWaitFrame`,
+/// so the same sequence of inputs and frame times always produces the same output.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ButtonBehavior {
+    /// Each press flips the output
+    Toggle,
+    /// Outputs true for a single sync when the button is released before `max_duration_ms`
+    ShortPress { max_duration_ms: u32 },
+    /// Outputs true while the button has been held for at least `min_duration_ms`
+    LongPress { min_duration_ms: u32 },
+    /// Outputs true while the second press of a double tap is held
+    DoubleTap { window_ms: u32 },
+    /// Repeatedly presses and releases while the button is held
+    Turbo { rate_hz: f32 },
+}
+
+#[derive(Debug)]
+pub struct BehaviorState {
+    behavior: ButtonBehavior,
+    pressed: bool,
+    press_time: xr::Time,
+    last_release_time: Option<xr::Time>,
+    output: bool,
+    changed: bool,
+    last_change_time: xr::Time,
+}
+
+impl BehaviorState {
+    pub fn new(behavior: ButtonBehavior) -> Self {
+        Self {
+            behavior,
+            pressed: false,
+            press_time: xr::Time::from_nanos(0),
+            last_release_time: None,
+            output: false,
+            changed: false,
+            last_change_time: xr::Time::from_nanos(0),
+        }
+    }
+
+    pub fn update(&mut self, pressed: bool, time: xr::Time) {
+        let was_pressed = self.pressed;
+        self.pressed = pressed;
+
+        if pressed && !was_pressed {
+            self.press_time = time;
+        }
+
+        let output = match self.behavior {
+            ButtonBehavior::Toggle => {
+                if pressed && !was_pressed {
+                    !self.output
+                } else {
+                    self.output
+                }
+            }
+            ButtonBehavior::ShortPress { max_duration_ms } => {
+                !pressed
+                    && was_pressed
+                    && elapsed_ms(self.press_time, time) < max_duration_ms as f64
+            }
+            ButtonBehavior::LongPress { min_duration_ms } => {
+                pressed && elapsed_ms(self.press_time, time) >= min_duration_ms as f64
+            }
+            ButtonBehavior::DoubleTap { window_ms } => {
+                if pressed && !was_pressed {
+                    self.last_release_time.take().map_or(false, |release| {
+                        elapsed_ms(release, time) <= window_ms as f64
+                    })
+                } else if !pressed && was_pressed {
+                    //The release of a double tap should not count as the first tap of another
+                    if !self.output {
+                        self.last_release_time = Some(time);
+                    }
+                    false
+                } else {
+                    self.output
+                }
+            }
+            ButtonBehavior::Turbo { rate_hz } => {
+                if pressed && rate_hz > 0. {
+                    let half_period_ms = 500. / rate_hz as f64;
+                    (elapsed_ms(self.press_time, time) / half_period_ms) as i64 % 2 == 0
+                } else {
+                    pressed
+                }
+            }
+        };
+
+        self.changed = output != self.output;
+        if self.changed {
+            self.last_change_time = time;
+        }
+        self.output = output;
+    }
+
+    pub fn state(&self) -> bool {
+        self.output
+    }
+
+    pub fn changed_since_last_sync(&self) -> bool {
+        self.changed
+    }
+
+    pub fn last_change_time(&self) -> xr::Time {
+        self.last_change_time
+    }
+}
+
+fn elapsed_ms(from: xr::Time, to: xr::Time) -> f64 {
+    (to.as_nanos() - from.as_nanos()) as f64 / 1_000_000.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feeds one sample of `(time in ms, pressed)` per sync and checks the output after each
+    fn check(behavior: ButtonBehavior, steps: &[(i64, bool, bool)]) {
+        let mut state = BehaviorState::new(behavior);
+        let mut previous = false;
+        for (ms, pressed, expected) in steps {
+            let time = xr::Time::from_nanos(ms * 1_000_000);
+            state.update(*pressed, time);
+            assert_eq!(state.state(), *expected, "{:?} at {} ms", behavior, ms);
+            assert_eq!(
+                state.changed_since_last_sync(),
+                *expected != previous,
+                "{:?} at {} ms",
+                behavior,
+                ms
+            );
+            if *expected != previous {
+                assert_eq!(state.last_change_time(), time);
+            }
+            previous = *expected;
+        }
+    }
+
+    #[test]
+    fn toggle() {
+        check(
+            ButtonBehavior::Toggle,
+            &[
+                (0, false, false),
+                (10, true, true),
+                (20, true, true),
+                (30, false, true),
+                (40, true, false),
+                (50, false, false),
+            ],
+        );
+    }
+
+    #[test]
+    fn short_and_long_press_of_one_button() {
+        //Time, pressed, short press output, long press output
+        let steps = [
+            (0, true, false, false),
+            (100, false, true, false),
+            (110, false, false, false),
+            (1000, true, false, false),
+            (1400, true, false, false),
+            (1500, true, false, true),
+            (1600, true, false, true),
+            (1700, false, false, false),
+        ];
+        check(
+            ButtonBehavior::ShortPress {
+                max_duration_ms: 200,
+            },
+            &steps.map(|(ms, pressed, short, _)| (ms, pressed, short)),
+        );
+        check(
+            ButtonBehavior::LongPress {
+                min_duration_ms: 500,
+            },
+            &steps.map(|(ms, pressed, _, long)| (ms, pressed, long)),
+        );
+    }
+
+    #[test]
+    fn double_tap() {
+        check(
+            ButtonBehavior::DoubleTap { window_ms: 300 },
+            &[
+                (0, true, false),
+                (100, false, false),
+                (300, true, true),
+                (350, true, true),
+                //Releasing a double tap does not start another one
+                (400, false, false),
+                (500, true, false),
+                (600, false, false),
+                //The window has expired
+                (1000, true, false),
+                (1100, false, false),
+                (1300, true, true),
+                (1310, false, false),
+            ],
+        );
+    }
+
+    #[test]
+    fn turbo() {
+        check(
+            ButtonBehavior::Turbo { rate_hz: 10. },
+            &[
+                (0, true, true),
+                (25, true, true),
+                (50, true, false),
+                (99, true, false),
+                (100, true, true),
+                (120, false, false),
+                (200, true, true),
+                (260, true, false),
+            ],
+        );
+        check(
+            ButtonBehavior::Turbo { rate_hz: 0. },
+            &[(0, true, true), (500, true, true), (600, false, false)],
+        );
+    }
+}
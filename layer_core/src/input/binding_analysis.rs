@@ -0,0 +1,372 @@
+use std::collections::BTreeMap;
+
+use log::{debug, info, warn};
+
+use crate::ipc::protocol::{
+    ActionSetInfo, BindingConflict, BindingModification, BindingReport, ExportedBinding,
+};
+
+use super::interaction_profiles::{self, ComponentType};
+
+/// Checks the effective bindings of one interaction profile, `bindings` may contain those of
+/// other profiles as well. `reserved` are the physical inputs the layer reads itself.
+pub fn analyze(
+    interaction_profile: &str,
+    action_sets: &[ActionSetInfo],
+    bindings: &[ExportedBinding],
+    reserved: &[&str],
+) -> BindingReport {
+    let bindings = bindings
+        .iter()
+        .filter(|binding| binding.interaction_profile == interaction_profile)
+        .collect::<Vec<_>>();
+
+    let mut report = BindingReport {
+        interaction_profile: interaction_profile.to_owned(),
+        ..Default::default()
+    };
+
+    for action_set in action_sets {
+        for action in &action_set.actions {
+            if !bindings.iter().any(|binding| {
+                binding.action_set == action_set.name && binding.action == action.name
+            }) {
+                report
+                    .unbound_actions
+                    .push(format!("{}/{}", action_set.name, action.name));
+            }
+        }
+    }
+
+    let mut actions_by_input = BTreeMap::<(&str, u32), Vec<String>>::new();
+    for binding in &bindings {
+        //Thresholds, dpad directions and modifiers share their inputs on purpose
+        let is_plain = binding
+            .modifications
+            .iter()
+            .all(|modification| matches!(modification, BindingModification::SubactionPath { .. }));
+        if !is_plain {
+            continue;
+        }
+        let priority = match action_sets
+            .iter()
+            .find(|action_set| action_set.name == binding.action_set)
+        {
+            Some(action_set) => action_set.priority,
+            None => continue,
+        };
+        //The click and value of a trigger are the same input
+        let input = identifier(&binding.binding).unwrap_or(&binding.binding);
+        let actions = actions_by_input.entry((input, priority)).or_default();
+        let action = format!("{}/{}", binding.action_set, binding.action);
+        if !actions.contains(&action) {
+            actions.push(action);
+        }
+    }
+    report.conflicts = actions_by_input
+        .into_iter()
+        .filter(|(_, actions)| actions.len() > 1)
+        .map(|((binding, priority), actions)| BindingConflict {
+            binding: binding.to_owned(),
+            priority,
+            actions,
+        })
+        .collect();
+
+    if let Some(profile) = interaction_profiles::get(interaction_profile) {
+        let used = bindings
+            .iter()
+            .flat_map(|binding| read_inputs(binding))
+            .chain(reserved.iter().copied())
+            .collect::<Vec<_>>();
+
+        let mut inputs = Vec::<String>::new();
+        for (path, component) in profile.binding_paths() {
+            if matches!(component.ty, ComponentType::Pose | ComponentType::Vibration) {
+                continue;
+            }
+            if let Some(input) = identifier(&path) {
+                if !inputs.iter().any(|known| *known == input) {
+                    inputs.push(input.to_owned());
+                }
+            }
+        }
+        report.unused_inputs = inputs
+            .into_iter()
+            .filter(|input| !used.iter().any(|binding| reads(binding, input)))
+            .collect();
+    }
+
+    report
+}
+
+/// The physical inputs a binding reads, including modifiers and dpad activators
+fn read_inputs(binding: &ExportedBinding) -> Vec<&str> {
+    let mut inputs = vec![binding.binding.as_str()];
+    for modification in &binding.modifications {
+        match modification {
+            BindingModification::DpadBinding {
+                activator: Some(activator),
+                ..
+            } => inputs.push(activator),
+            BindingModification::Modifier { modifier } => inputs.push(modifier),
+            _ => {}
+        }
+    }
+    inputs
+}
+
+/// The identifier path of a component, e.g. `/user/hand/left/input/trigger` for
+/// `/user/hand/left/input/trigger/value`
+fn identifier(path: &str) -> Option<&str> {
+    let start = path.find("/input/")? + "/input/".len();
+    let end = path[start..]
+        .find('/')
+        .map_or(path.len(), |end| start + end);
+    Some(&path[..end])
+}
+
+/// Whether a binding reads any component of an identifier
+fn reads(binding: &str, identifier: &str) -> bool {
+    binding == identifier
+        || binding
+            .strip_prefix(identifier)
+            .map_or(false, |rest| rest.starts_with('/'))
+}
+
+/// Writes a report to the log, conflicts as warnings
+pub fn log(application_name: &str, report: &BindingReport) {
+    let interaction_profile = &report.interaction_profile;
+    for action in &report.unbound_actions {
+        info!(
+            "{}: `{}` has no binding on `{}`",
+            application_name, action, interaction_profile
+        );
+    }
+    for conflict in &report.conflicts {
+        warn!(
+            "{}: `{}` is bound to {} on `{}`",
+            application_name,
+            conflict.binding,
+            conflict.actions.join(", "),
+            interaction_profile
+        );
+    }
+    if !report.unused_inputs.is_empty() {
+        debug!(
+            "{}: unused inputs on `{}`: {}",
+            application_name,
+            interaction_profile,
+            report.unused_inputs.join(", ")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ipc::protocol::{ActionInfo, ActionType};
+
+    use super::*;
+
+    const SIMPLE_CONTROLLER: &str = "/interaction_profiles/khr/simple_controller";
+
+    fn action_set(name: &str, priority: u32, actions: &[&str]) -> ActionSetInfo {
+        ActionSetInfo {
+            name: name.to_owned(),
+            localized_name: String::new(),
+            priority,
+            actions: actions
+                .iter()
+                .map(|action| ActionInfo {
+                    name: (*action).to_owned(),
+                    localized_name: String::new(),
+                    action_type: ActionType::Boolean,
+                    sub_action_paths: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    fn binding(
+        interaction_profile: &str,
+        action: &str,
+        binding: &str,
+        modifications: Vec<BindingModification>,
+    ) -> ExportedBinding {
+        let (action_set, action) = action.split_once('/').unwrap();
+        ExportedBinding {
+            interaction_profile: interaction_profile.to_owned(),
+            action_set: action_set.to_owned(),
+            action: action.to_owned(),
+            binding: binding.to_owned(),
+            modifications,
+        }
+    }
+
+    #[test]
+    fn identifiers() {
+        for (path, expected) in [
+            (
+                "/user/hand/left/input/trigger/value",
+                Some("/user/hand/left/input/trigger"),
+            ),
+            (
+                "/user/hand/left/input/thumbstick",
+                Some("/user/hand/left/input/thumbstick"),
+            ),
+            ("/user/hand/left/output/haptic", None),
+        ] {
+            assert_eq!(identifier(path), expected, "{}", path);
+        }
+    }
+
+    #[test]
+    fn unbound_actions() {
+        let action_sets = [
+            action_set("gameplay", 0, &["jump", "crouch"]),
+            action_set("menu", 1, &["open"]),
+        ];
+        for (bindings, expected) in [
+            (
+                vec![],
+                vec!["gameplay/jump", "gameplay/crouch", "menu/open"],
+            ),
+            (
+                vec![binding(
+                    SIMPLE_CONTROLLER,
+                    "gameplay/jump",
+                    "/user/hand/left/input/select/click",
+                    vec![],
+                )],
+                vec!["gameplay/crouch", "menu/open"],
+            ),
+            //Bindings on other profiles do not count
+            (
+                vec![binding(
+                    "/interaction_profiles/oculus/touch_controller",
+                    "menu/open",
+                    "/user/hand/left/input/menu/click",
+                    vec![],
+                )],
+                vec!["gameplay/jump", "gameplay/crouch", "menu/open"],
+            ),
+        ] {
+            let report = analyze(SIMPLE_CONTROLLER, &action_sets, &bindings, &[]);
+            assert_eq!(report.unbound_actions, expected);
+        }
+    }
+
+    #[test]
+    fn conflicts() {
+        let action_sets = [
+            action_set("gameplay", 0, &["jump", "crouch"]),
+            action_set("menu", 1, &["open"]),
+        ];
+        let select = "/user/hand/left/input/select/click";
+        for (bindings, expected) in [
+            //Different inputs
+            (
+                vec![
+                    binding(SIMPLE_CONTROLLER, "gameplay/jump", select, vec![]),
+                    binding(
+                        SIMPLE_CONTROLLER,
+                        "gameplay/crouch",
+                        "/user/hand/left/input/menu/click",
+                        vec![],
+                    ),
+                ],
+                vec![],
+            ),
+            //The same input with the same priority
+            (
+                vec![
+                    binding(SIMPLE_CONTROLLER, "gameplay/jump", select, vec![]),
+                    binding(
+                        SIMPLE_CONTROLLER,
+                        "gameplay/crouch",
+                        "/user/hand/left/input/select",
+                        vec![],
+                    ),
+                ],
+                vec![(
+                    "/user/hand/left/input/select",
+                    0,
+                    vec!["gameplay/jump", "gameplay/crouch"],
+                )],
+            ),
+            //Sets of another priority hide each other's bindings
+            (
+                vec![
+                    binding(SIMPLE_CONTROLLER, "gameplay/jump", select, vec![]),
+                    binding(SIMPLE_CONTROLLER, "menu/open", select, vec![]),
+                ],
+                vec![],
+            ),
+            //Modifiers share their inputs on purpose
+            (
+                vec![
+                    binding(SIMPLE_CONTROLLER, "gameplay/jump", select, vec![]),
+                    binding(
+                        SIMPLE_CONTROLLER,
+                        "gameplay/crouch",
+                        select,
+                        vec![BindingModification::Modifier {
+                            modifier: "/user/hand/right/input/select/click".to_owned(),
+                        }],
+                    ),
+                ],
+                vec![],
+            ),
+        ] {
+            let report = analyze(SIMPLE_CONTROLLER, &action_sets, &bindings, &[]);
+            let conflicts = report
+                .conflicts
+                .iter()
+                .map(|conflict| {
+                    (
+                        conflict.binding.as_str(),
+                        conflict.priority,
+                        conflict.actions.iter().map(String::as_str).collect(),
+                    )
+                })
+                .collect::<Vec<(&str, u32, Vec<&str>)>>();
+            assert_eq!(conflicts, expected);
+        }
+    }
+
+    #[test]
+    fn unused_inputs() {
+        let action_sets = [action_set("gameplay", 0, &["jump", "crouch"])];
+        for (bindings, reserved, expected) in [
+            (
+                vec![],
+                vec![],
+                vec![
+                    "/user/hand/left/input/select",
+                    "/user/hand/left/input/menu",
+                    "/user/hand/right/input/select",
+                    "/user/hand/right/input/menu",
+                ],
+            ),
+            (
+                vec![binding(
+                    SIMPLE_CONTROLLER,
+                    "gameplay/jump",
+                    "/user/hand/left/input/select/click",
+                    vec![BindingModification::Modifier {
+                        modifier: "/user/hand/right/input/select/click".to_owned(),
+                    }],
+                )],
+                vec!["/user/hand/left/input/menu/click"],
+                vec!["/user/hand/right/input/menu"],
+            ),
+        ] {
+            let report = analyze(SIMPLE_CONTROLLER, &action_sets, &bindings, &reserved);
+            let mut unused = report.unused_inputs.clone();
+            unused.sort();
+            let mut expected = expected;
+            expected.sort();
+            assert_eq!(unused, expected);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::{
+    collections::HashSet,
+    sync::mpsc::{self, Receiver, Sender},
+};
+
+use openxr::sys as xr;
+use parking_lot::Mutex;
+
+/// Waits for the user to press a physical input, used to rebind actions
+#[derive(Debug, Default)]
+pub struct InputCapture {
+    pending: Mutex<Option<PendingCapture>>,
+}
+
+#[derive(Debug)]
+struct PendingCapture {
+    /// Inputs which were already held, these are only captured after being released
+    held: Option<HashSet<xr::Action>>,
+    sender: Sender<xr::Action>,
+}
+
+impl InputCapture {
+    /// Starts a capture, replacing any capture still in progress
+    pub fn start(&self) -> Receiver<xr::Action> {
+        let (sender, receiver) = mpsc::channel();
+        *self.pending.lock() = Some(PendingCapture { held: None, sender });
+        receiver
+    }
+
+    pub fn cancel(&self) {
+        *self.pending.lock() = None;
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.pending.lock().is_some()
+    }
+
+    /// Feeds the layer's capture actions which are currently pressed
+    pub fn update(&self, pressed: HashSet<xr::Action>) {
+        let mut pending = self.pending.lock();
+        let capture = match pending.as_mut() {
+            Some(capture) => capture,
+            None => return,
+        };
+
+        let held = match &mut capture.held {
+            Some(held) => held,
+            None => {
+                capture.held = Some(pressed);
+                return;
+            }
+        };
+
+        match pressed.iter().find(|action| !held.contains(action)) {
+            Some(action) => {
+                //Nobody is waiting anymore if sending fails, either way the capture is over
+                let _ = capture.sender.send(*action);
+                *pending = None;
+            }
+            None => held.retain(|action| pressed.contains(action)),
+        }
+    }
+}
@@ -0,0 +1,430 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use openxr::sys as xr;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    math::{self, Transform},
+    recording::InputValue,
+};
+
+use super::user_paths::Hand;
+
+/// Drives controller inputs and a hand's pose from the keyboard and mouse, so applications can be
+/// tested at a desk with the headset off
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DesktopConfig {
+    /// Keys and mouse buttons, named like evdev names them, e.g. `KEY_SPACE` or `BTN_LEFT`
+    pub keys: Vec<KeyBinding>,
+    /// Relative mouse axes, e.g. `REL_WHEEL`
+    pub axes: Vec<AxisBinding>,
+    /// The hand which points where the mouse is moved
+    pub mouse_pose: Option<MousePoseConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyBinding {
+    pub key: String,
+    /// The controller input the key presses, e.g. `/user/hand/right/input/a/click`
+    pub binding: String,
+    /// The value of the input while the key is held, keys of the same input add up
+    #[serde(default = "default_key_value")]
+    pub value: f32,
+}
+
+fn default_key_value() -> f32 {
+    1.
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AxisBinding {
+    pub axis: String,
+    /// A float input, e.g. `/user/hand/right/input/thumbstick/y`
+    pub binding: String,
+    /// The value of the input per count moved during a frame
+    pub scale: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MousePoseConfig {
+    pub hand: Hand,
+    /// Radians turned per count of mouse motion
+    #[serde(default = "default_sensitivity")]
+    pub sensitivity: f32,
+    /// Where the hand is held relative to the head before turning
+    #[serde(default = "default_hand_offset")]
+    pub offset: Transform,
+}
+
+fn default_sensitivity() -> f32 {
+    0.002
+}
+
+fn default_hand_offset() -> Transform {
+    Transform {
+        position: [0., -0.2, -0.4],
+        rotation: [0., 0., 0.],
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DesktopEvent {
+    Key { key: String, pressed: bool },
+    Motion { axis: String, delta: i32 },
+}
+
+/// Somewhere keyboard and mouse events come from
+pub trait EventSource: Send {
+    /// Appends every event which happened since the last call
+    fn poll(&mut self, events: &mut Vec<DesktopEvent>);
+}
+
+/// Events pushed by hand, e.g. from tests
+#[derive(Debug, Clone, Default)]
+pub struct SyntheticSource {
+    events: Arc<Mutex<Vec<DesktopEvent>>>,
+}
+
+impl SyntheticSource {
+    pub fn push(&self, event: DesktopEvent) {
+        self.events.lock().push(event);
+    }
+}
+
+impl EventSource for SyntheticSource {
+    fn poll(&mut self, events: &mut Vec<DesktopEvent>) {
+        events.append(&mut self.events.lock());
+    }
+}
+
+/// Reads every keyboard and mouse under `/dev/input`, which needs the user to be allowed to read
+/// them (usually through the `input` group)
+#[cfg(target_os = "linux")]
+pub struct EvdevSource {
+    events: Arc<Mutex<Vec<DesktopEvent>>>,
+}
+
+#[cfg(target_os = "linux")]
+impl EvdevSource {
+    pub fn open() -> Self {
+        use evdev::InputEventKind;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        for (path, mut device) in evdev::enumerate() {
+            if device.supported_keys().is_none() && device.supported_relative_axes().is_none() {
+                continue;
+            }
+            log::info!(
+                "Reading desktop input from `{}` ({})",
+                path.display(),
+                device.name().unwrap_or("unnamed")
+            );
+
+            //Reading blocks, so every device gets a thread which ends with the source
+            let weak = Arc::downgrade(&events);
+            std::thread::spawn(move || loop {
+                let read = match device.fetch_events() {
+                    Ok(read) => read
+                        .filter_map(|event| match event.kind() {
+                            InputEventKind::Key(key) => Some(DesktopEvent::Key {
+                                key: format!("{:?}", key),
+                                pressed: event.value() != 0,
+                            }),
+                            InputEventKind::RelAxis(axis) => Some(DesktopEvent::Motion {
+                                axis: format!("{:?}", axis),
+                                delta: event.value(),
+                            }),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>(),
+                    Err(err) => {
+                        log::warn!("Stopped reading `{}`: {}", path.display(), err);
+                        return;
+                    }
+                };
+                match weak.upgrade() {
+                    Some(events) => events.lock().extend(read),
+                    None => return,
+                }
+            });
+        }
+
+        EvdevSource { events }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl EventSource for EvdevSource {
+    fn poll(&mut self, events: &mut Vec<DesktopEvent>) {
+        events.append(&mut self.events.lock());
+    }
+}
+
+/// The keyboards and mice of the system, if reading them is supported
+pub fn system_source() -> Option<Box<dyn EventSource>> {
+    #[cfg(target_os = "linux")]
+    {
+        Some(Box::new(EvdevSource::open()))
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+pub struct DesktopInput {
+    config: DesktopConfig,
+    source: Mutex<Box<dyn EventSource>>,
+    state: Mutex<DesktopState>,
+}
+
+#[derive(Default)]
+struct DesktopState {
+    held: HashSet<String>,
+    /// The values of controller inputs after the last sync
+    values: HashMap<String, f32>,
+    yaw: f32,
+    pitch: f32,
+}
+
+impl DesktopInput {
+    pub fn new(config: DesktopConfig, source: Box<dyn EventSource>) -> Self {
+        Self {
+            config,
+            source: Mutex::new(source),
+            state: Default::default(),
+        }
+    }
+
+    /// Applies the events since the previous `xrSyncActions`
+    pub fn sync(&self) {
+        let mut events = Vec::new();
+        self.source.lock().poll(&mut events);
+
+        let mut state = self.state.lock();
+        let mut motion = HashMap::<&str, i32>::new();
+        for event in &events {
+            match event {
+                DesktopEvent::Key { key, pressed: true } => {
+                    state.held.insert(key.clone());
+                }
+                DesktopEvent::Key {
+                    key,
+                    pressed: false,
+                } => {
+                    state.held.remove(key);
+                }
+                DesktopEvent::Motion { axis, delta } => {
+                    *motion.entry(axis.as_str()).or_default() += delta
+                }
+            }
+        }
+
+        let mut values = HashMap::<String, f32>::new();
+        for key in &self.config.keys {
+            if state.held.contains(&key.key) {
+                *values.entry(key.binding.clone()).or_default() += key.value;
+            }
+        }
+        for axis in &self.config.axes {
+            if let Some(delta) = motion.get(axis.axis.as_str()) {
+                *values.entry(axis.binding.clone()).or_default() += *delta as f32 * axis.scale;
+            }
+        }
+        for value in values.values_mut() {
+            *value = value.clamp(-1., 1.);
+        }
+        state.values = values;
+
+        if let Some(mouse_pose) = &self.config.mouse_pose {
+            let dx = motion.get("REL_X").copied().unwrap_or_default() as f32;
+            let dy = motion.get("REL_Y").copied().unwrap_or_default() as f32;
+            let limit = 89f32.to_radians();
+            state.yaw -= dx * mouse_pose.sensitivity;
+            state.pitch = (state.pitch - dy * mouse_pose.sensitivity).clamp(-limit, limit);
+        }
+    }
+
+    /// The value of a controller input, a binding without a component such as
+    /// `/user/hand/left/input/trigger` reads its `click` or `value` component
+    pub fn value(&self, binding: &str) -> Option<InputValue> {
+        super::input_value(&self.state.lock().values, binding)
+    }
+
+    pub fn is_pressed(&self, binding: &str) -> bool {
+        self.value(binding).map_or(false, InputValue::is_pressed)
+    }
+
+    /// The pose of a hand aimed with the mouse, relative to the head
+    pub fn aim(&self, hand: Hand) -> Option<xr::Posef> {
+        let mouse_pose = self.config.mouse_pose.as_ref()?;
+        if mouse_pose.hand != hand {
+            return None;
+        }
+
+        let state = self.state.lock();
+        let turn = xr::Posef {
+            orientation: math::quat_mul(
+                math::quat_from_axis_angle(math::vec3(0., 1., 0.), state.yaw),
+                math::quat_from_axis_angle(math::vec3(1., 0., 0.), state.pitch),
+            ),
+            position: math::vec3(0., 0., 0.),
+        };
+        Some(math::pose_mul(turn, mouse_pose.offset.to_pose()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    fn key(key: &str, binding: &str, value: f32) -> KeyBinding {
+        KeyBinding {
+            key: key.to_owned(),
+            binding: binding.to_owned(),
+            value,
+        }
+    }
+
+    fn press(source: &SyntheticSource, key: &str, pressed: bool) {
+        source.push(DesktopEvent::Key {
+            key: key.to_owned(),
+            pressed,
+        });
+    }
+
+    fn motion(source: &SyntheticSource, axis: &str, delta: i32) {
+        source.push(DesktopEvent::Motion {
+            axis: axis.to_owned(),
+            delta,
+        });
+    }
+
+    fn assert_close(actual: xr::Vector3f, expected: [f32; 3]) {
+        let [x, y, z] = expected;
+        assert!(
+            (actual.x - x).abs() < 1e-4
+                && (actual.y - y).abs() < 1e-4
+                && (actual.z - z).abs() < 1e-4,
+            "expected {:?}, found {:?}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn keys_press_their_bindings() {
+        let source = SyntheticSource::default();
+        let input = DesktopInput::new(
+            DesktopConfig {
+                keys: vec![
+                    key("KEY_SPACE", "/user/hand/right/input/a/click", 1.),
+                    key("KEY_W", "/user/hand/left/input/thumbstick/y", 0.5),
+                    key("KEY_LEFTSHIFT", "/user/hand/left/input/thumbstick/y", 0.5),
+                ],
+                ..Default::default()
+            },
+            Box::new(source.clone()),
+        );
+
+        press(&source, "KEY_SPACE", true);
+        press(&source, "KEY_W", true);
+        input.sync();
+        assert!(input.is_pressed("/user/hand/right/input/a"));
+        assert_eq!(
+            input.value("/user/hand/left/input/thumbstick"),
+            Some(InputValue::Vector2f([0., 0.5]))
+        );
+
+        //Keys stay held without new events and keys of the same input add up
+        press(&source, "KEY_LEFTSHIFT", true);
+        input.sync();
+        assert!(input.is_pressed("/user/hand/right/input/a/click"));
+        assert_eq!(
+            input.value("/user/hand/left/input/thumbstick/y"),
+            Some(InputValue::Float(1.))
+        );
+
+        press(&source, "KEY_SPACE", false);
+        input.sync();
+        assert!(!input.is_pressed("/user/hand/right/input/a/click"));
+        assert_eq!(input.value("/user/hand/right/input/a/click"), None);
+    }
+
+    #[test]
+    fn axes_scale_the_motion_of_one_frame() {
+        let source = SyntheticSource::default();
+        let input = DesktopInput::new(
+            DesktopConfig {
+                axes: vec![AxisBinding {
+                    axis: "REL_WHEEL".to_owned(),
+                    binding: "/user/hand/right/input/thumbstick/y".to_owned(),
+                    scale: 0.25,
+                }],
+                ..Default::default()
+            },
+            Box::new(source.clone()),
+        );
+
+        motion(&source, "REL_WHEEL", 1);
+        motion(&source, "REL_WHEEL", 2);
+        input.sync();
+        assert_eq!(
+            input.value("/user/hand/right/input/thumbstick/y"),
+            Some(InputValue::Float(0.75))
+        );
+
+        input.sync();
+        assert_eq!(input.value("/user/hand/right/input/thumbstick/y"), None);
+
+        motion(&source, "REL_WHEEL", -10);
+        input.sync();
+        assert_eq!(
+            input.value("/user/hand/right/input/thumbstick/y"),
+            Some(InputValue::Float(-1.))
+        );
+    }
+
+    #[test]
+    fn mouse_turns_the_aim() {
+        let source = SyntheticSource::default();
+        let input = DesktopInput::new(
+            DesktopConfig {
+                mouse_pose: Some(MousePoseConfig {
+                    hand: Hand::Right,
+                    sensitivity: FRAC_PI_2 / 100.,
+                    offset: default_hand_offset(),
+                }),
+                ..Default::default()
+            },
+            Box::new(source.clone()),
+        );
+
+        assert_eq!(input.aim(Hand::Left), None);
+        input.sync();
+        assert_close(input.aim(Hand::Right).unwrap().position, [0., -0.2, -0.4]);
+
+        //Moving the mouse left turns the hand to the left of the head
+        motion(&source, "REL_X", -100);
+        input.sync();
+        assert_close(input.aim(Hand::Right).unwrap().position, [-0.4, -0.2, 0.]);
+
+        //Pitch stops short of straight up or down
+        motion(&source, "REL_Y", 1000);
+        input.sync();
+        let limit = input.aim(Hand::Right).unwrap();
+        motion(&source, "REL_Y", 1000);
+        input.sync();
+        assert_eq!(input.aim(Hand::Right).unwrap(), limit);
+        assert!(limit.position.y < -0.39);
+    }
+}
@@ -0,0 +1,298 @@
+//! The OpenXR functions SuInput's `OpenXRDriver` calls in place of the runtime's. The driver's
+//! actions are read from the runtime as usual and then combined with the layer's input sources
+//! bound to the same physical inputs, so SuInput evaluates a replay, the keyboard and mouse and
+//! the other sources like any physical input.
+
+use std::{
+    collections::HashMap,
+    ffi::{c_char, CStr},
+    sync::Arc,
+};
+
+use dashmap::DashMap;
+use once_cell::sync::OnceCell;
+use openxr::sys::{self as xr, pfn};
+use parking_lot::Mutex;
+
+use crate::{recording::InputValue, wrappers::instance::InnerInstance};
+
+use super::{
+    input_sources::InputSources,
+    reported_state::{ReportedState, ReportedStates},
+    user_paths,
+};
+
+//Every instance is created through the same loader chain
+static NEXT_GET_INSTANCE_PROC_ADDR: OnceCell<pfn::GetInstanceProcAddr> = OnceCell::new();
+static mut DRIVER_INSTANCES: OnceCell<DashMap<xr::Instance, Arc<DriverInstance>>> = OnceCell::new();
+static mut DRIVER_SESSIONS: OnceCell<DashMap<xr::Session, Arc<DriverSession>>> = OnceCell::new();
+
+pub(crate) fn initialize() {
+    unsafe {
+        DRIVER_INSTANCES.get_or_init(DashMap::new);
+        DRIVER_SESSIONS.get_or_init(DashMap::new);
+    }
+}
+
+fn instances<'a>() -> &'a DashMap<xr::Instance, Arc<DriverInstance>> {
+    unsafe { DRIVER_INSTANCES.get().unwrap() }
+}
+
+fn sessions<'a>() -> &'a DashMap<xr::Session, Arc<DriverSession>> {
+    unsafe { DRIVER_SESSIONS.get().unwrap() }
+}
+
+struct DriverInstance {
+    inner: Arc<InnerInstance>,
+    sources: Arc<InputSources>,
+    /// The physical bindings the driver suggested for each of its actions, with their top level
+    /// paths
+    bindings: Mutex<HashMap<xr::Action, Vec<(xr::Path, String)>>>,
+}
+
+struct DriverSession {
+    instance: Arc<DriverInstance>,
+    /// The states given to the driver, changes are found like for the application's actions
+    states: ReportedStates,
+}
+
+impl DriverSession {
+    /// The state of a driver action combined with the input sources, `None` when nothing changes
+    /// the runtime's state
+    fn combine(
+        &self,
+        get_info: &xr::ActionStateGetInfo,
+        runtime: InputValue,
+    ) -> Option<ReportedState> {
+        let sources = &self.instance.sources;
+        if !sources.is_active() {
+            return None;
+        }
+        let bindings = self
+            .instance
+            .bindings
+            .lock()
+            .get(&get_info.action)?
+            .iter()
+            .filter(|(top_level_path, _)| {
+                get_info.subaction_path == xr::Path::NULL
+                    || *top_level_path == get_info.subaction_path
+            })
+            .map(|(_, binding)| binding.clone())
+            .collect::<Vec<_>>();
+
+        let value = sources.combine(runtime, bindings.iter().map(String::as_str));
+        self.states
+            .get_or_read(get_info.action, get_info.subaction_path, || Ok(value))
+            .ok()
+    }
+}
+
+/// Prepares the hooks for an instance before its driver is created
+pub fn register_instance(
+    next_get_instance_proc_addr: pfn::GetInstanceProcAddr,
+    inner: Arc<InnerInstance>,
+    sources: Arc<InputSources>,
+) {
+    NEXT_GET_INSTANCE_PROC_ADDR.get_or_init(|| next_get_instance_proc_addr);
+    instances().insert(
+        inner.instance,
+        Arc::new(DriverInstance {
+            inner,
+            sources,
+            bindings: Default::default(),
+        }),
+    );
+}
+
+pub fn unregister_instance(instance: xr::Instance) {
+    instances().remove(&instance);
+    sessions().retain(|_, session| session.instance.inner.instance != instance);
+}
+
+/// Called before the driver is bound to a session
+pub fn register_session(instance: xr::Instance, session: xr::Session) {
+    if let Some(instance) = instances().get(&instance) {
+        sessions().insert(
+            session,
+            Arc::new(DriverSession {
+                instance: instance.clone(),
+                states: Default::default(),
+            }),
+        );
+    }
+}
+
+/// Called every `xrSyncActions` before SuInput syncs, afterwards every state is combined again
+pub fn sync(session: xr::Session, time: xr::Time) {
+    if let Some(session) = sessions().get(&session) {
+        session.states.sync(time);
+    }
+}
+
+/// The `xrGetInstanceProcAddr` of the instance given to the driver
+pub unsafe extern "system" fn get_instance_proc_addr(
+    instance: xr::Instance,
+    name: *const c_char,
+    function: *mut Option<pfn::VoidFunction>,
+) -> xr::Result {
+    let next = match NEXT_GET_INSTANCE_PROC_ADDR.get() {
+        Some(next) => *next,
+        None => return xr::Result::ERROR_RUNTIME_FAILURE,
+    };
+    let result = next(instance, name, function);
+    if result.into_raw() >= 0 && instance != xr::Instance::NULL {
+        if let Some(hook) = CStr::from_ptr(name)
+            .to_str()
+            .ok()
+            .and_then(|name| hook(name))
+        {
+            *function = Some(hook);
+        }
+    }
+    result
+}
+
+unsafe fn hook(name: &str) -> Option<pfn::VoidFunction> {
+    use std::mem::transmute;
+    use xr::pfn::*;
+    Some(match name {
+        "xrSuggestInteractionProfileBindings" => {
+            transmute(suggest_interaction_profile_bindings as SuggestInteractionProfileBindings)
+        }
+        "xrGetActionStateBoolean" => transmute(get_action_state_boolean as GetActionStateBoolean),
+        "xrGetActionStateFloat" => transmute(get_action_state_float as GetActionStateFloat),
+        "xrGetActionStateVector2f" => {
+            transmute(get_action_state_vector2f as GetActionStateVector2f)
+        }
+        _ => return None,
+    })
+}
+
+unsafe extern "system" fn suggest_interaction_profile_bindings(
+    instance: xr::Instance,
+    suggested_bindings: *const xr::InteractionProfileSuggestedBinding,
+) -> xr::Result {
+    let driver = match instances().get(&instance) {
+        Some(driver) => driver.clone(),
+        None => return xr::Result::ERROR_HANDLE_INVALID,
+    };
+    let result =
+        (driver.inner.core.suggest_interaction_profile_bindings)(instance, suggested_bindings);
+    if result.into_raw() < 0 {
+        return result;
+    }
+
+    let suggested_bindings = &*suggested_bindings;
+    let suggested_bindings = std::slice::from_raw_parts(
+        suggested_bindings.suggested_bindings,
+        suggested_bindings.count_suggested_bindings as usize,
+    );
+    let mut bindings = driver.bindings.lock();
+    for suggested_binding in suggested_bindings {
+        let binding = match driver.inner.path_to_string(suggested_binding.binding) {
+            Ok(binding) => binding,
+            Err(_) => continue,
+        };
+        let top_level_path = match user_paths::top_level_path(&binding)
+            .and_then(|path| driver.inner.string_to_path(path).ok())
+        {
+            Some(path) => path,
+            None => continue,
+        };
+        let action_bindings = bindings.entry(suggested_binding.action).or_default();
+        if !action_bindings
+            .iter()
+            .any(|(_, existing)| *existing == binding)
+        {
+            action_bindings.push((top_level_path, binding));
+        }
+    }
+    result
+}
+
+unsafe extern "system" fn get_action_state_boolean(
+    session: xr::Session,
+    get_info: *const xr::ActionStateGetInfo,
+    state: *mut xr::ActionStateBoolean,
+) -> xr::Result {
+    let driver = match sessions().get(&session) {
+        Some(driver) => driver.clone(),
+        None => return xr::Result::ERROR_HANDLE_INVALID,
+    };
+    let result = (driver.instance.inner.core.get_action_state_boolean)(session, get_info, state);
+    if result.into_raw() < 0 {
+        return result;
+    }
+
+    let state = &mut *state;
+    let runtime =
+        InputValue::Boolean(bool::from(state.is_active) && bool::from(state.current_state));
+    if let Some(combined) = driver.combine(&*get_info, runtime) {
+        state.is_active = true.into();
+        state.current_state = combined.value.is_pressed().into();
+        state.changed_since_last_sync = combined.changed_since_last_sync.into();
+        state.last_change_time = combined.last_change_time;
+    }
+    result
+}
+
+unsafe extern "system" fn get_action_state_float(
+    session: xr::Session,
+    get_info: *const xr::ActionStateGetInfo,
+    state: *mut xr::ActionStateFloat,
+) -> xr::Result {
+    let driver = match sessions().get(&session) {
+        Some(driver) => driver.clone(),
+        None => return xr::Result::ERROR_HANDLE_INVALID,
+    };
+    let result = (driver.instance.inner.core.get_action_state_float)(session, get_info, state);
+    if result.into_raw() < 0 {
+        return result;
+    }
+
+    let state = &mut *state;
+    let runtime = InputValue::Float(if state.is_active.into() {
+        state.current_state
+    } else {
+        0.
+    });
+    if let Some(combined) = driver.combine(&*get_info, runtime) {
+        state.is_active = true.into();
+        state.current_state = combined.value.as_float();
+        state.changed_since_last_sync = combined.changed_since_last_sync.into();
+        state.last_change_time = combined.last_change_time;
+    }
+    result
+}
+
+unsafe extern "system" fn get_action_state_vector2f(
+    session: xr::Session,
+    get_info: *const xr::ActionStateGetInfo,
+    state: *mut xr::ActionStateVector2f,
+) -> xr::Result {
+    let driver = match sessions().get(&session) {
+        Some(driver) => driver.clone(),
+        None => return xr::Result::ERROR_HANDLE_INVALID,
+    };
+    let result = (driver.instance.inner.core.get_action_state_vector2f)(session, get_info, state);
+    if result.into_raw() < 0 {
+        return result;
+    }
+
+    let state = &mut *state;
+    let runtime = InputValue::Vector2f(if state.is_active.into() {
+        [state.current_state.x, state.current_state.y]
+    } else {
+        [0., 0.]
+    });
+    if let Some(combined) = driver.combine(&*get_info, runtime) {
+        if let InputValue::Vector2f([x, y]) = combined.value {
+            state.is_active = true.into();
+            state.current_state = xr::Vector2f { x, y };
+            state.changed_since_last_sync = combined.changed_since_last_sync.into();
+            state.last_change_time = combined.last_change_time;
+        }
+    }
+    result
+}
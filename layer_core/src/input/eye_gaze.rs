@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+use super::user_paths::Hand;
+
+pub const EYE_GAZE_EXTENSION: &str = "XR_EXT_eye_gaze_interaction";
+
+pub const GAZE_POSE: &str = "/user/eyes_ext/input/gaze_ext/pose";
+
+/// Uses the eye gaze in place of a controller's aim and stands in for the eye gaze of
+/// applications on hardware without an eye tracker
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EyeGazeConfig {
+    /// Aims a hand along the gaze while the eye tracker follows the eyes
+    pub aim: Option<GazeAimConfig>,
+    /// What the gaze of the application follows while the runtime cannot locate the eyes
+    pub fallback: GazeFallback,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GazeAimConfig {
+    pub hand: Hand,
+    /// A physical input which pulls the trigger of the hand, e.g. a button of the other hand
+    #[serde(default)]
+    pub trigger: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GazeFallback {
+    /// The gaze looks straight ahead from the head
+    Head,
+    /// The gaze follows the aim of a controller
+    Aim(Hand),
+    /// The gaze is left untracked
+    Disabled,
+}
+
+impl Default for GazeFallback {
+    fn default() -> Self {
+        GazeFallback::Head
+    }
+}
+
+/// The values of the trigger components of `hand` pulled to `value` by the gaze trigger
+pub fn trigger_values(hand: Hand, value: f32) -> impl Iterator<Item = (String, f32)> {
+    let click = f32::from(u8::from(value >= 0.5));
+    [
+        ("trigger/value", value),
+        ("trigger/click", click),
+        ("select/click", click),
+    ]
+    .into_iter()
+    .map(move |(component, value)| (format!("{}/input/{}", hand.user_path(), component), value))
+}
@@ -0,0 +1,643 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use openxr::sys as xr;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::recording::InputValue;
+
+use super::user_paths::Hand;
+
+/// The inputs of a standard gamepad, named like those of the
+/// `/interaction_profiles/microsoft/xbox_controller` profile
+pub const INPUTS: &[&str] = &[
+    "/user/gamepad/input/a/click",
+    "/user/gamepad/input/b/click",
+    "/user/gamepad/input/x/click",
+    "/user/gamepad/input/y/click",
+    "/user/gamepad/input/menu/click",
+    "/user/gamepad/input/view/click",
+    "/user/gamepad/input/dpad_up/click",
+    "/user/gamepad/input/dpad_down/click",
+    "/user/gamepad/input/dpad_left/click",
+    "/user/gamepad/input/dpad_right/click",
+    "/user/gamepad/input/shoulder_left/click",
+    "/user/gamepad/input/shoulder_right/click",
+    "/user/gamepad/input/thumbstick_left/click",
+    "/user/gamepad/input/thumbstick_right/click",
+    "/user/gamepad/input/trigger_left/value",
+    "/user/gamepad/input/trigger_right/value",
+    "/user/gamepad/input/thumbstick_left/x",
+    "/user/gamepad/input/thumbstick_left/y",
+    "/user/gamepad/input/thumbstick_right/x",
+    "/user/gamepad/input/thumbstick_right/y",
+];
+
+/// The controller inputs which stand in for the gamepad's inputs when no gamepad is connected
+pub const CONTROLLER_EMULATION: &[(&str, &str)] = &[
+    (
+        "/user/gamepad/input/a/click",
+        "/user/hand/right/input/a/click",
+    ),
+    (
+        "/user/gamepad/input/b/click",
+        "/user/hand/right/input/b/click",
+    ),
+    (
+        "/user/gamepad/input/x/click",
+        "/user/hand/left/input/x/click",
+    ),
+    (
+        "/user/gamepad/input/y/click",
+        "/user/hand/left/input/y/click",
+    ),
+    (
+        "/user/gamepad/input/menu/click",
+        "/user/hand/left/input/menu/click",
+    ),
+    (
+        "/user/gamepad/input/shoulder_left/click",
+        "/user/hand/left/input/squeeze/value",
+    ),
+    (
+        "/user/gamepad/input/shoulder_right/click",
+        "/user/hand/right/input/squeeze/value",
+    ),
+    (
+        "/user/gamepad/input/thumbstick_left/click",
+        "/user/hand/left/input/thumbstick/click",
+    ),
+    (
+        "/user/gamepad/input/thumbstick_right/click",
+        "/user/hand/right/input/thumbstick/click",
+    ),
+    (
+        "/user/gamepad/input/trigger_left/value",
+        "/user/hand/left/input/trigger/value",
+    ),
+    (
+        "/user/gamepad/input/trigger_right/value",
+        "/user/hand/right/input/trigger/value",
+    ),
+    (
+        "/user/gamepad/input/thumbstick_left/x",
+        "/user/hand/left/input/thumbstick/x",
+    ),
+    (
+        "/user/gamepad/input/thumbstick_left/y",
+        "/user/hand/left/input/thumbstick/y",
+    ),
+    (
+        "/user/gamepad/input/thumbstick_right/x",
+        "/user/hand/right/input/thumbstick/x",
+    ),
+    (
+        "/user/gamepad/input/thumbstick_right/y",
+        "/user/hand/right/input/thumbstick/y",
+    ),
+];
+
+/// Whether `path` is a gamepad input or one of them without its component
+pub fn is_input(path: &str) -> bool {
+    INPUTS.iter().any(|input| {
+        input
+            .strip_prefix(path)
+            .map_or(false, |rest| rest.is_empty() || rest.starts_with('/'))
+    })
+}
+
+/// Reads a gamepad through SDL2, for seated play or as an accessibility aid. Applications which
+/// bind `/user/gamepad` paths themselves receive the gamepad without any bindings here.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GamepadConfig {
+    pub bindings: Vec<GamepadBinding>,
+    pub rumble: Option<RumbleConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GamepadBinding {
+    /// A gamepad input, e.g. `/user/gamepad/input/a/click` or `/user/gamepad/input/thumbstick_left`
+    pub gamepad: String,
+    /// The controller input it drives, e.g. `/user/hand/right/input/a/click`
+    pub binding: String,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+}
+
+fn default_scale() -> f32 {
+    1.
+}
+
+/// Plays haptic feedback of the controllers on the gamepad's motors, the left hand on the low
+/// and the right hand on the high frequency motor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RumbleConfig {
+    /// Only rumble for haptics of this hand
+    #[serde(default)]
+    pub hand: Option<Hand>,
+    #[serde(default = "default_scale")]
+    pub strength: f32,
+}
+
+pub trait GamepadBackend: Send {
+    /// The values of the inputs of the gamepad in use, `None` while none is connected
+    fn state(&mut self) -> Option<HashMap<String, f32>>;
+
+    /// Rumbles the low and high frequency motors with strengths from 0 to 1
+    fn rumble(&mut self, low: f32, high: f32, duration: Duration);
+}
+
+/// A gamepad controlled by hand, e.g. in tests
+#[derive(Debug, Clone, Default)]
+pub struct VirtualGamepad {
+    inner: Arc<Mutex<VirtualGamepadState>>,
+}
+
+#[derive(Debug, Default)]
+struct VirtualGamepadState {
+    connected: bool,
+    values: HashMap<String, f32>,
+    rumble: Option<(f32, f32, Duration)>,
+}
+
+impl VirtualGamepad {
+    pub fn connect(&self) {
+        self.inner.lock().connected = true;
+    }
+
+    pub fn disconnect(&self) {
+        let mut inner = self.inner.lock();
+        inner.connected = false;
+        inner.values.clear();
+    }
+
+    pub fn set(&self, input: &str, value: f32) {
+        self.inner.lock().values.insert(input.to_owned(), value);
+    }
+
+    /// The most recent rumble as low and high frequency strength and duration
+    pub fn last_rumble(&self) -> Option<(f32, f32, Duration)> {
+        self.inner.lock().rumble
+    }
+}
+
+impl GamepadBackend for VirtualGamepad {
+    fn state(&mut self) -> Option<HashMap<String, f32>> {
+        let inner = self.inner.lock();
+        inner.connected.then(|| inner.values.clone())
+    }
+
+    fn rumble(&mut self, low: f32, high: f32, duration: Duration) {
+        self.inner.lock().rumble = Some((low, high, duration));
+    }
+}
+
+#[cfg(feature = "sdl2")]
+mod sdl {
+    use std::{
+        collections::HashMap,
+        sync::{mpsc, Arc, Weak},
+        time::Duration,
+    };
+
+    use log::{info, warn};
+    use parking_lot::Mutex;
+    use sdl2::{
+        controller::{Axis, Button, GameController},
+        event::Event,
+    };
+
+    use super::GamepadBackend;
+
+    const BUTTONS: &[(&str, Button)] = &[
+        ("/user/gamepad/input/a/click", Button::A),
+        ("/user/gamepad/input/b/click", Button::B),
+        ("/user/gamepad/input/x/click", Button::X),
+        ("/user/gamepad/input/y/click", Button::Y),
+        ("/user/gamepad/input/menu/click", Button::Start),
+        ("/user/gamepad/input/view/click", Button::Back),
+        ("/user/gamepad/input/dpad_up/click", Button::DPadUp),
+        ("/user/gamepad/input/dpad_down/click", Button::DPadDown),
+        ("/user/gamepad/input/dpad_left/click", Button::DPadLeft),
+        ("/user/gamepad/input/dpad_right/click", Button::DPadRight),
+        (
+            "/user/gamepad/input/shoulder_left/click",
+            Button::LeftShoulder,
+        ),
+        (
+            "/user/gamepad/input/shoulder_right/click",
+            Button::RightShoulder,
+        ),
+        (
+            "/user/gamepad/input/thumbstick_left/click",
+            Button::LeftStick,
+        ),
+        (
+            "/user/gamepad/input/thumbstick_right/click",
+            Button::RightStick,
+        ),
+    ];
+
+    /// SDL's stick axes point down, OpenXR's point up
+    const AXES: &[(&str, Axis, f32)] = &[
+        (
+            "/user/gamepad/input/trigger_left/value",
+            Axis::TriggerLeft,
+            1.,
+        ),
+        (
+            "/user/gamepad/input/trigger_right/value",
+            Axis::TriggerRight,
+            1.,
+        ),
+        ("/user/gamepad/input/thumbstick_left/x", Axis::LeftX, 1.),
+        ("/user/gamepad/input/thumbstick_left/y", Axis::LeftY, -1.),
+        ("/user/gamepad/input/thumbstick_right/x", Axis::RightX, 1.),
+        ("/user/gamepad/input/thumbstick_right/y", Axis::RightY, -1.),
+    ];
+
+    type State = Arc<Mutex<Option<HashMap<String, f32>>>>;
+
+    /// SDL is only used from the thread it was initialized on, so a thread owns it and shares
+    /// the state of the first connected gamepad
+    pub struct Sdl2Backend {
+        state: State,
+        rumble: mpsc::Sender<(f32, f32, Duration)>,
+    }
+
+    impl Sdl2Backend {
+        pub fn start() -> Self {
+            let state = State::default();
+            let (rumble, commands) = mpsc::channel();
+
+            let weak = Arc::downgrade(&state);
+            std::thread::spawn(move || {
+                if let Err(err) = run(weak, commands) {
+                    warn!("Failed to read gamepads through SDL2: {}", err);
+                }
+            });
+
+            Sdl2Backend { state, rumble }
+        }
+    }
+
+    impl GamepadBackend for Sdl2Backend {
+        fn state(&mut self) -> Option<HashMap<String, f32>> {
+            self.state.lock().clone()
+        }
+
+        fn rumble(&mut self, low: f32, high: f32, duration: Duration) {
+            let _ = self.rumble.send((low, high, duration));
+        }
+    }
+
+    fn run(
+        state: Weak<Mutex<Option<HashMap<String, f32>>>>,
+        commands: mpsc::Receiver<(f32, f32, Duration)>,
+    ) -> Result<(), String> {
+        //The application's window has the focus, not ours
+        sdl2::hint::set("SDL_JOYSTICK_ALLOW_BACKGROUND_EVENTS", "1");
+        let sdl = sdl2::init()?;
+        let subsystem = sdl.game_controller()?;
+        let mut events = sdl.event_pump()?;
+        let mut controller: Option<GameController> = None;
+
+        loop {
+            for event in events.poll_iter() {
+                match event {
+                    Event::ControllerDeviceAdded { which, .. } if controller.is_none() => {
+                        match subsystem.open(which) {
+                            Ok(opened) => {
+                                info!("Using gamepad `{}`", opened.name());
+                                controller = Some(opened);
+                            }
+                            Err(err) => warn!("Failed to open gamepad {}: {}", which, err),
+                        }
+                    }
+                    Event::ControllerDeviceRemoved { which, .. }
+                        if controller
+                            .as_ref()
+                            .map_or(false, |controller| controller.instance_id() == which) =>
+                    {
+                        info!("Gamepad disconnected");
+                        controller = None;
+                    }
+                    _ => {}
+                }
+            }
+
+            while let Ok((low, high, duration)) = commands.try_recv() {
+                if let Some(controller) = &mut controller {
+                    let millis = duration.as_millis().min(u32::MAX as u128) as u32;
+                    if let Err(err) = controller.set_rumble(
+                        (low.clamp(0., 1.) * u16::MAX as f32) as u16,
+                        (high.clamp(0., 1.) * u16::MAX as f32) as u16,
+                        millis,
+                    ) {
+                        warn!("Failed to rumble gamepad: {}", err);
+                    }
+                }
+            }
+
+            let values = controller.as_ref().map(|controller| {
+                let mut values = HashMap::new();
+                for (path, button) in BUTTONS {
+                    values.insert((*path).to_owned(), controller.button(*button) as u8 as f32);
+                }
+                for (path, axis, sign) in AXES {
+                    let value = controller.axis(*axis) as f32 / i16::MAX as f32;
+                    values.insert((*path).to_owned(), (value * sign).clamp(-1., 1.));
+                }
+                values
+            });
+            match state.upgrade() {
+                Some(state) => *state.lock() = values,
+                None => return Ok(()),
+            }
+
+            std::thread::sleep(Duration::from_millis(4));
+        }
+    }
+}
+
+#[cfg(feature = "sdl2")]
+pub use sdl::Sdl2Backend;
+
+/// The gamepads of the system, if the layer was built with SDL2
+pub fn system_backend() -> Option<Box<dyn GamepadBackend>> {
+    #[cfg(feature = "sdl2")]
+    {
+        Some(Box::new(Sdl2Backend::start()))
+    }
+    #[cfg(not(feature = "sdl2"))]
+    {
+        None
+    }
+}
+
+pub struct GamepadInput {
+    config: GamepadConfig,
+    backend: Mutex<Box<dyn GamepadBackend>>,
+    /// The values of the gamepad's inputs and the controller inputs they drive after the last sync
+    values: Mutex<HashMap<String, f32>>,
+    connected: AtomicBool,
+}
+
+impl GamepadInput {
+    pub fn new(config: GamepadConfig, backend: Box<dyn GamepadBackend>) -> Self {
+        Self {
+            config,
+            backend: Mutex::new(backend),
+            values: Default::default(),
+            connected: AtomicBool::new(false),
+        }
+    }
+
+    /// Reads the gamepad for the current `xrSyncActions`
+    pub fn sync(&self) {
+        let gamepad = self.backend.lock().state();
+        self.connected.store(gamepad.is_some(), Ordering::Relaxed);
+        let gamepad = gamepad.unwrap_or_default();
+
+        let mut values = gamepad.clone();
+        for binding in &self.config.bindings {
+            match super::input_value(&gamepad, &binding.gamepad) {
+                Some(InputValue::Vector2f([x, y])) => {
+                    for (component, value) in [("x", x), ("y", y)] {
+                        *values
+                            .entry(format!("{}/{}", binding.binding, component))
+                            .or_default() += value * binding.scale;
+                    }
+                }
+                Some(value) => {
+                    *values.entry(binding.binding.clone()).or_default() +=
+                        value.as_float() * binding.scale;
+                }
+                None => {}
+            }
+        }
+        for value in values.values_mut() {
+            *value = value.clamp(-1., 1.);
+        }
+
+        *self.values.lock() = values;
+    }
+
+    /// Whether a gamepad was connected at the last sync
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    /// The value of a gamepad input or of a controller input driven by the gamepad
+    pub fn value(&self, binding: &str) -> Option<InputValue> {
+        super::input_value(&self.values.lock(), binding)
+    }
+
+    pub fn is_pressed(&self, binding: &str) -> bool {
+        self.value(binding).map_or(false, InputValue::is_pressed)
+    }
+
+    /// Rumbles for a vibration of the given hand, or of both hands if `None`
+    pub fn apply_vibration(&self, hand: Option<Hand>, vibration: &xr::HapticVibration) {
+        let rumble = match &self.config.rumble {
+            Some(rumble) => rumble,
+            None => return,
+        };
+        if let (Some(only), Some(hand)) = (rumble.hand, hand) {
+            if only != hand {
+                return;
+            }
+        }
+
+        let strength = vibration.amplitude.clamp(0., 1.) * rumble.strength;
+        //Negative durations ask for the shortest pulse the device supports
+        let duration = match vibration.duration.as_nanos() {
+            nanos if nanos <= 0 => Duration::from_millis(20),
+            nanos => Duration::from_nanos(nanos as u64),
+        };
+        let (low, high) = match hand {
+            Some(Hand::Left) => (strength, 0.),
+            Some(Hand::Right) => (0., strength),
+            None => (strength, strength),
+        };
+        self.backend.lock().rumble(low, high, duration);
+    }
+
+    pub fn stop_vibration(&self) {
+        if self.config.rumble.is_some() {
+            self.backend.lock().rumble(0., 0., Duration::ZERO);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gamepad_input(
+        gamepad: &VirtualGamepad,
+        bindings: &[(&str, &str, f32)],
+        rumble: Option<RumbleConfig>,
+    ) -> GamepadInput {
+        let bindings = bindings
+            .iter()
+            .map(|(gamepad, binding, scale)| GamepadBinding {
+                gamepad: (*gamepad).to_owned(),
+                binding: (*binding).to_owned(),
+                scale: *scale,
+            })
+            .collect();
+        GamepadInput::new(
+            GamepadConfig { bindings, rumble },
+            Box::new(gamepad.clone()),
+        )
+    }
+
+    fn vibration(amplitude: f32, duration_nanos: i64) -> xr::HapticVibration {
+        xr::HapticVibration {
+            ty: xr::HapticVibration::TYPE,
+            next: std::ptr::null(),
+            duration: xr::Duration::from_nanos(duration_nanos),
+            frequency: 0.,
+            amplitude,
+        }
+    }
+
+    #[test]
+    fn inputs_drive_their_bindings() {
+        let gamepad = VirtualGamepad::default();
+        let input = gamepad_input(
+            &gamepad,
+            &[
+                (
+                    "/user/gamepad/input/a/click",
+                    "/user/hand/right/input/a/click",
+                    1.,
+                ),
+                (
+                    "/user/gamepad/input/thumbstick_left",
+                    "/user/hand/left/input/thumbstick",
+                    0.5,
+                ),
+                (
+                    "/user/gamepad/input/trigger_right/value",
+                    "/user/hand/right/input/trigger/value",
+                    2.,
+                ),
+            ],
+            None,
+        );
+
+        gamepad.connect();
+        gamepad.set("/user/gamepad/input/a/click", 1.);
+        gamepad.set("/user/gamepad/input/thumbstick_left/x", 1.);
+        gamepad.set("/user/gamepad/input/thumbstick_left/y", -0.5);
+        gamepad.set("/user/gamepad/input/trigger_right/value", 0.75);
+        input.sync();
+
+        assert!(input.is_connected());
+        assert!(input.is_pressed("/user/gamepad/input/a"));
+        assert!(input.is_pressed("/user/hand/right/input/a/click"));
+        assert_eq!(
+            input.value("/user/hand/left/input/thumbstick"),
+            Some(InputValue::Vector2f([0.5, -0.25]))
+        );
+        //Scaled values are clamped
+        assert_eq!(
+            input.value("/user/hand/right/input/trigger/value"),
+            Some(InputValue::Float(1.))
+        );
+        assert_eq!(input.value("/user/hand/left/input/x/click"), None);
+    }
+
+    #[test]
+    fn disconnecting_releases_every_input() {
+        let gamepad = VirtualGamepad::default();
+        let input = gamepad_input(
+            &gamepad,
+            &[(
+                "/user/gamepad/input/a/click",
+                "/user/hand/right/input/a/click",
+                1.,
+            )],
+            None,
+        );
+
+        input.sync();
+        assert!(!input.is_connected());
+
+        gamepad.connect();
+        gamepad.set("/user/gamepad/input/a/click", 1.);
+        input.sync();
+        assert!(input.is_pressed("/user/hand/right/input/a/click"));
+
+        gamepad.disconnect();
+        input.sync();
+        assert!(!input.is_connected());
+        assert!(!input.is_pressed("/user/hand/right/input/a/click"));
+        assert_eq!(input.value("/user/gamepad/input/a/click"), None);
+    }
+
+    #[test]
+    fn vibrations_rumble_the_motor_of_their_hand() {
+        let gamepad = VirtualGamepad::default();
+        let input = gamepad_input(
+            &gamepad,
+            &[],
+            Some(RumbleConfig {
+                hand: None,
+                strength: 0.5,
+            }),
+        );
+
+        input.apply_vibration(Some(Hand::Left), &vibration(1., 100_000_000));
+        assert_eq!(
+            gamepad.last_rumble(),
+            Some((0.5, 0., Duration::from_millis(100)))
+        );
+
+        input.apply_vibration(Some(Hand::Right), &vibration(2., 100_000_000));
+        assert_eq!(
+            gamepad.last_rumble(),
+            Some((0., 0.5, Duration::from_millis(100)))
+        );
+
+        //The shortest pulse is requested with a negative duration
+        input.apply_vibration(None, &vibration(0.5, -1));
+        assert_eq!(
+            gamepad.last_rumble(),
+            Some((0.25, 0.25, Duration::from_millis(20)))
+        );
+
+        input.stop_vibration();
+        assert_eq!(gamepad.last_rumble(), Some((0., 0., Duration::ZERO)));
+    }
+
+    #[test]
+    fn vibrations_of_other_hands_are_ignored() {
+        let gamepad = VirtualGamepad::default();
+        let input = gamepad_input(
+            &gamepad,
+            &[],
+            Some(RumbleConfig {
+                hand: Some(Hand::Right),
+                strength: 1.,
+            }),
+        );
+        input.apply_vibration(Some(Hand::Left), &vibration(1., 100_000_000));
+        assert_eq!(gamepad.last_rumble(), None);
+
+        let gamepad = VirtualGamepad::default();
+        let input = gamepad_input(&gamepad, &[], None);
+        input.apply_vibration(None, &vibration(1., 100_000_000));
+        assert_eq!(gamepad.last_rumble(), None);
+    }
+}
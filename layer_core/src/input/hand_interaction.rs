@@ -0,0 +1,70 @@
+use openxr::sys as xr;
+
+use crate::math;
+
+use super::user_paths::Hand;
+
+pub const HAND_INTERACTION_EXTENSION: &str = "XR_EXT_hand_interaction";
+pub const PALM_POSE_EXTENSION: &str = "XR_EXT_palm_pose";
+
+pub const PALM_POSE: &str = "/input/palm_ext/pose";
+const GRIP_POSE: &str = "/input/grip/pose";
+
+/// The controller inputs which drive the inputs of the emulated hand interaction profile
+pub const CONTROLLER_EMULATION: &[(&str, &str)] = &[
+    (
+        "/user/hand/left/input/pinch_ext/value",
+        "/user/hand/left/input/trigger/value",
+    ),
+    (
+        "/user/hand/left/input/aim_activate_ext/value",
+        "/user/hand/left/input/trigger/value",
+    ),
+    (
+        "/user/hand/left/input/grasp_ext/value",
+        "/user/hand/left/input/squeeze/value",
+    ),
+    (
+        "/user/hand/right/input/pinch_ext/value",
+        "/user/hand/right/input/trigger/value",
+    ),
+    (
+        "/user/hand/right/input/aim_activate_ext/value",
+        "/user/hand/right/input/trigger/value",
+    ),
+    (
+        "/user/hand/right/input/grasp_ext/value",
+        "/user/hand/right/input/squeeze/value",
+    ),
+];
+
+/// The inputs telling whether a hand can currently perform the gestures
+pub fn ready_inputs(hand: Hand) -> impl Iterator<Item = String> {
+    ["pinch_ext", "aim_activate_ext", "grasp_ext"]
+        .into_iter()
+        .map(move |input| format!("{}/input/{}/ready_ext", hand.user_path(), input))
+}
+
+/// The grip pose binding the runtime locates instead of a palm pose binding
+pub fn palm_to_grip(binding: &str) -> Option<String> {
+    binding
+        .strip_suffix(PALM_POSE)
+        .map(|user_path| format!("{}{}", user_path, GRIP_POSE))
+}
+
+pub fn is_palm_pose(binding: &str) -> bool {
+    binding.ends_with(PALM_POSE)
+}
+
+/// The palm relative to the grip of a controller. The palm lies on the side of the handle and its
+/// -Z axis follows the straightened fingers, pitched down from the handle.
+pub fn palm_offset(hand: Hand) -> xr::Posef {
+    let right = xr::Posef {
+        orientation: math::quat_from_axis_angle(math::vec3(1., 0., 0.), (-45f32).to_radians()),
+        position: math::vec3(0.03, 0., 0.),
+    };
+    match hand {
+        Hand::Left => math::mirror_x(right),
+        Hand::Right => right,
+    }
+}
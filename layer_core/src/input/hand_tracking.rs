@@ -0,0 +1,469 @@
+use std::collections::HashMap;
+
+use openxr::sys as xr;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::{math, recording::InputValue};
+
+use super::user_paths::Hand;
+
+pub const HAND_TRACKING_EXTENSION: &str = "XR_EXT_hand_tracking";
+
+/// The joints of `XR_HAND_JOINT_SET_DEFAULT_EXT`
+pub const JOINT_COUNT: usize = 26;
+
+const PALM: usize = 0;
+const THUMB_TIP: usize = 5;
+const INDEX_PROXIMAL: usize = 7;
+const INDEX_TIP: usize = 10;
+const MIDDLE_TIP: usize = 15;
+const RING_TIP: usize = 20;
+const LITTLE_TIP: usize = 25;
+
+/// Values from which a derived button counts as clicked
+const CLICK_THRESHOLD: f32 = 0.75;
+
+/// Derives controller inputs from tracked hands while the runtime reports no controller for them,
+/// so applications which only support controllers can be used with bare hands
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HandTrackingConfig {
+    /// Distance in meters between the thumb and index finger tips, driving the trigger
+    pub pinch: GestureRange,
+    /// Mean distance in meters from the middle, ring and little finger tips to the palm, driving
+    /// the squeeze
+    pub fist: GestureRange,
+    /// Pinching while the palm faces the head presses the menu button instead of the trigger
+    pub palm_menu: bool,
+    /// The cosine of the largest angle between the palm and the direction to the head at which
+    /// the palm still faces the head
+    pub palm_facing: f32,
+}
+
+impl Default for HandTrackingConfig {
+    fn default() -> Self {
+        Self {
+            pinch: GestureRange {
+                open: 0.05,
+                closed: 0.015,
+            },
+            fist: GestureRange {
+                open: 0.09,
+                closed: 0.045,
+            },
+            palm_menu: true,
+            palm_facing: 0.7,
+        }
+    }
+}
+
+/// Distances at which a gesture maps to 0 and to 1
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct GestureRange {
+    pub open: f32,
+    pub closed: f32,
+}
+
+impl GestureRange {
+    fn value(&self, distance: f32) -> f32 {
+        if self.open == self.closed {
+            return if distance <= self.closed { 1. } else { 0. };
+        }
+        ((self.open - distance) / (self.open - self.closed)).clamp(0., 1.)
+    }
+}
+
+/// The pose of every joint of a hand relative to the same space, as stored in fixtures
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HandJoints {
+    pub positions: [[f32; 3]; JOINT_COUNT],
+    /// Quaternions in `x, y, z, w` order
+    pub orientations: [[f32; 4]; JOINT_COUNT],
+}
+
+impl HandJoints {
+    /// Takes the joints located by the runtime, `None` unless every joint is fully tracked
+    pub fn from_locations(locations: &[xr::HandJointLocationEXT; JOINT_COUNT]) -> Option<Self> {
+        let valid =
+            xr::SpaceLocationFlags::POSITION_VALID | xr::SpaceLocationFlags::ORIENTATION_VALID;
+        if !locations
+            .iter()
+            .all(|location| location.location_flags.contains(valid))
+        {
+            return None;
+        }
+
+        Some(Self {
+            positions: locations.map(|location| {
+                let position = location.pose.position;
+                [position.x, position.y, position.z]
+            }),
+            orientations: locations.map(|location| {
+                let orientation = location.pose.orientation;
+                [orientation.x, orientation.y, orientation.z, orientation.w]
+            }),
+        })
+    }
+
+    fn position(&self, joint: usize) -> xr::Vector3f {
+        let [x, y, z] = self.positions[joint];
+        math::vec3(x, y, z)
+    }
+
+    fn pose(&self, joint: usize) -> xr::Posef {
+        let [x, y, z, w] = self.orientations[joint];
+        xr::Posef {
+            orientation: math::quat_normalize(xr::Quaternionf { x, y, z, w }),
+            position: self.position(joint),
+        }
+    }
+
+    fn distance(&self, a: usize, b: usize) -> f32 {
+        math::length(math::sub(self.position(a), self.position(b)))
+    }
+
+    /// A grip pose in the space of the joints. The palm joint points its -Z axis towards the
+    /// fingers and its +Y axis out of the back of the hand, the grip points -Z out of the fist
+    /// on the thumb's side and +X into the right and out of the left palm.
+    pub fn grip(&self, hand: Hand) -> xr::Posef {
+        let rotation = match hand {
+            Hand::Left => xr::Quaternionf {
+                x: 0.5,
+                y: -0.5,
+                z: -0.5,
+                w: 0.5,
+            },
+            Hand::Right => xr::Quaternionf {
+                x: 0.5,
+                y: 0.5,
+                z: 0.5,
+                w: 0.5,
+            },
+        };
+        let palm = self.pose(PALM);
+        xr::Posef {
+            orientation: math::quat_mul(palm.orientation, rotation),
+            position: palm.position,
+        }
+    }
+
+    /// A palm pose in the space of the joints, which turns the palm joint to point +X into the
+    /// right and out of the left palm
+    pub fn palm(&self, hand: Hand) -> xr::Posef {
+        let angle = match hand {
+            Hand::Left => -90f32,
+            Hand::Right => 90f32,
+        };
+        let palm = self.pose(PALM);
+        xr::Posef {
+            orientation: math::quat_mul(
+                palm.orientation,
+                math::quat_from_axis_angle(math::vec3(0., 0., 1.), angle.to_radians()),
+            ),
+            position: palm.position,
+        }
+    }
+
+    /// An aim pose in the space of the joints, pointing along the flat hand from the knuckle of
+    /// the index finger
+    pub fn aim(&self) -> xr::Posef {
+        xr::Posef {
+            orientation: self.pose(PALM).orientation,
+            position: self.position(INDEX_PROXIMAL),
+        }
+    }
+}
+
+/// The controller inputs read from one hand
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct HandGestures {
+    pub trigger: f32,
+    pub squeeze: f32,
+    pub menu: bool,
+}
+
+impl HandGestures {
+    /// Reads the gestures of a hand, `head` is the position of the head in the space of the joints
+    pub fn evaluate(config: &HandTrackingConfig, joints: &HandJoints, head: xr::Vector3f) -> Self {
+        let pinch = config.pinch.value(joints.distance(THUMB_TIP, INDEX_TIP));
+        let fist = [MIDDLE_TIP, RING_TIP, LITTLE_TIP]
+            .iter()
+            .map(|tip| joints.distance(*tip, PALM))
+            .sum::<f32>()
+            / 3.;
+        let squeeze = config.fist.value(fist);
+
+        if config.palm_menu && palm_faces(joints, head, config.palm_facing) {
+            Self {
+                trigger: 0.,
+                squeeze,
+                menu: pinch >= CLICK_THRESHOLD,
+            }
+        } else {
+            Self {
+                trigger: pinch,
+                squeeze,
+                menu: false,
+            }
+        }
+    }
+
+    /// The values of the controller components of `hand` as bindings read them
+    pub fn values(&self, hand: Hand) -> impl Iterator<Item = (String, f32)> {
+        let click = |value: f32| f32::from(u8::from(value >= CLICK_THRESHOLD));
+        [
+            ("trigger/value", self.trigger),
+            ("trigger/click", click(self.trigger)),
+            ("select/click", click(self.trigger)),
+            ("squeeze/value", self.squeeze),
+            ("squeeze/click", click(self.squeeze)),
+            ("menu/click", f32::from(u8::from(self.menu))),
+        ]
+        .into_iter()
+        .map(move |(component, value)| (format!("{}/input/{}", hand.user_path(), component), value))
+    }
+}
+
+/// Whether the inside of the palm is turned towards `head`
+fn palm_faces(joints: &HandJoints, head: xr::Vector3f, min_cos: f32) -> bool {
+    let palm = joints.pose(PALM);
+    let to_head = math::sub(head, palm.position);
+    let distance = math::length(to_head);
+    if distance < f32::EPSILON {
+        return false;
+    }
+    let normal = math::rotate(palm.orientation, math::vec3(0., -1., 0.));
+    let cos = (normal.x * to_head.x + normal.y * to_head.y + normal.z * to_head.z) / distance;
+    cos >= min_cos
+}
+
+/// Which pose of a hand an action space follows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandPose {
+    Grip,
+    Aim,
+    /// `pinch_ext` of `XR_EXT_hand_interaction`
+    Pinch,
+    /// `poke_ext` of `XR_EXT_hand_interaction`
+    Poke,
+    /// `palm_ext` of `XR_EXT_palm_pose`
+    Palm,
+}
+
+impl HandPose {
+    pub fn of_binding(binding: &str) -> Self {
+        if binding.ends_with("/aim/pose") {
+            HandPose::Aim
+        } else if binding.ends_with("/pinch_ext/pose") {
+            HandPose::Pinch
+        } else if binding.ends_with("/poke_ext/pose") {
+            HandPose::Poke
+        } else if binding.ends_with("/palm_ext/pose") {
+            HandPose::Palm
+        } else {
+            HandPose::Grip
+        }
+    }
+
+    pub fn from_joints(self, joints: &HandJoints, hand: Hand) -> xr::Posef {
+        match self {
+            HandPose::Grip => joints.grip(hand),
+            HandPose::Aim => joints.aim(),
+            HandPose::Pinch => xr::Posef {
+                orientation: joints.aim().orientation,
+                position: math::lerp(joints.position(THUMB_TIP), joints.position(INDEX_TIP), 0.5),
+            },
+            HandPose::Poke => joints.pose(INDEX_TIP),
+            HandPose::Palm => joints.palm(hand),
+        }
+    }
+}
+
+/// The controller inputs of the tracked hands, updated by the session every `xrSyncActions`
+pub struct HandInput {
+    config: HandTrackingConfig,
+    state: Mutex<HandState>,
+}
+
+#[derive(Default)]
+struct HandState {
+    values: HashMap<String, f32>,
+    /// Hands which stand in for a controller during this sync
+    tracked: Vec<Hand>,
+}
+
+impl HandInput {
+    pub fn new(config: HandTrackingConfig) -> Self {
+        Self {
+            config,
+            state: Default::default(),
+        }
+    }
+
+    /// Sets the joints of a hand relative to the head for the current sync, `None` while the hand
+    /// is not tracked or holds a controller
+    pub fn update(&self, hand: Hand, joints: Option<&HandJoints>) {
+        let mut state = self.state.lock();
+        state
+            .values
+            .retain(|binding, _| Hand::of_path(binding) != Some(hand));
+        state.tracked.retain(|tracked| *tracked != hand);
+
+        if let Some(joints) = joints {
+            let gestures = HandGestures::evaluate(&self.config, joints, math::vec3(0., 0., 0.));
+            state.values.extend(gestures.values(hand));
+            state.tracked.push(hand);
+        }
+    }
+
+    /// Whether the hand stands in for its controller
+    pub fn is_tracked(&self, hand: Hand) -> bool {
+        self.state.lock().tracked.contains(&hand)
+    }
+
+    pub fn value(&self, binding: &str) -> Option<InputValue> {
+        super::input_value(&self.state.lock().values, binding)
+    }
+
+    pub fn is_pressed(&self, binding: &str) -> bool {
+        self.value(binding).map_or(false, InputValue::is_pressed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Below and in front of the head, which the joints are located relative to
+    const PALM_POSITION: [f32; 3] = [0., -0.4, -0.3];
+    const PALM_DOWN: [f32; 4] = [0., 0., 0., 1.];
+    /// Turned half way around the Z axis, so the inside of the palm faces up towards the head
+    const PALM_UP: [f32; 4] = [0., 0., 1., 0.];
+
+    /// Finger tips relative to the palm
+    const OPEN: [(usize, [f32; 3]); 5] = [
+        (THUMB_TIP, [-0.06, 0., -0.05]),
+        (INDEX_TIP, [-0.02, 0., -0.1]),
+        (MIDDLE_TIP, [0., 0., -0.11]),
+        (RING_TIP, [0.02, 0., -0.1]),
+        (LITTLE_TIP, [0.04, 0., -0.085]),
+    ];
+    const PINCH: [(usize, [f32; 3]); 2] = [
+        (THUMB_TIP, [-0.03, 0., -0.08]),
+        (INDEX_TIP, [-0.03, 0., -0.09]),
+    ];
+    const HALF_PINCH: [(usize, [f32; 3]); 2] = [
+        (THUMB_TIP, [-0.03, 0., -0.08]),
+        (INDEX_TIP, [-0.03, 0., -0.1125]),
+    ];
+    const FIST: [(usize, [f32; 3]); 3] = [
+        (MIDDLE_TIP, [0., -0.03, -0.01]),
+        (RING_TIP, [0.01, -0.03, 0.]),
+        (LITTLE_TIP, [0.03, -0.01, 0.]),
+    ];
+
+    /// An open hand with some of its finger tips moved
+    fn fixture(orientation: [f32; 4], tips: &[(usize, [f32; 3])]) -> HandJoints {
+        let mut positions = [PALM_POSITION; JOINT_COUNT];
+        for (joint, offset) in OPEN.iter().chain(tips) {
+            positions[*joint] = [
+                PALM_POSITION[0] + offset[0],
+                PALM_POSITION[1] + offset[1],
+                PALM_POSITION[2] + offset[2],
+            ];
+        }
+        HandJoints {
+            positions,
+            orientations: [orientation; JOINT_COUNT],
+        }
+    }
+
+    fn evaluate(config: &HandTrackingConfig, joints: &HandJoints) -> HandGestures {
+        HandGestures::evaluate(config, joints, math::vec3(0., 0., 0.))
+    }
+
+    #[test]
+    fn open_hand_reads_nothing() {
+        let config = HandTrackingConfig::default();
+        assert_eq!(
+            evaluate(&config, &fixture(PALM_DOWN, &[])),
+            HandGestures::default()
+        );
+    }
+
+    #[test]
+    fn pinch_pulls_the_trigger() {
+        let config = HandTrackingConfig::default();
+        assert_eq!(
+            evaluate(&config, &fixture(PALM_DOWN, &PINCH)),
+            HandGestures {
+                trigger: 1.,
+                squeeze: 0.,
+                menu: false,
+            }
+        );
+
+        let half = evaluate(&config, &fixture(PALM_DOWN, &HALF_PINCH));
+        assert!((half.trigger - 0.5).abs() < 1e-4, "{:?}", half);
+        assert!(!half.menu);
+    }
+
+    #[test]
+    fn fist_squeezes() {
+        let config = HandTrackingConfig::default();
+        let gestures = evaluate(&config, &fixture(PALM_DOWN, &FIST));
+        assert_eq!(gestures.squeeze, 1.);
+        assert_eq!(gestures.trigger, 0.);
+    }
+
+    #[test]
+    fn pinch_towards_the_head_presses_menu() {
+        let config = HandTrackingConfig::default();
+        assert_eq!(
+            evaluate(&config, &fixture(PALM_UP, &PINCH)),
+            HandGestures {
+                trigger: 0.,
+                squeeze: 0.,
+                menu: true,
+            }
+        );
+        //A partial pinch does not click
+        assert!(!evaluate(&config, &fixture(PALM_UP, &HALF_PINCH)).menu);
+
+        let config = HandTrackingConfig {
+            palm_menu: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            evaluate(&config, &fixture(PALM_UP, &PINCH)),
+            HandGestures {
+                trigger: 1.,
+                squeeze: 0.,
+                menu: false,
+            }
+        );
+    }
+
+    #[test]
+    fn gestures_drive_controller_bindings() {
+        let input = HandInput::new(HandTrackingConfig::default());
+        input.update(Hand::Left, Some(&fixture(PALM_DOWN, &PINCH)));
+        input.update(Hand::Right, Some(&fixture(PALM_DOWN, &FIST)));
+
+        assert!(input.is_tracked(Hand::Left));
+        assert!(input.is_pressed("/user/hand/left/input/trigger"));
+        assert!(input.is_pressed("/user/hand/left/input/select/click"));
+        assert_eq!(
+            input.value("/user/hand/left/input/trigger/value"),
+            Some(InputValue::Float(1.))
+        );
+        assert!(!input.is_pressed("/user/hand/left/input/squeeze"));
+        assert!(input.is_pressed("/user/hand/right/input/squeeze/click"));
+
+        input.update(Hand::Left, None);
+        assert!(!input.is_tracked(Hand::Left));
+        assert_eq!(input.value("/user/hand/left/input/trigger/value"), None);
+        assert!(input.is_tracked(Hand::Right));
+    }
+}
@@ -0,0 +1,149 @@
+use once_cell::sync::OnceCell;
+use openxr::sys as xr;
+use parking_lot::Mutex;
+
+use crate::recording::{InputValue, Replay};
+
+use super::{
+    desktop::DesktopInput, gamepad::GamepadInput, hand_tracking::HandInput, reported_state,
+    virtual_device::VirtualInput,
+};
+
+/// Physical input the layer reads besides the runtime's. A replay stands in for the runtime and
+/// every other source, the others add to the runtime's input.
+#[derive(Default)]
+pub struct InputSources {
+    replay: Mutex<Option<Replay>>,
+    /// Keyboard and mouse
+    desktop: OnceCell<DesktopInput>,
+    gamepad: OnceCell<GamepadInput>,
+    /// Controller input derived from tracked hands, added while no controller is held
+    hands: OnceCell<HandInput>,
+    /// The inputs of the profile's virtual devices
+    virtual_input: OnceCell<VirtualInput>,
+}
+
+impl InputSources {
+    pub fn start_replay(&self, replay: Replay) {
+        *self.replay.lock() = Some(replay);
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.replay.lock().is_some()
+    }
+
+    /// Moves the replay on by one sync, returning the predicted display time it was recorded at
+    pub fn advance_replay(&self) -> Option<xr::Time> {
+        self.replay.lock().as_mut()?.advance()
+    }
+
+    pub fn start_desktop(&self, desktop: DesktopInput) {
+        if self.desktop.set(desktop).is_err() {
+            log::warn!("Desktop input was started twice");
+        }
+    }
+
+    pub fn desktop(&self) -> Option<&DesktopInput> {
+        self.desktop.get()
+    }
+
+    pub fn start_gamepad(&self, gamepad: GamepadInput) {
+        if self.gamepad.set(gamepad).is_err() {
+            log::warn!("Gamepad input was started twice");
+        }
+    }
+
+    pub fn gamepad(&self) -> Option<&GamepadInput> {
+        self.gamepad.get()
+    }
+
+    pub fn start_hands(&self, hands: HandInput) {
+        if self.hands.set(hands).is_err() {
+            log::warn!("Hand tracking input was started twice");
+        }
+    }
+
+    pub fn hands(&self) -> Option<&HandInput> {
+        self.hands.get()
+    }
+
+    pub fn start_virtual_input(&self, virtual_input: VirtualInput) {
+        if self.virtual_input.set(virtual_input).is_err() {
+            log::warn!("Virtual input was started twice");
+        }
+    }
+
+    pub fn virtual_input(&self) -> Option<&VirtualInput> {
+        self.virtual_input.get()
+    }
+
+    /// Whether any source changes the runtime's input
+    pub fn is_active(&self) -> bool {
+        self.is_replaying()
+            || self.desktop.get().is_some()
+            || self.gamepad.get().is_some()
+            || self.hands.get().is_some()
+            || self.virtual_input.get().is_some()
+    }
+
+    /// Reads the keyboard, mouse and gamepad for the current `xrSyncActions`
+    pub fn sync(&self) {
+        if let Some(desktop) = self.desktop.get() {
+            desktop.sync();
+        }
+        if let Some(gamepad) = self.gamepad.get() {
+            gamepad.sync();
+        }
+    }
+
+    /// The values the sources give a physical input, only the replayed one while replaying
+    pub fn values(&self, binding: &str) -> Vec<InputValue> {
+        if let Some(replay) = self.replay.lock().as_ref() {
+            return replay.value(binding).into_iter().collect();
+        }
+
+        [
+            self.desktop
+                .get()
+                .and_then(|desktop| desktop.value(binding)),
+            self.gamepad
+                .get()
+                .and_then(|gamepad| gamepad.value(binding)),
+            self.hands.get().and_then(|hands| hands.value(binding)),
+            self.virtual_input
+                .get()
+                .and_then(|virtual_input| virtual_input.value(binding)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// Combines the state the runtime gave an action bound to `bindings` with the values of the
+    /// sources, like the runtimes combine the bindings of an action. A replay replaces the
+    /// runtime's state.
+    pub fn combine<'a>(
+        &self,
+        runtime: InputValue,
+        bindings: impl IntoIterator<Item = &'a str>,
+    ) -> InputValue {
+        let mut values = if self.is_replaying() {
+            Vec::new()
+        } else {
+            vec![runtime]
+        };
+        for binding in bindings {
+            values.extend(self.values(binding));
+        }
+
+        match runtime {
+            InputValue::Boolean(_) => {
+                InputValue::Boolean(values.into_iter().any(InputValue::is_pressed))
+            }
+            InputValue::Float(_) => InputValue::Float(reported_state::combine_float(values)),
+            InputValue::Vector2f(_) => {
+                InputValue::Vector2f(reported_state::combine_vector2f(values))
+            }
+        }
+    }
+}
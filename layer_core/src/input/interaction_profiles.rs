@@ -0,0 +1,360 @@
+use super::{
+    eye_gaze, hand_interaction,
+    user_paths::{self, Hand, EYES, GAMEPAD, LEFT_HAND, RIGHT_HAND},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentType {
+    Boolean,
+    Float,
+    Vector2f,
+    Pose,
+    Vibration,
+}
+
+#[derive(Debug)]
+pub struct Component {
+    /// Limits the component to a single top level path, otherwise it exists on all of them
+    pub user_path: Option<&'static str>,
+    pub path: &'static str,
+    pub ty: ComponentType,
+}
+
+#[derive(Debug)]
+pub struct InteractionProfile {
+    pub path: &'static str,
+    pub user_paths: &'static [&'static str],
+    pub components: &'static [Component],
+}
+
+const HANDS: &[&str] = &[LEFT_HAND, RIGHT_HAND];
+
+const fn both(path: &'static str, ty: ComponentType) -> Component {
+    Component {
+        user_path: None,
+        path,
+        ty,
+    }
+}
+
+const fn left(path: &'static str, ty: ComponentType) -> Component {
+    Component {
+        user_path: Some(LEFT_HAND),
+        path,
+        ty,
+    }
+}
+
+const fn right(path: &'static str, ty: ComponentType) -> Component {
+    Component {
+        user_path: Some(RIGHT_HAND),
+        path,
+        ty,
+    }
+}
+
+use ComponentType::*;
+
+pub const PROFILES: &[InteractionProfile] = &[
+    InteractionProfile {
+        path: "/interaction_profiles/khr/simple_controller",
+        user_paths: HANDS,
+        components: &[
+            both("/input/select/click", Boolean),
+            both("/input/menu/click", Boolean),
+            both("/input/grip/pose", Pose),
+            both("/input/aim/pose", Pose),
+            both("/output/haptic", Vibration),
+        ],
+    },
+    InteractionProfile {
+        path: "/interaction_profiles/oculus/touch_controller",
+        user_paths: HANDS,
+        components: &[
+            left("/input/x/click", Boolean),
+            left("/input/x/touch", Boolean),
+            left("/input/y/click", Boolean),
+            left("/input/y/touch", Boolean),
+            left("/input/menu/click", Boolean),
+            right("/input/a/click", Boolean),
+            right("/input/a/touch", Boolean),
+            right("/input/b/click", Boolean),
+            right("/input/b/touch", Boolean),
+            right("/input/system/click", Boolean),
+            both("/input/squeeze/value", Float),
+            both("/input/trigger/value", Float),
+            both("/input/trigger/touch", Boolean),
+            both("/input/thumbstick", Vector2f),
+            both("/input/thumbstick/x", Float),
+            both("/input/thumbstick/y", Float),
+            both("/input/thumbstick/click", Boolean),
+            both("/input/thumbstick/touch", Boolean),
+            both("/input/thumbrest/touch", Boolean),
+            both("/input/grip/pose", Pose),
+            both("/input/aim/pose", Pose),
+            both("/output/haptic", Vibration),
+        ],
+    },
+    InteractionProfile {
+        path: "/interaction_profiles/valve/index_controller",
+        user_paths: HANDS,
+        components: &[
+            both("/input/system/click", Boolean),
+            both("/input/system/touch", Boolean),
+            both("/input/a/click", Boolean),
+            both("/input/a/touch", Boolean),
+            both("/input/b/click", Boolean),
+            both("/input/b/touch", Boolean),
+            both("/input/squeeze/value", Float),
+            both("/input/squeeze/force", Float),
+            both("/input/trigger/click", Boolean),
+            both("/input/trigger/value", Float),
+            both("/input/trigger/touch", Boolean),
+            both("/input/thumbstick", Vector2f),
+            both("/input/thumbstick/x", Float),
+            both("/input/thumbstick/y", Float),
+            both("/input/thumbstick/click", Boolean),
+            both("/input/thumbstick/touch", Boolean),
+            both("/input/trackpad", Vector2f),
+            both("/input/trackpad/x", Float),
+            both("/input/trackpad/y", Float),
+            both("/input/trackpad/force", Float),
+            both("/input/trackpad/touch", Boolean),
+            both("/input/grip/pose", Pose),
+            both("/input/aim/pose", Pose),
+            both("/output/haptic", Vibration),
+        ],
+    },
+    InteractionProfile {
+        path: "/interaction_profiles/htc/vive_controller",
+        user_paths: HANDS,
+        components: &[
+            both("/input/system/click", Boolean),
+            both("/input/squeeze/click", Boolean),
+            both("/input/menu/click", Boolean),
+            both("/input/trigger/click", Boolean),
+            both("/input/trigger/value", Float),
+            both("/input/trackpad", Vector2f),
+            both("/input/trackpad/x", Float),
+            both("/input/trackpad/y", Float),
+            both("/input/trackpad/click", Boolean),
+            both("/input/trackpad/touch", Boolean),
+            both("/input/grip/pose", Pose),
+            both("/input/aim/pose", Pose),
+            both("/output/haptic", Vibration),
+        ],
+    },
+    InteractionProfile {
+        path: "/interaction_profiles/microsoft/motion_controller",
+        user_paths: HANDS,
+        components: &[
+            both("/input/menu/click", Boolean),
+            both("/input/squeeze/click", Boolean),
+            both("/input/trigger/value", Float),
+            both("/input/thumbstick", Vector2f),
+            both("/input/thumbstick/x", Float),
+            both("/input/thumbstick/y", Float),
+            both("/input/thumbstick/click", Boolean),
+            both("/input/trackpad", Vector2f),
+            both("/input/trackpad/x", Float),
+            both("/input/trackpad/y", Float),
+            both("/input/trackpad/click", Boolean),
+            both("/input/trackpad/touch", Boolean),
+            both("/input/grip/pose", Pose),
+            both("/input/aim/pose", Pose),
+            both("/output/haptic", Vibration),
+        ],
+    },
+    InteractionProfile {
+        path: "/interaction_profiles/hp/mixed_reality_controller",
+        user_paths: HANDS,
+        components: &[
+            left("/input/x/click", Boolean),
+            left("/input/y/click", Boolean),
+            right("/input/a/click", Boolean),
+            right("/input/b/click", Boolean),
+            both("/input/menu/click", Boolean),
+            both("/input/squeeze/value", Float),
+            both("/input/trigger/value", Float),
+            both("/input/thumbstick", Vector2f),
+            both("/input/thumbstick/x", Float),
+            both("/input/thumbstick/y", Float),
+            both("/input/thumbstick/click", Boolean),
+            both("/input/grip/pose", Pose),
+            both("/input/aim/pose", Pose),
+            both("/output/haptic", Vibration),
+        ],
+    },
+];
+
+pub const XBOX_CONTROLLER: &str = "/interaction_profiles/microsoft/xbox_controller";
+pub const HAND_INTERACTION: &str = "/interaction_profiles/ext/hand_interaction_ext";
+pub const EYE_GAZE_INTERACTION: &str = "/interaction_profiles/ext/eye_gaze_interaction";
+
+/// Extensions the layer implements itself when the runtime lacks them
+pub const EMULATED_EXTENSIONS: &[&str] = &[
+    hand_interaction::HAND_INTERACTION_EXTENSION,
+    hand_interaction::PALM_POSE_EXTENSION,
+    eye_gaze::EYE_GAZE_EXTENSION,
+];
+
+/// Profiles the layer evaluates itself instead of the runtime, from whichever device is available.
+/// The profiles of extensions are only emulated when the runtime lacks the extension.
+pub const EMULATED: &[InteractionProfile] = &[
+    InteractionProfile {
+        path: XBOX_CONTROLLER,
+        user_paths: &[GAMEPAD],
+        components: &[
+            both("/input/menu/click", Boolean),
+            both("/input/view/click", Boolean),
+            both("/input/a/click", Boolean),
+            both("/input/b/click", Boolean),
+            both("/input/x/click", Boolean),
+            both("/input/y/click", Boolean),
+            both("/input/dpad_down/click", Boolean),
+            both("/input/dpad_right/click", Boolean),
+            both("/input/dpad_up/click", Boolean),
+            both("/input/dpad_left/click", Boolean),
+            both("/input/shoulder_left/click", Boolean),
+            both("/input/shoulder_right/click", Boolean),
+            both("/input/thumbstick_left/click", Boolean),
+            both("/input/thumbstick_right/click", Boolean),
+            both("/input/trigger_left/value", Float),
+            both("/input/trigger_right/value", Float),
+            both("/input/thumbstick_left", Vector2f),
+            both("/input/thumbstick_left/x", Float),
+            both("/input/thumbstick_left/y", Float),
+            both("/input/thumbstick_right", Vector2f),
+            both("/input/thumbstick_right/x", Float),
+            both("/input/thumbstick_right/y", Float),
+            both("/output/haptic_left", Vibration),
+            both("/output/haptic_right", Vibration),
+            both("/output/haptic_left_trigger", Vibration),
+            both("/output/haptic_right_trigger", Vibration),
+        ],
+    },
+    InteractionProfile {
+        path: HAND_INTERACTION,
+        user_paths: HANDS,
+        components: &[
+            both("/input/aim/pose", Pose),
+            both("/input/grip/pose", Pose),
+            both("/input/pinch_ext/pose", Pose),
+            both("/input/poke_ext/pose", Pose),
+            both("/input/palm_ext/pose", Pose),
+            both("/input/pinch_ext/value", Float),
+            both("/input/pinch_ext/ready_ext", Boolean),
+            both("/input/aim_activate_ext/value", Float),
+            both("/input/aim_activate_ext/ready_ext", Boolean),
+            both("/input/grasp_ext/value", Float),
+            both("/input/grasp_ext/ready_ext", Boolean),
+        ],
+    },
+    InteractionProfile {
+        path: EYE_GAZE_INTERACTION,
+        user_paths: &[EYES],
+        components: &[both("/input/gaze_ext/pose", Pose)],
+    },
+];
+
+/// The runtime's profiles followed by the emulated ones
+pub fn all() -> impl Iterator<Item = &'static InteractionProfile> {
+    PROFILES.iter().chain(EMULATED)
+}
+
+pub fn get(path: &str) -> Option<&'static InteractionProfile> {
+    all().find(|profile| profile.path == path)
+}
+
+pub fn is_emulated(path: &str) -> bool {
+    EMULATED.iter().any(|profile| profile.path == path)
+}
+
+/// The extension which defines an emulated profile, if the runtime may support it natively
+pub fn extension(path: &str) -> Option<&'static str> {
+    match path {
+        HAND_INTERACTION => Some(hand_interaction::HAND_INTERACTION_EXTENSION),
+        EYE_GAZE_INTERACTION => Some(eye_gaze::EYE_GAZE_EXTENSION),
+        _ => None,
+    }
+}
+
+/// Whether a profile describes a device besides the hands, so the runtime's choice of profile for
+/// the hands does not depend on it
+pub fn is_handless(profile: &InteractionProfile) -> bool {
+    !profile.user_paths.contains(&LEFT_HAND) && !profile.user_paths.contains(&RIGHT_HAND)
+}
+
+impl Component {
+    pub fn exists_on(&self, user_path: &str) -> bool {
+        self.user_path.map_or(true, |path| path == user_path)
+    }
+}
+
+impl InteractionProfile {
+    /// Finds the component a full binding path such as `/user/hand/left/input/trigger/value` refers to
+    pub fn component(&self, binding: &str) -> Option<&'static Component> {
+        let (user_path, path) = self
+            .user_paths
+            .iter()
+            .find_map(|user_path| Some((*user_path, binding.strip_prefix(user_path)?)))?;
+
+        self.components
+            .iter()
+            .find(|component| component.path == path && component.exists_on(user_path))
+    }
+
+    /// Whether a binding path is accepted by the profile, this includes identifier paths such as
+    /// `/input/trigger` which the runtime resolves to one of its components
+    pub fn has_binding(&self, binding: &str) -> bool {
+        self.user_paths.iter().any(|user_path| {
+            let path = match binding.strip_prefix(user_path) {
+                Some(path) => path,
+                None => return false,
+            };
+            self.components.iter().any(|component| {
+                component.exists_on(user_path)
+                    && (component.path == path
+                        || component
+                            .path
+                            .strip_prefix(path)
+                            .map_or(false, |rest| rest.starts_with('/')))
+            })
+        })
+    }
+
+    /// Moves a binding of one hand onto `hand`. Components only one hand has are paired with the
+    /// other hand's in the order they are listed, e.g. `/input/x/click` of the left Touch
+    /// controller with `/input/a/click` of the right one. `None` if there is no counterpart.
+    pub fn binding_on_hand(&self, binding: &str, hand: Hand) -> Option<String> {
+        let from = Hand::of_path(binding)?;
+        let path = user_paths::strip_user_path(binding, from.user_path())?;
+        let moved = format!("{}{}", hand.user_path(), path);
+        if self.has_binding(&moved) {
+            return Some(moved);
+        }
+
+        let limited_to = |hand: Hand| {
+            self.components
+                .iter()
+                .filter(move |component| component.user_path == Some(hand.user_path()))
+        };
+        //Identifiers such as `/input/x` keep the part of the component they leave out
+        let (index, suffix) = limited_to(from).enumerate().find_map(|(i, component)| {
+            let suffix = component.path.strip_prefix(path)?;
+            (suffix.is_empty() || suffix.starts_with('/')).then_some((i, suffix))
+        })?;
+        let counterpart = limited_to(hand).nth(index)?.path.strip_suffix(suffix)?;
+        Some(format!("{}{}", hand.user_path(), counterpart))
+    }
+
+    /// Every full binding path of the profile's components
+    pub fn binding_paths(&self) -> impl Iterator<Item = (String, &'static Component)> + '_ {
+        self.user_paths.iter().flat_map(move |user_path| {
+            self.components
+                .iter()
+                .filter(move |component| component.exists_on(user_path))
+                .map(move |component| (format!("{}{}", user_path, component.path), component))
+        })
+    }
+}
@@ -0,0 +1,322 @@
+use log::warn;
+use openxr::sys as xr;
+
+use crate::{profile::RemapProfile, wrappers::instance::InnerInstance};
+
+use super::{
+    advanced_binding::BindingKind,
+    eye_gaze, gamepad, hand_interaction,
+    hand_tracking::HandPose,
+    interaction_profiles::{self, ComponentType},
+    native_actions::NativeActions,
+    user_paths::Hand,
+    virtual_device::VirtualSource,
+};
+
+/// Physical inputs reserved by the layer for its own features
+#[derive(Debug, Default)]
+pub struct LayerInputs {
+    pub one_handed_switch: Option<xr::Action>,
+    pub playspace_mover: Option<PlayspaceMoverInputs>,
+    /// One action for every button and trigger of the known controllers, used to capture the input
+    /// the user wants to bind
+    pub capture: Vec<(xr::Action, String)>,
+    /// The actions reading the inputs of each of the profile's advanced bindings, in the order of
+    /// `BindingKind::inputs`
+    pub advanced_bindings: Vec<Vec<(xr::Action, xr::ActionType)>>,
+    /// One action for every input of the known controllers except poses, read while recording
+    pub physical: Vec<(xr::Action, xr::ActionType, String)>,
+    /// One action for every controller input standing in for a gamepad input, with the gamepad
+    /// input it emulates
+    pub gamepad_emulation: Vec<(xr::Action, xr::ActionType, &'static str)>,
+    /// Like `gamepad_emulation` for the inputs of the emulated hand interaction profile
+    pub hand_interaction: Vec<(xr::Action, xr::ActionType, &'static str)>,
+    /// The grip and aim poses of both hands, located for the poses of emulated profiles
+    pub hand_poses: Vec<(Hand, HandPose, xr::Action)>,
+    /// The eye gaze, located for the hand aimed by the eyes
+    pub gaze_pose: Option<xr::Action>,
+    /// The input pulling the trigger of the hand aimed by the eyes
+    pub gaze_trigger: Option<xr::Action>,
+    /// The components of the virtual devices computed from other inputs
+    pub virtual_components: Vec<VirtualComponentInputs>,
+}
+
+#[derive(Debug)]
+pub struct VirtualComponentInputs {
+    pub binding: String,
+    pub source: VirtualSource,
+    /// The actions reading the inputs of the source, booleans for combos and floats for axes
+    pub inputs: Vec<xr::Action>,
+}
+
+#[derive(Debug)]
+pub struct PlayspaceMoverInputs {
+    pub grab: xr::Action,
+    /// The grip pose of the hand the grab button belongs to
+    pub grip: xr::Action,
+}
+
+impl LayerInputs {
+    pub fn create(
+        profile: &RemapProfile,
+        inner: &InnerInstance,
+        native_actions: &NativeActions,
+    ) -> openxr::Result<Self> {
+        let one_handed_switch = match &profile.one_handed {
+            Some(one_handed) => Some(native_actions.create_layer_action(
+                inner,
+                "one_handed_switch",
+                xr::ActionType::BOOLEAN_INPUT,
+                &[one_handed.switch.binding()],
+            )?),
+            None => None,
+        };
+
+        let playspace_mover = match &profile.playspace_mover {
+            Some(playspace_mover) => match Hand::of_path(&playspace_mover.binding) {
+                Some(hand) => Some(PlayspaceMoverInputs {
+                    grab: native_actions.create_layer_action(
+                        inner,
+                        "playspace_grab",
+                        xr::ActionType::BOOLEAN_INPUT,
+                        &[&playspace_mover.binding],
+                    )?,
+                    grip: native_actions.create_layer_action(
+                        inner,
+                        "playspace_grip",
+                        xr::ActionType::POSE_INPUT,
+                        &[&format!("{}/input/grip/pose", hand.user_path())],
+                    )?,
+                }),
+                None => {
+                    warn!(
+                        "Playspace mover binding `{}` does not belong to a hand",
+                        playspace_mover.binding
+                    );
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let advanced_bindings = profile
+            .advanced_bindings
+            .iter()
+            .enumerate()
+            .map(|(i, advanced)| {
+                advanced
+                    .kind
+                    .inputs()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(j, input)| {
+                        let action_type = match (&advanced.kind, j) {
+                            (BindingKind::Threshold { .. }, _) => xr::ActionType::FLOAT_INPUT,
+                            (BindingKind::Dpad { .. }, 0) => xr::ActionType::VECTOR2F_INPUT,
+                            _ => xr::ActionType::BOOLEAN_INPUT,
+                        };
+                        let action = native_actions.create_profile_layer_action(
+                            inner,
+                            &format!("advanced_{}_{}", i, j),
+                            action_type,
+                            Some(&advanced.interaction_profile),
+                            &[input],
+                        )?;
+                        Ok((action, action_type))
+                    })
+                    .collect::<openxr::Result<Vec<_>>>()
+            })
+            .collect::<openxr::Result<Vec<_>>>()?;
+
+        let reserved = profile.reserved_bindings();
+        let mut capture_bindings = interaction_profiles::PROFILES
+            .iter()
+            .flat_map(|profile| profile.binding_paths())
+            .filter(|(binding, component)| {
+                matches!(component.ty, ComponentType::Boolean | ComponentType::Float)
+                    //Stick axes drift too easily to be captured
+                    && !binding.ends_with("/x")
+                    && !binding.ends_with("/y")
+                    && !reserved.contains(&binding.as_str())
+            })
+            .map(|(binding, _)| binding)
+            .collect::<Vec<_>>();
+        capture_bindings.sort();
+        capture_bindings.dedup();
+
+        let capture = capture_bindings
+            .into_iter()
+            .enumerate()
+            .map(|(i, binding)| {
+                let action = native_actions.create_layer_action(
+                    inner,
+                    &format!("capture_{}", i),
+                    xr::ActionType::BOOLEAN_INPUT,
+                    &[&binding],
+                )?;
+                Ok((action, binding))
+            })
+            .collect::<openxr::Result<Vec<_>>>()?;
+
+        let mut physical_bindings = interaction_profiles::PROFILES
+            .iter()
+            .flat_map(|profile| profile.binding_paths())
+            .filter_map(|(binding, component)| {
+                let action_type = match component.ty {
+                    ComponentType::Boolean => xr::ActionType::BOOLEAN_INPUT,
+                    ComponentType::Float => xr::ActionType::FLOAT_INPUT,
+                    ComponentType::Vector2f => xr::ActionType::VECTOR2F_INPUT,
+                    ComponentType::Pose | ComponentType::Vibration => return None,
+                };
+                Some((binding, action_type))
+            })
+            .collect::<Vec<_>>();
+        physical_bindings.sort_by(|a, b| a.0.cmp(&b.0));
+        physical_bindings.dedup_by(|a, b| a.0 == b.0);
+
+        let physical = physical_bindings
+            .into_iter()
+            .enumerate()
+            .map(|(i, (binding, action_type))| {
+                let action = native_actions.create_layer_action(
+                    inner,
+                    &format!("physical_{}", i),
+                    action_type,
+                    &[&binding],
+                )?;
+                Ok((action, action_type, binding))
+            })
+            .collect::<openxr::Result<Vec<_>>>()?;
+
+        let gamepad_emulation = emulation_actions(
+            inner,
+            native_actions,
+            "gamepad",
+            gamepad::CONTROLLER_EMULATION,
+        )?;
+        let hand_interaction = emulation_actions(
+            inner,
+            native_actions,
+            "hand_interaction",
+            hand_interaction::CONTROLLER_EMULATION,
+        )?;
+
+        let mut hand_poses = Vec::with_capacity(4);
+        for (hand, side) in [(Hand::Left, "left"), (Hand::Right, "right")] {
+            for (pose, component) in [(HandPose::Grip, "grip"), (HandPose::Aim, "aim")] {
+                let action = native_actions.create_layer_action(
+                    inner,
+                    &format!("{}_{}", component, side),
+                    xr::ActionType::POSE_INPUT,
+                    &[&format!("{}/input/{}/pose", hand.user_path(), component)],
+                )?;
+                hand_poses.push((hand, pose, action));
+            }
+        }
+
+        let gaze_aim = profile
+            .eye_gaze
+            .as_ref()
+            .and_then(|eye_gaze| eye_gaze.aim.as_ref());
+        let gaze_pose = match gaze_aim {
+            Some(_) => Some(native_actions.create_layer_action(
+                inner,
+                "gaze_pose",
+                xr::ActionType::POSE_INPUT,
+                &[eye_gaze::GAZE_POSE],
+            )?),
+            None => None,
+        };
+        let gaze_trigger = match gaze_aim.and_then(|aim| aim.trigger.as_ref()) {
+            Some(trigger) => Some(native_actions.create_layer_action(
+                inner,
+                "gaze_trigger",
+                xr::ActionType::FLOAT_INPUT,
+                &[trigger],
+            )?),
+            None => None,
+        };
+
+        let mut virtual_components = Vec::new();
+        for (binding, source) in profile
+            .virtual_devices
+            .iter()
+            .flat_map(|device| device.bindings())
+        {
+            let (action_type, inputs) = match source {
+                VirtualSource::External => continue,
+                VirtualSource::Axis { inputs } => (
+                    xr::ActionType::FLOAT_INPUT,
+                    inputs
+                        .iter()
+                        .map(|input| input.binding.as_str())
+                        .collect::<Vec<_>>(),
+                ),
+                VirtualSource::Combo { inputs } => (
+                    xr::ActionType::BOOLEAN_INPUT,
+                    inputs.iter().map(String::as_str).collect(),
+                ),
+            };
+            let index = virtual_components.len();
+            let inputs = inputs
+                .into_iter()
+                .enumerate()
+                .map(|(i, input)| {
+                    native_actions.create_layer_action(
+                        inner,
+                        &format!("virtual_{}_{}", index, i),
+                        action_type,
+                        &[input],
+                    )
+                })
+                .collect::<openxr::Result<Vec<_>>>()?;
+            virtual_components.push(VirtualComponentInputs {
+                binding,
+                source: source.clone(),
+                inputs,
+            });
+        }
+
+        Ok(Self {
+            one_handed_switch,
+            playspace_mover,
+            capture,
+            advanced_bindings,
+            physical,
+            gamepad_emulation,
+            hand_interaction,
+            hand_poses,
+            gaze_pose,
+            gaze_trigger,
+            virtual_components,
+        })
+    }
+}
+
+/// Creates an action for every controller input of an emulation table, reading booleans from
+/// `/click` components and floats from the others
+fn emulation_actions(
+    inner: &InnerInstance,
+    native_actions: &NativeActions,
+    name: &str,
+    table: &[(&'static str, &'static str)],
+) -> openxr::Result<Vec<(xr::Action, xr::ActionType, &'static str)>> {
+    table
+        .iter()
+        .enumerate()
+        .map(|(i, (emulated, controller))| {
+            let action_type = if controller.ends_with("/click") {
+                xr::ActionType::BOOLEAN_INPUT
+            } else {
+                xr::ActionType::FLOAT_INPUT
+            };
+            let action = native_actions.create_layer_action(
+                inner,
+                &format!("{}_{}", name, i),
+                action_type,
+                &[controller],
+            )?;
+            Ok((action, action_type, *emulated))
+        })
+        .collect()
+}
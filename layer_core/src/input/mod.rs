@@ -1,8 +1,34 @@
+use std::collections::HashMap;
+
 use openxr::Instance;
 use openxr_driver::OpenXRDriver;
 use suinput::{instance::SuInstance, SuInputRuntime};
 
+use crate::recording::InputValue;
+
+pub mod action_override;
+pub mod advanced_binding;
+pub mod behavior;
+pub mod binding_analysis;
+pub mod capture;
+pub mod desktop;
+pub mod driver_hooks;
+pub mod eye_gaze;
+pub mod gamepad;
+pub mod hand_interaction;
+pub mod hand_tracking;
+pub mod input_sources;
+pub mod interaction_profiles;
+pub mod layer_inputs;
+pub mod native_actions;
+pub mod one_handed;
+pub mod playspace;
+pub mod pose_filter;
+pub mod reported_state;
 pub mod suggested_bindings;
+pub mod translation;
+pub mod user_paths;
+pub mod virtual_device;
 
 pub fn create(instance: Instance) -> (SuInputRuntime, SuInstance, OpenXRDriver) {
     let runtime = suinput::load_runtime();
@@ -13,3 +39,30 @@ pub fn create(instance: Instance) -> (SuInputRuntime, SuInstance, OpenXRDriver)
 
     (runtime, instance, driver)
 }
+
+/// Reads a physical input from the values of its float components. A binding without a component
+/// such as `/user/hand/left/input/trigger` reads its `click` or `value` component and one of a
+/// stick combines its `x` and `y` components.
+pub fn input_value(values: &HashMap<String, f32>, binding: &str) -> Option<InputValue> {
+    let float = |binding: &str| {
+        values.get(binding).copied().or_else(|| {
+            ["click", "value"]
+                .iter()
+                .find_map(|component| values.get(&format!("{}/{}", binding, component)))
+                .copied()
+        })
+    };
+
+    match (
+        float(binding),
+        float(&format!("{}/x", binding)),
+        float(&format!("{}/y", binding)),
+    ) {
+        (Some(value), _, _) => Some(InputValue::Float(value)),
+        (None, None, None) => None,
+        (None, x, y) => Some(InputValue::Vector2f([
+            x.unwrap_or_default(),
+            y.unwrap_or_default(),
+        ])),
+    }
+}
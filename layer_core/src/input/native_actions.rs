@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+
+use dashmap::DashMap;
+use once_cell::sync::OnceCell;
+use openxr::sys as xr;
+use parking_lot::Mutex;
+
+use crate::{
+    ffi_helpers::place_cstr, recording::InputValue, wrappers::instance::InnerInstance, ToResult,
+};
+
+use super::{interaction_profiles, user_paths::Hand};
+
+const ACTION_SET_NAME: &str = "suinput_layer";
+const LOCALIZED_ACTION_SET_NAME: &str = "SuInput Layer";
+
+/// Actions the layer creates on the runtime below us for the input types SuInput does not handle
+/// (poses and haptics) and for the layer's own inputs
+#[derive(Default)]
+pub struct NativeActions {
+    action_set: OnceCell<xr::ActionSet>,
+    actions: DashMap<xr::Action, NativeAction>,
+    /// Actions used by the layer itself, the physical bindings they should receive and the
+    /// interaction profile they are limited to
+    layer_bindings: Mutex<Vec<(xr::Action, String, Option<String>)>>,
+    /// The translated bindings of the application's actions for each interaction profile
+    bindings: Mutex<HashMap<String, Vec<(xr::Action, String)>>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NativeAction {
+    pub handle: xr::Action,
+    /// Pairs of the subaction paths the application uses and the subaction paths used by the runtime
+    pub sub_action_paths: Vec<(xr::Path, xr::Path)>,
+}
+
+impl NativeAction {
+    pub fn physical_sub_action_path(&self, sub_action_path: xr::Path) -> Option<xr::Path> {
+        if sub_action_path == xr::Path::NULL {
+            return Some(xr::Path::NULL);
+        }
+        self.sub_action_paths
+            .iter()
+            .find(|(path, _)| *path == sub_action_path)
+            .map(|(_, physical)| *physical)
+    }
+}
+
+impl NativeActions {
+    pub fn action_set(&self) -> Option<xr::ActionSet> {
+        self.action_set.get().copied()
+    }
+
+    pub fn get(&self, layer_action: xr::Action) -> Option<NativeAction> {
+        self.actions.get(&layer_action).map(|action| action.clone())
+    }
+
+    fn get_or_create_action_set(&self, inner: &InnerInstance) -> openxr::Result<xr::ActionSet> {
+        self.action_set
+            .get_or_try_init(|| unsafe {
+                let mut create_info = xr::ActionSetCreateInfo {
+                    ty: xr::ActionSetCreateInfo::TYPE,
+                    next: std::ptr::null(),
+                    action_set_name: [0; xr::MAX_ACTION_SET_NAME_SIZE],
+                    localized_action_set_name: [0; xr::MAX_LOCALIZED_ACTION_SET_NAME_SIZE],
+                    priority: 0,
+                };
+                place_cstr(&mut create_info.action_set_name, ACTION_SET_NAME);
+                place_cstr(
+                    &mut create_info.localized_action_set_name,
+                    LOCALIZED_ACTION_SET_NAME,
+                );
+
+                let mut action_set = xr::ActionSet::NULL;
+                (inner.core.create_action_set)(inner.instance, &create_info, &mut action_set)
+                    .result()?;
+                Ok(action_set)
+            })
+            .copied()
+    }
+
+    fn create_native_action(
+        &self,
+        inner: &InnerInstance,
+        name: &str,
+        action_type: xr::ActionType,
+        sub_action_paths: &[xr::Path],
+    ) -> openxr::Result<xr::Action> {
+        let action_set = self.get_or_create_action_set(inner)?;
+
+        let mut create_info = xr::ActionCreateInfo {
+            ty: xr::ActionCreateInfo::TYPE,
+            next: std::ptr::null(),
+            action_name: [0; xr::MAX_ACTION_NAME_SIZE],
+            action_type,
+            count_subaction_paths: sub_action_paths.len() as u32,
+            subaction_paths: sub_action_paths.as_ptr(),
+            localized_action_name: [0; xr::MAX_LOCALIZED_ACTION_NAME_SIZE],
+        };
+        place_cstr(&mut create_info.action_name, name);
+        place_cstr(&mut create_info.localized_action_name, name);
+
+        let mut handle = xr::Action::NULL;
+        unsafe { (inner.core.create_action)(action_set, &create_info, &mut handle) }.result()?;
+        Ok(handle)
+    }
+
+    /// Mirrors a pose or haptic action of the application onto the runtime
+    pub fn create_action(
+        &self,
+        inner: &InnerInstance,
+        layer_action: xr::Action,
+        action_type: xr::ActionType,
+        sub_action_paths: Vec<(xr::Path, xr::Path)>,
+    ) -> openxr::Result<()> {
+        //Action names must be unique within the set so the layer handle is used rather than the application's name
+        let name = format!("action_{}", layer_action.into_raw());
+
+        //Several subaction paths of the application may be folded onto the same physical one
+        let mut physical_sub_action_paths = Vec::with_capacity(sub_action_paths.len());
+        for (_, physical) in &sub_action_paths {
+            if !physical_sub_action_paths.contains(physical) {
+                physical_sub_action_paths.push(*physical);
+            }
+        }
+
+        let handle =
+            self.create_native_action(inner, &name, action_type, &physical_sub_action_paths)?;
+
+        self.actions.insert(
+            layer_action,
+            NativeAction {
+                handle,
+                sub_action_paths,
+            },
+        );
+
+        Ok(())
+    }
+    pub fn set_bindings(&self, interaction_profile: &str, bindings: Vec<(xr::Action, String)>) {
+        self.bindings
+            .lock()
+            .insert(interaction_profile.to_owned(), bindings);
+    }
+
+    pub fn bindings_for(&self, interaction_profile: &str) -> Vec<(xr::Action, String)> {
+        self.bindings
+            .lock()
+            .get(interaction_profile)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// The physical binding of an application action in an interaction profile, limited to `hand` if given
+    pub fn binding(
+        &self,
+        layer_action: xr::Action,
+        interaction_profile: &str,
+        hand: Option<Hand>,
+    ) -> Option<String> {
+        self.bindings
+            .lock()
+            .get(interaction_profile)?
+            .iter()
+            .filter(|(action, _)| *action == layer_action)
+            .map(|(_, binding)| binding)
+            .find(|binding| hand.map_or(true, |hand| Hand::of_path(binding) == Some(hand)))
+            .cloned()
+    }
+
+    /// The physical bindings of an application action for `hand` with the interaction profiles
+    /// they belong to
+    pub fn bindings_of(&self, layer_action: xr::Action, hand: Hand) -> Vec<(String, String)> {
+        self.bindings
+            .lock()
+            .iter()
+            .flat_map(|(interaction_profile, bindings)| {
+                bindings
+                    .iter()
+                    .filter(|(action, binding)| {
+                        *action == layer_action && Hand::of_path(binding) == Some(hand)
+                    })
+                    .map(move |(_, binding)| (interaction_profile.clone(), binding.clone()))
+            })
+            .collect()
+    }
+
+    /// Creates an action used by the layer itself which is bound to `bindings` in every
+    /// interaction profile that has them
+    pub fn create_layer_action(
+        &self,
+        inner: &InnerInstance,
+        name: &str,
+        action_type: xr::ActionType,
+        bindings: &[&str],
+    ) -> openxr::Result<xr::Action> {
+        self.create_profile_layer_action(inner, name, action_type, None, bindings)
+    }
+
+    /// Creates an action used by the layer itself, only bound in `interaction_profile` if given
+    pub fn create_profile_layer_action(
+        &self,
+        inner: &InnerInstance,
+        name: &str,
+        action_type: xr::ActionType,
+        interaction_profile: Option<&str>,
+        bindings: &[&str],
+    ) -> openxr::Result<xr::Action> {
+        let handle = self.create_native_action(inner, name, action_type, &[])?;
+
+        self.layer_bindings
+            .lock()
+            .extend(bindings.iter().map(|binding| {
+                (
+                    handle,
+                    (*binding).to_owned(),
+                    interaction_profile.map(str::to_owned),
+                )
+            }));
+
+        Ok(handle)
+    }
+
+    /// The bindings of the layer's own actions which are valid for an interaction profile
+    pub fn layer_bindings(
+        &self,
+        inner: &InnerInstance,
+        interaction_profile: &str,
+    ) -> openxr::Result<Vec<xr::ActionSuggestedBinding>> {
+        let profile = match interaction_profiles::get(interaction_profile) {
+            Some(profile) => profile,
+            None => return Ok(Vec::new()),
+        };
+
+        self.layer_bindings
+            .lock()
+            .iter()
+            .filter(|(_, binding, limit)| {
+                profile.has_binding(binding)
+                    && limit
+                        .as_deref()
+                        .map_or(true, |limit| limit == interaction_profile)
+            })
+            .map(|(action, binding, _)| {
+                Ok(xr::ActionSuggestedBinding {
+                    action: *action,
+                    binding: inner.string_to_path(binding)?,
+                })
+            })
+            .collect()
+    }
+
+    /// The physical bindings of one of the layer's own actions
+    pub fn layer_bindings_of(&self, action: xr::Action) -> Vec<String> {
+        self.layer_bindings
+            .lock()
+            .iter()
+            .filter(|(handle, _, _)| *handle == action)
+            .map(|(_, binding, _)| binding.clone())
+            .collect()
+    }
+
+    fn get_info(action: xr::Action) -> xr::ActionStateGetInfo {
+        xr::ActionStateGetInfo {
+            ty: xr::ActionStateGetInfo::TYPE,
+            next: std::ptr::null(),
+            action,
+            subaction_path: xr::Path::NULL,
+        }
+    }
+
+    /// Reads the state of one of the layer's own actions from the runtime, inactive actions read
+    /// as released
+    pub fn read(
+        &self,
+        inner: &InnerInstance,
+        session: xr::Session,
+        action: xr::Action,
+        action_type: xr::ActionType,
+    ) -> openxr::Result<InputValue> {
+        let get_info = Self::get_info(action);
+        Ok(match action_type {
+            xr::ActionType::FLOAT_INPUT => {
+                let mut state = xr::ActionStateFloat {
+                    ty: xr::ActionStateFloat::TYPE,
+                    next: std::ptr::null_mut(),
+                    current_state: 0.,
+                    changed_since_last_sync: false.into(),
+                    last_change_time: xr::Time::from_nanos(0),
+                    is_active: false.into(),
+                };
+                unsafe { (inner.core.get_action_state_float)(session, &get_info, &mut state) }
+                    .result()?;
+                InputValue::Float(if state.is_active.into() {
+                    state.current_state
+                } else {
+                    0.
+                })
+            }
+            xr::ActionType::VECTOR2F_INPUT => {
+                let mut state = xr::ActionStateVector2f {
+                    ty: xr::ActionStateVector2f::TYPE,
+                    next: std::ptr::null_mut(),
+                    current_state: Default::default(),
+                    changed_since_last_sync: false.into(),
+                    last_change_time: xr::Time::from_nanos(0),
+                    is_active: false.into(),
+                };
+                unsafe { (inner.core.get_action_state_vector2f)(session, &get_info, &mut state) }
+                    .result()?;
+                InputValue::Vector2f(if state.is_active.into() {
+                    [state.current_state.x, state.current_state.y]
+                } else {
+                    [0., 0.]
+                })
+            }
+            _ => {
+                let mut state = xr::ActionStateBoolean {
+                    ty: xr::ActionStateBoolean::TYPE,
+                    next: std::ptr::null_mut(),
+                    current_state: false.into(),
+                    changed_since_last_sync: false.into(),
+                    last_change_time: xr::Time::from_nanos(0),
+                    is_active: false.into(),
+                };
+                unsafe { (inner.core.get_action_state_boolean)(session, &get_info, &mut state) }
+                    .result()?;
+                InputValue::Boolean(bool::from(state.is_active) && bool::from(state.current_state))
+            }
+        })
+    }
+}
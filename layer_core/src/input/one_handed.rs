@@ -0,0 +1,82 @@
+use crate::profile::OneHandedSwitch;
+
+#[derive(Debug, Default)]
+pub struct OneHandedState {
+    other_hand_active: bool,
+    switch_pressed: bool,
+}
+
+impl OneHandedState {
+    /// Returns whether the hand whose input is used changed
+    pub fn update(&mut self, switch: &OneHandedSwitch, pressed: bool) -> bool {
+        let other_hand_active = self.other_hand_active;
+        match switch {
+            OneHandedSwitch::Modifier { .. } => self.other_hand_active = pressed,
+            OneHandedSwitch::Cycle { .. } => {
+                if pressed && !self.switch_pressed {
+                    self.other_hand_active = !self.other_hand_active;
+                }
+            }
+        }
+        self.switch_pressed = pressed;
+        self.other_hand_active != other_hand_active
+    }
+
+    /// Whether input folded from the other hand should be used instead of the used hand's own input
+    pub fn other_hand_active(&self) -> bool {
+        self.other_hand_active
+    }
+
+    /// Whether a binding is used, bindings folded from the other hand replace the used hand's own
+    pub fn is_enabled(&self, folded: bool) -> bool {
+        folded == self.other_hand_active
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modifier() -> OneHandedSwitch {
+        OneHandedSwitch::Modifier {
+            binding: "/user/hand/right/input/thumbstick/click".to_owned(),
+        }
+    }
+
+    fn cycle() -> OneHandedSwitch {
+        OneHandedSwitch::Cycle {
+            binding: "/user/hand/right/input/thumbstick/click".to_owned(),
+        }
+    }
+
+    #[test]
+    fn modifier_switches_while_held() {
+        let mut state = OneHandedState::default();
+        assert!(state.is_enabled(false));
+        assert!(!state.is_enabled(true));
+
+        assert!(state.update(&modifier(), true));
+        assert!(state.other_hand_active());
+        assert!(state.is_enabled(true));
+        assert!(!state.is_enabled(false));
+
+        assert!(!state.update(&modifier(), true));
+        assert!(state.update(&modifier(), false));
+        assert!(!state.other_hand_active());
+    }
+
+    #[test]
+    fn cycle_switches_on_press() {
+        let mut state = OneHandedState::default();
+        assert!(state.update(&cycle(), true));
+        assert!(state.other_hand_active());
+
+        //Holding or releasing the switch keeps the hand
+        assert!(!state.update(&cycle(), true));
+        assert!(!state.update(&cycle(), false));
+        assert!(state.other_hand_active());
+
+        assert!(state.update(&cycle(), true));
+        assert!(!state.other_hand_active());
+    }
+}
@@ -0,0 +1,88 @@
+use openxr::sys as xr;
+use serde::{Deserialize, Serialize};
+
+use crate::math;
+
+/// Lets the user drag the world around while holding a button, like the space drag of
+/// OVR Advanced Settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayspaceMoverConfig {
+    /// The physical button which grabs the world, e.g. `/user/hand/left/input/x/click`.
+    /// The world follows the grip pose of the hand the button belongs to.
+    pub binding: String,
+    /// Only drag the world without rotating it around the hand
+    #[serde(default)]
+    pub lock_rotation: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Grab {
+    hand: xr::Posef,
+    offset: xr::Posef,
+}
+
+/// The offset of the application's LOCAL and STAGE spaces, expressed in the runtime's STAGE space
+#[derive(Debug)]
+pub struct PlayspaceMover {
+    offset: xr::Posef,
+    grab: Option<Grab>,
+}
+
+impl Default for PlayspaceMover {
+    fn default() -> Self {
+        Self {
+            offset: math::IDENTITY,
+            grab: None,
+        }
+    }
+}
+
+impl PlayspaceMover {
+    pub fn offset(&self) -> xr::Posef {
+        self.offset
+    }
+
+    /// Updates the offset from the grab button and the hand's pose in the runtime's STAGE space
+    pub fn update(&mut self, grabbing: bool, hand: Option<xr::Posef>, lock_rotation: bool) {
+        let hand = match (grabbing, hand) {
+            (true, Some(hand)) => hand,
+            (true, None) => return,
+            (false, _) => {
+                self.grab = None;
+                return;
+            }
+        };
+
+        let grab = *self.grab.get_or_insert(Grab {
+            hand,
+            offset: self.offset,
+        });
+
+        let rotation = if lock_rotation {
+            math::IDENTITY.orientation
+        } else {
+            yaw(math::quat_mul(
+                hand.orientation,
+                math::quat_conjugate(grab.hand.orientation),
+            ))
+        };
+
+        //Moves the world so the point grabbed at the start of the grab stays in the hand
+        let delta = xr::Posef {
+            orientation: rotation,
+            position: math::sub(hand.position, math::rotate(rotation, grab.hand.position)),
+        };
+
+        self.offset = math::pose_mul(delta, grab.offset);
+    }
+}
+
+/// The rotation around the vertical axis contained in `q`
+pub fn yaw(q: xr::Quaternionf) -> xr::Quaternionf {
+    math::quat_normalize(xr::Quaternionf {
+        x: 0.,
+        y: q.y,
+        z: 0.,
+        w: q.w,
+    })
+}
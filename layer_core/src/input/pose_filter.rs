@@ -0,0 +1,332 @@
+use std::f32::consts::PI;
+
+use openxr::sys as xr;
+use serde::{Deserialize, Serialize};
+
+use crate::math;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PoseFilterConfig {
+    pub smoothing: Option<Smoothing>,
+    /// Locates the space at most this far past the frame's predicted display time, limiting how
+    /// far the runtime extrapolates
+    pub max_prediction_ms: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Smoothing {
+    /// Speed adaptive low pass filter, see <https://gery.casiez.net/1euro/>.
+    /// Position speeds are in m/s and rotation speeds in rad/s.
+    OneEuro {
+        min_cutoff: f32,
+        beta: f32,
+        #[serde(default = "default_derivative_cutoff")]
+        derivative_cutoff: f32,
+    },
+    /// Frame rate independent exponential moving average
+    Exponential { time_constant_ms: f32 },
+}
+
+fn default_derivative_cutoff() -> f32 {
+    1.
+}
+
+impl PoseFilterConfig {
+    pub fn clamp_time(&self, time: xr::Time, predicted_display_time: xr::Time) -> xr::Time {
+        match self.max_prediction_ms {
+            Some(max_prediction_ms) if predicted_display_time.as_nanos() != 0 => {
+                let max = predicted_display_time.as_nanos() + (max_prediction_ms * 1e6) as i64;
+                xr::Time::from_nanos(time.as_nanos().min(max))
+            }
+            _ => time,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FilteredPose {
+    pub pose: xr::Posef,
+    /// Derived from the filtered poses, `None` for the first sample
+    pub velocity: Option<(xr::Vector3f, xr::Vector3f)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    time: xr::Time,
+    output: FilteredPose,
+    linear_speed: f32,
+    angular_speed: f32,
+}
+
+/// The state of a filter for a single space located relative to a single base space
+#[derive(Debug)]
+pub struct PoseFilterState {
+    smoothing: Smoothing,
+    last: Option<Sample>,
+}
+
+impl PoseFilterState {
+    pub fn new(smoothing: Smoothing) -> Self {
+        Self {
+            smoothing,
+            last: None,
+        }
+    }
+
+    pub fn smoothing(&self) -> Smoothing {
+        self.smoothing
+    }
+
+    /// Filters the pose located at `time`.
+    ///
+    /// Locating the same time twice returns the same result and times older than the last sample
+    /// are passed through without affecting the filter.
+    pub fn update(&mut self, pose: xr::Posef, time: xr::Time) -> FilteredPose {
+        let last = match self.last {
+            Some(last) if time == last.time => return last.output,
+            Some(last) if time < last.time => {
+                return FilteredPose {
+                    pose,
+                    velocity: None,
+                }
+            }
+            Some(last) => last,
+            None => {
+                let output = FilteredPose {
+                    pose,
+                    velocity: None,
+                };
+                self.last = Some(Sample {
+                    time,
+                    output,
+                    linear_speed: 0.,
+                    angular_speed: 0.,
+                });
+                return output;
+            }
+        };
+
+        let dt = (time.as_nanos() - last.time.as_nanos()) as f32 / 1e9;
+        let previous = last.output.pose;
+
+        let raw_linear_speed = math::length(math::sub(pose.position, previous.position)) / dt;
+        let raw_angular_speed = math::length(math::rotation_vector(math::quat_mul(
+            pose.orientation,
+            math::quat_conjugate(previous.orientation),
+        ))) / dt;
+
+        let (linear_alpha, angular_alpha, linear_speed, angular_speed) = match self.smoothing {
+            Smoothing::OneEuro {
+                min_cutoff,
+                beta,
+                derivative_cutoff,
+            } => {
+                let derivative_alpha = one_euro_alpha(dt, derivative_cutoff);
+                let linear_speed =
+                    last.linear_speed + (raw_linear_speed - last.linear_speed) * derivative_alpha;
+                let angular_speed = last.angular_speed
+                    + (raw_angular_speed - last.angular_speed) * derivative_alpha;
+                (
+                    one_euro_alpha(dt, min_cutoff + beta * linear_speed),
+                    one_euro_alpha(dt, min_cutoff + beta * angular_speed),
+                    linear_speed,
+                    angular_speed,
+                )
+            }
+            Smoothing::Exponential { time_constant_ms } => {
+                let alpha = exponential_alpha(dt, time_constant_ms / 1000.);
+                (alpha, alpha, raw_linear_speed, raw_angular_speed)
+            }
+        };
+
+        let filtered = xr::Posef {
+            orientation: math::nlerp(previous.orientation, pose.orientation, angular_alpha),
+            position: math::lerp(previous.position, pose.position, linear_alpha),
+        };
+
+        let output = FilteredPose {
+            pose: filtered,
+            velocity: Some(velocity_between(previous, filtered, dt)),
+        };
+
+        self.last = Some(Sample {
+            time,
+            output,
+            linear_speed,
+            angular_speed,
+        });
+
+        output
+    }
+}
+
+pub fn one_euro_alpha(dt: f32, cutoff: f32) -> f32 {
+    let r = 2. * PI * cutoff * dt;
+    r / (r + 1.)
+}
+
+pub fn exponential_alpha(dt: f32, time_constant: f32) -> f32 {
+    if time_constant <= 0. {
+        return 1.;
+    }
+    1. - (-dt / time_constant).exp()
+}
+
+/// The linear and angular velocity moving from `from` to `to` in `dt` seconds
+pub fn velocity_between(from: xr::Posef, to: xr::Posef, dt: f32) -> (xr::Vector3f, xr::Vector3f) {
+    let linear = math::scale(math::sub(to.position, from.position), 1. / dt);
+    let angular = math::scale(
+        math::rotation_vector(math::quat_mul(
+            to.orientation,
+            math::quat_conjugate(from.orientation),
+        )),
+        1. / dt,
+    );
+    (linear, angular)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPONENTIAL: Smoothing = Smoothing::Exponential {
+        time_constant_ms: 100.,
+    };
+
+    fn pose_at(x: f32) -> xr::Posef {
+        xr::Posef {
+            orientation: math::IDENTITY.orientation,
+            position: math::vec3(x, 0., 0.),
+        }
+    }
+
+    fn time_ms(ms: i64) -> xr::Time {
+        xr::Time::from_nanos(ms * 1_000_000)
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-4,
+            "expected {}, found {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn first_sample_passes_through() {
+        let mut state = PoseFilterState::new(EXPONENTIAL);
+        assert_eq!(
+            state.update(pose_at(1.), time_ms(0)),
+            FilteredPose {
+                pose: pose_at(1.),
+                velocity: None,
+            }
+        );
+    }
+
+    #[test]
+    fn same_time_returns_the_same_output() {
+        let mut state = PoseFilterState::new(EXPONENTIAL);
+        state.update(pose_at(0.), time_ms(0));
+        let output = state.update(pose_at(1.), time_ms(10));
+        assert_close(output.pose.position.x, exponential_alpha(0.01, 0.1));
+
+        assert_eq!(state.update(pose_at(5.), time_ms(10)), output);
+    }
+
+    #[test]
+    fn older_times_bypass_the_filter() {
+        let mut state = PoseFilterState::new(EXPONENTIAL);
+        state.update(pose_at(0.), time_ms(0));
+        let output = state.update(pose_at(1.), time_ms(10));
+
+        assert_eq!(
+            state.update(pose_at(3.), time_ms(5)),
+            FilteredPose {
+                pose: pose_at(3.),
+                velocity: None,
+            }
+        );
+        assert_eq!(state.update(pose_at(1.), time_ms(10)), output);
+    }
+
+    #[test]
+    fn one_euro_follows_slow_and_fast_movement() {
+        let mut state = PoseFilterState::new(Smoothing::OneEuro {
+            min_cutoff: 1.,
+            beta: 10.,
+            derivative_cutoff: 1.,
+        });
+        state.update(pose_at(0.), time_ms(0));
+        let slow = state.update(pose_at(0.001), time_ms(10)).pose.position.x;
+
+        let mut state = PoseFilterState::new(state.smoothing());
+        state.update(pose_at(0.), time_ms(0));
+        let fast = state.update(pose_at(1.), time_ms(10)).pose.position.x;
+
+        //Faster movement raises the cutoff, so less of it is filtered away
+        assert!(slow / 0.001 < fast);
+    }
+
+    #[test]
+    fn alpha_limits() {
+        assert_eq!(one_euro_alpha(0.01, 0.), 0.);
+        assert!(one_euro_alpha(0.01, 1e6) > 0.999);
+        assert!(one_euro_alpha(0.01, 1.) < one_euro_alpha(0.01, 2.));
+
+        assert_eq!(exponential_alpha(0.01, 0.), 1.);
+        assert_eq!(exponential_alpha(0.01, -1.), 1.);
+        assert_eq!(exponential_alpha(0., 0.1), 0.);
+        assert!(exponential_alpha(10., 0.01) > 0.999);
+        assert_close(exponential_alpha(0.1, 0.1), 1. - (-1f32).exp());
+    }
+
+    #[test]
+    fn velocity_between_poses() {
+        let to = xr::Posef {
+            orientation: math::quat_from_axis_angle(math::vec3(0., 1., 0.), 0.1),
+            position: math::vec3(1., 2., 0.),
+        };
+        let (linear, angular) = velocity_between(math::IDENTITY, to, 0.5);
+        assert_close(linear.x, 2.);
+        assert_close(linear.y, 4.);
+        assert_close(linear.z, 0.);
+        assert_close(angular.x, 0.);
+        assert_close(angular.y, 0.2);
+        assert_close(angular.z, 0.);
+    }
+
+    #[test]
+    fn velocity_follows_the_filtered_pose() {
+        let mut state = PoseFilterState::new(EXPONENTIAL);
+        state.update(pose_at(0.), time_ms(0));
+        let output = state.update(pose_at(1.), time_ms(10));
+
+        let (linear, angular) = output.velocity.unwrap();
+        let (expected_linear, expected_angular) = velocity_between(pose_at(0.), output.pose, 0.01);
+        assert_eq!(linear, expected_linear);
+        assert_eq!(angular, expected_angular);
+        assert_close(linear.x, output.pose.position.x / 0.01);
+    }
+
+    #[test]
+    fn clamp_time() {
+        let config = PoseFilterConfig {
+            smoothing: None,
+            max_prediction_ms: Some(10.),
+        };
+        assert_eq!(config.clamp_time(time_ms(150), time_ms(100)), time_ms(110));
+        assert_eq!(config.clamp_time(time_ms(105), time_ms(100)), time_ms(105));
+        assert_eq!(config.clamp_time(time_ms(50), time_ms(100)), time_ms(50));
+        //Before the first frame there is no predicted display time to clamp to
+        assert_eq!(config.clamp_time(time_ms(150), time_ms(0)), time_ms(150));
+
+        assert_eq!(
+            PoseFilterConfig::default().clamp_time(time_ms(150), time_ms(100)),
+            time_ms(150)
+        );
+    }
+}
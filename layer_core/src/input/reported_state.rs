@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+use openxr::sys as xr;
+use parking_lot::Mutex;
+
+use crate::recording::InputValue;
+
+/// The states of boolean, float and vector2f actions as they were returned to the application.
+/// A state is read once after each `xrSyncActions` and then kept, so every read in between agrees
+/// and changes are found by comparing with the state of the previous read.
+pub struct ReportedStates {
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    /// The number of calls to `xrSyncActions` so far
+    sync: u64,
+    /// The predicted display time of the last sync
+    time: xr::Time,
+    states: HashMap<(xr::Action, xr::Path), ReportedState>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ReportedState {
+    pub value: InputValue,
+    pub changed_since_last_sync: bool,
+    pub last_change_time: xr::Time,
+    /// The sync the state was read after
+    sync: u64,
+}
+
+impl Default for ReportedStates {
+    fn default() -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                sync: 0,
+                time: xr::Time::from_nanos(0),
+                states: HashMap::new(),
+            }),
+        }
+    }
+}
+
+impl ReportedStates {
+    /// Called every `xrSyncActions`, afterwards every state is read again
+    pub fn sync(&self, time: xr::Time) {
+        let mut inner = self.inner.lock();
+        inner.sync += 1;
+        inner.time = time;
+    }
+
+    /// The state of the action since the last sync, calling `read` if it has not been read yet.
+    /// An action which is not read after every sync has changed when its state differs from the
+    /// one it was read with last.
+    pub fn get_or_read(
+        &self,
+        action: xr::Action,
+        sub_action_path: xr::Path,
+        read: impl FnOnce() -> Result<InputValue, xr::Result>,
+    ) -> Result<ReportedState, xr::Result> {
+        let key = (action, sub_action_path);
+        {
+            let inner = self.inner.lock();
+            if let Some(state) = inner
+                .states
+                .get(&key)
+                .filter(|state| state.sync == inner.sync)
+            {
+                return Ok(*state);
+            }
+        }
+
+        //Reading locks other state of the session, which must not wait for this lock
+        let value = read()?;
+
+        let mut inner = self.inner.lock();
+        let (sync, time) = (inner.sync, inner.time);
+        let previous = inner.states.get(&key).copied();
+        if let Some(state) = previous.filter(|state| state.sync == sync) {
+            return Ok(state);
+        }
+
+        let changed = previous.map_or(!is_at_rest(value), |previous| previous.value != value);
+        let state = ReportedState {
+            value,
+            changed_since_last_sync: changed,
+            last_change_time: if changed {
+                time
+            } else {
+                previous.map_or(xr::Time::from_nanos(0), |previous| {
+                    previous.last_change_time
+                })
+            },
+            sync,
+        };
+        inner.states.insert(key, state);
+        Ok(state)
+    }
+}
+
+fn is_at_rest(value: InputValue) -> bool {
+    match value {
+        InputValue::Boolean(pressed) => !pressed,
+        InputValue::Float(value) => value == 0.,
+        InputValue::Vector2f([x, y]) => x == 0. && y == 0.,
+    }
+}
+
+/// Combines the values of the bindings of a float action like the runtimes do, by taking the one
+/// furthest from zero
+pub fn combine_float(values: impl IntoIterator<Item = InputValue>) -> f32 {
+    values
+        .into_iter()
+        .map(InputValue::as_float)
+        .fold(0., |largest, value| {
+            if value.abs() > largest.abs() {
+                value
+            } else {
+                largest
+            }
+        })
+}
+
+/// Combines the values of the bindings of a vector2f action like the runtimes do, by taking the
+/// longest vector
+pub fn combine_vector2f(values: impl IntoIterator<Item = InputValue>) -> [f32; 2] {
+    values
+        .into_iter()
+        .filter_map(|value| match value {
+            InputValue::Vector2f(vector) => Some(vector),
+            _ => None,
+        })
+        .fold([0., 0.], |longest, vector| {
+            if vector[0].hypot(vector[1]) > longest[0].hypot(longest[1]) {
+                vector
+            } else {
+                longest
+            }
+        })
+}
@@ -1,21 +1,42 @@
-use std::{collections::HashMap, sync::Arc};
 use openxr as xr;
-use thunderdome::Index;
+use std::{collections::HashMap, sync::Arc};
+use suinput::{SimpleBinding, SuBindingLayout, SuPath};
+use thunderdome::{Arena, Index};
+
+use crate::wrappers::{
+    instance::InstanceWrapper, layer_action::LayerAction, layer_action_set::LayerActionSet,
+};
 
-use crate::wrappers::layer_action_set::LayerActionSet;
+use super::advanced_binding::BindingKind;
 
 pub struct InteractionProfileSuggestedBindings {
     pub action_sets: HashMap<Arc<LayerActionSet>, SuggestedBindings>,
 }
 
 pub struct SuggestedBindings {
+    pub interaction_profile: String,
     pub bindings: Vec<SuggestedBinding>,
 }
 
+#[derive(Clone)]
 pub enum SuggestedBinding {
     SimpleBinding {
         action: Index,
-        binding: xr::Path,
+        /// The top level path of the binding as suggested by the application
+        sub_action_path: xr::Path,
+        /// The physical binding after translation, kept as a string so it is read without looking
+        /// up the path
+        binding: String,
+        /// Whether the binding was folded from the other hand in one handed mode, it is only used
+        /// while the other hand is switched to
+        folded: bool,
+    },
+    /// A binding of the profile which the layer evaluates itself, see
+    /// `RemapProfile::advanced_bindings`
+    Advanced {
+        action: Index,
+        sub_action_path: xr::Path,
+        kind: BindingKind,
     },
     AnalogThreshold {
         action: Index,
@@ -35,11 +56,68 @@ pub enum SuggestedBinding {
         is_sticky: bool,
         on_haptic: (),
         off_haptic: (),
-    }
+    },
 }
 
 impl SuggestedBinding {
     // pub fn is_valid(&self) -> bool {
 
     // }
-}
\ No newline at end of file
+}
+
+impl SuggestedBindings {
+    /// The layout SuInput evaluates for the profile, with the bindings folded in one handed mode
+    /// if `other_hand_active` or else the others
+    pub fn create_binding_layout(
+        &self,
+        instance: &InstanceWrapper,
+        interaction_profile: SuPath,
+        layer_actions: &Arena<Arc<LayerAction>>,
+        other_hand_active: bool,
+    ) -> xr::Result<SuBindingLayout> {
+        let mut bindings = Vec::with_capacity(self.bindings.len());
+
+        for binding in &self.bindings {
+            if let SuggestedBinding::SimpleBinding {
+                action,
+                sub_action_path,
+                binding,
+                folded,
+            } = binding
+            {
+                if *folded != other_hand_active {
+                    continue;
+                }
+
+                let action = layer_actions
+                    .get(*action)
+                    .ok_or(xr::sys::Result::ERROR_HANDLE_INVALID)?;
+
+                let su_action = match action.sub_actions.su_action_handle(*sub_action_path) {
+                    Some(su_action) => su_action,
+                    None => continue,
+                };
+
+                //Inputs of virtual devices are read by the layer
+                if instance.profile.virtual_component(binding).is_some() {
+                    continue;
+                }
+
+                let path = instance
+                    .suinput_instance
+                    .get_path(binding)
+                    .map_err(|_| xr::sys::Result::ERROR_PATH_UNSUPPORTED)?;
+
+                bindings.push(SimpleBinding {
+                    action: su_action,
+                    path,
+                });
+            }
+        }
+
+        instance
+            .suinput_instance
+            .create_binding_layout("Suggested", interaction_profile, &bindings)
+            .map_err(|_| xr::sys::Result::ERROR_PATH_UNSUPPORTED)
+    }
+}
@@ -0,0 +1,332 @@
+use std::borrow::Cow;
+
+use openxr::sys as xr;
+
+use crate::profile::RemapProfile;
+
+use super::{
+    interaction_profiles,
+    user_paths::{self, Hand},
+};
+
+/// Why a binding suggested by the application is not bound
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DroppedBinding {
+    /// The physical path is reserved by the layer
+    Reserved,
+    /// Mirroring or folding moved the binding to this path which the interaction profile does not
+    /// have
+    Missing(String),
+}
+
+/// Maps a path suggested by the application onto the physical path it should be bound to.
+/// Bindings of `interaction_profile` which are mirrored or folded onto a hand lacking the original
+/// component are moved to the matching component of that hand.
+pub fn translate_path<'a>(
+    profile: &RemapProfile,
+    interaction_profile: Option<&str>,
+    path: &'a str,
+    action_type: xr::ActionType,
+) -> Result<Cow<'a, str>, DroppedBinding> {
+    let mut path = Cow::Borrowed(path);
+    if profile.left_handed {
+        if let Some(hand) = Hand::of_path(&path) {
+            path = move_to_hand(interaction_profile, path, hand.other())?;
+        }
+    }
+
+    if let Some(one_handed) = &profile.one_handed {
+        //Poses are only folded when they will be derived from the used hand
+        let fold = action_type != xr::ActionType::POSE_INPUT || one_handed.derived_pose.is_some();
+        if fold {
+            path = move_to_hand(interaction_profile, path, one_handed.hand)?;
+        }
+    }
+
+    if profile
+        .reserved_bindings()
+        .iter()
+        .any(|reserved| *reserved == path)
+    {
+        return Err(DroppedBinding::Reserved);
+    }
+
+    Ok(path)
+}
+
+/// Moves a path of the other hand onto `hand`, other paths are kept
+fn move_to_hand<'a>(
+    interaction_profile: Option<&str>,
+    path: Cow<'a, str>,
+    hand: Hand,
+) -> Result<Cow<'a, str>, DroppedBinding> {
+    let rest = match user_paths::strip_user_path(&path, hand.other().user_path()) {
+        Some(rest) => rest,
+        None => return Ok(path),
+    };
+    let moved = format!("{}{}", hand.user_path(), rest);
+    match interaction_profile.and_then(interaction_profiles::get) {
+        Some(interaction_profile) if !rest.is_empty() => interaction_profile
+            .binding_on_hand(&path, hand)
+            .map(Cow::Owned)
+            .ok_or(DroppedBinding::Missing(moved)),
+        _ => Ok(Cow::Owned(moved)),
+    }
+}
+
+/// The hand a path suggested by the application physically belongs to before one handed folding
+pub fn physical_hand(profile: &RemapProfile, path: &str) -> Option<Hand> {
+    let hand = Hand::of_path(path)?;
+    Some(if profile.left_handed {
+        hand.other()
+    } else {
+        hand
+    })
+}
+
+/// Whether input for a path suggested by the application has been folded onto the other hand
+pub fn is_folded(profile: &RemapProfile, path: &str) -> bool {
+    match &profile.one_handed {
+        Some(one_handed) => physical_hand(profile, path) == Some(one_handed.hand.other()),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::profile::{DerivedPose, OneHandedConfig, OneHandedSwitch};
+
+    use super::*;
+
+    const TOUCH: &str = "/interaction_profiles/oculus/touch_controller";
+    const SWITCH: &str = "/user/hand/right/input/thumbstick/click";
+
+    fn one_handed(derived_pose: Option<DerivedPose>) -> RemapProfile {
+        RemapProfile {
+            one_handed: Some(OneHandedConfig {
+                hand: Hand::Right,
+                switch: OneHandedSwitch::Modifier {
+                    binding: SWITCH.to_owned(),
+                },
+                derived_pose,
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn translate(
+        profile: &RemapProfile,
+        path: &str,
+        action_type: xr::ActionType,
+    ) -> Result<String, DroppedBinding> {
+        translate_path(profile, Some(TOUCH), path, action_type).map(Cow::into_owned)
+    }
+
+    #[test]
+    fn paths_are_kept_without_remapping() {
+        let path = "/user/hand/left/input/x/click";
+        assert_eq!(
+            translate_path(
+                &RemapProfile::default(),
+                Some(TOUCH),
+                path,
+                xr::ActionType::BOOLEAN_INPUT
+            ),
+            Ok(Cow::Borrowed(path))
+        );
+    }
+
+    #[test]
+    fn left_handed_mirrors_paths() {
+        let profile = RemapProfile {
+            left_handed: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            translate(
+                &profile,
+                "/user/hand/left/input/trigger/value",
+                xr::ActionType::FLOAT_INPUT
+            ),
+            Ok("/user/hand/right/input/trigger/value".to_owned())
+        );
+    }
+
+    #[test]
+    fn left_handed_maps_components_of_one_hand() {
+        let profile = RemapProfile {
+            left_handed: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            translate(
+                &profile,
+                "/user/hand/right/input/a/click",
+                xr::ActionType::BOOLEAN_INPUT
+            ),
+            Ok("/user/hand/left/input/x/click".to_owned())
+        );
+        assert_eq!(
+            translate(
+                &profile,
+                "/user/hand/left/input/menu/click",
+                xr::ActionType::BOOLEAN_INPUT
+            ),
+            Ok("/user/hand/right/input/system/click".to_owned())
+        );
+        assert_eq!(
+            translate(
+                &profile,
+                "/user/hand/right/input/foo/click",
+                xr::ActionType::BOOLEAN_INPUT
+            ),
+            Err(DroppedBinding::Missing(
+                "/user/hand/left/input/foo/click".to_owned()
+            ))
+        );
+        assert_eq!(
+            translate_path(
+                &profile,
+                None,
+                "/user/hand/right",
+                xr::ActionType::BOOLEAN_INPUT
+            ),
+            Ok(Cow::Owned("/user/hand/left".to_owned()))
+        );
+        assert_eq!(
+            translate(
+                &profile,
+                "/user/gamepad/input/a/click",
+                xr::ActionType::BOOLEAN_INPUT
+            ),
+            Ok("/user/gamepad/input/a/click".to_owned())
+        );
+    }
+
+    #[test]
+    fn folding_moves_components_both_hands_have() {
+        assert_eq!(
+            translate(
+                &one_handed(None),
+                "/user/hand/left/input/trigger/value",
+                xr::ActionType::FLOAT_INPUT
+            ),
+            Ok("/user/hand/right/input/trigger/value".to_owned())
+        );
+        assert_eq!(
+            translate(
+                &one_handed(None),
+                "/user/hand/right/input/trigger/value",
+                xr::ActionType::FLOAT_INPUT
+            ),
+            Ok("/user/hand/right/input/trigger/value".to_owned())
+        );
+    }
+
+    #[test]
+    fn folding_maps_components_of_one_hand() {
+        let profile = one_handed(None);
+        assert_eq!(
+            translate(
+                &profile,
+                "/user/hand/left/input/x/click",
+                xr::ActionType::BOOLEAN_INPUT
+            ),
+            Ok("/user/hand/right/input/a/click".to_owned())
+        );
+        assert_eq!(
+            translate(
+                &profile,
+                "/user/hand/left/input/y",
+                xr::ActionType::BOOLEAN_INPUT
+            ),
+            Ok("/user/hand/right/input/b".to_owned())
+        );
+        assert_eq!(
+            translate(
+                &profile,
+                "/user/hand/left/input/foo/click",
+                xr::ActionType::BOOLEAN_INPUT
+            ),
+            Err(DroppedBinding::Missing(
+                "/user/hand/right/input/foo/click".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn folding_unknown_profiles_swaps_the_hand() {
+        assert_eq!(
+            translate_path(
+                &one_handed(None),
+                Some("/interaction_profiles/unknown/controller"),
+                "/user/hand/left/input/x/click",
+                xr::ActionType::BOOLEAN_INPUT
+            ),
+            Ok(Cow::Owned("/user/hand/right/input/x/click".to_owned()))
+        );
+        assert_eq!(
+            translate_path(
+                &one_handed(None),
+                None,
+                "/user/hand/left",
+                xr::ActionType::BOOLEAN_INPUT
+            ),
+            Ok(Cow::Owned("/user/hand/right".to_owned()))
+        );
+    }
+
+    #[test]
+    fn poses_are_folded_when_derived() {
+        let path = "/user/hand/left/input/grip/pose";
+        assert_eq!(
+            translate(&one_handed(None), path, xr::ActionType::POSE_INPUT),
+            Ok(path.to_owned())
+        );
+        assert_eq!(
+            translate(
+                &one_handed(Some(DerivedPose::Mirrored)),
+                path,
+                xr::ActionType::POSE_INPUT
+            ),
+            Ok("/user/hand/right/input/grip/pose".to_owned())
+        );
+    }
+
+    #[test]
+    fn reserved_bindings_are_dropped() {
+        assert_eq!(
+            translate(&one_handed(None), SWITCH, xr::ActionType::BOOLEAN_INPUT),
+            Err(DroppedBinding::Reserved)
+        );
+        //Folding onto the switch reserves the other hand's thumbstick as well
+        assert_eq!(
+            translate(
+                &one_handed(None),
+                "/user/hand/left/input/thumbstick/click",
+                xr::ActionType::BOOLEAN_INPUT
+            ),
+            Err(DroppedBinding::Reserved)
+        );
+    }
+
+    #[test]
+    fn folded_paths_follow_the_physical_hand() {
+        let profile = one_handed(None);
+        assert!(is_folded(&profile, "/user/hand/left/input/x/click"));
+        assert!(is_folded(&profile, "/user/hand/left"));
+        assert!(!is_folded(&profile, "/user/hand/right/input/a/click"));
+        assert!(!is_folded(&profile, "/user/gamepad/input/a/click"));
+        assert!(!is_folded(
+            &RemapProfile::default(),
+            "/user/hand/left/input/x/click"
+        ));
+
+        let left_handed = RemapProfile {
+            left_handed: true,
+            ..one_handed(None)
+        };
+        assert!(is_folded(&left_handed, "/user/hand/right/input/a/click"));
+        assert!(!is_folded(&left_handed, "/user/hand/left/input/x/click"));
+    }
+}
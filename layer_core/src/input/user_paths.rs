@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+
+pub const LEFT_HAND: &str = "/user/hand/left";
+pub const RIGHT_HAND: &str = "/user/hand/right";
+pub const GAMEPAD: &str = "/user/gamepad";
+pub const EYES: &str = "/user/eyes_ext";
+
+/// Returns the remainder of `path` if it begins with the top level path `user_path`
+pub fn strip_user_path<'a>(path: &'a str, user_path: &str) -> Option<&'a str> {
+    let rest = path.strip_prefix(user_path)?;
+    if rest.is_empty() || rest.starts_with('/') {
+        Some(rest)
+    } else {
+        None
+    }
+}
+
+/// The top level `/user/...` path of a binding path, e.g. `/user/hand/left` for
+/// `/user/hand/left/input/trigger/value`
+pub fn top_level_path(path: &str) -> Option<&str> {
+    let end = path.find("/input/").or_else(|| path.find("/output/"))?;
+    let top_level = &path[..end];
+    if top_level.starts_with("/user/") {
+        Some(top_level)
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Hash, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Hand {
+    Left,
+    Right,
+}
+
+impl Hand {
+    pub fn user_path(self) -> &'static str {
+        match self {
+            Hand::Left => LEFT_HAND,
+            Hand::Right => RIGHT_HAND,
+        }
+    }
+
+    pub fn other(self) -> Self {
+        match self {
+            Hand::Left => Hand::Right,
+            Hand::Right => Hand::Left,
+        }
+    }
+
+    pub fn of_path(path: &str) -> Option<Self> {
+        if strip_user_path(path, LEFT_HAND).is_some() {
+            Some(Hand::Left)
+        } else if strip_user_path(path, RIGHT_HAND).is_some() {
+            Some(Hand::Right)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_paths_end_at_a_separator() {
+        assert_eq!(
+            strip_user_path("/user/hand/left/input/x/click", LEFT_HAND),
+            Some("/input/x/click")
+        );
+        assert_eq!(strip_user_path(LEFT_HAND, LEFT_HAND), Some(""));
+        assert_eq!(strip_user_path("/user/hand/leftover", LEFT_HAND), None);
+        assert_eq!(strip_user_path("/user/gamepad", LEFT_HAND), None);
+    }
+
+    #[test]
+    fn top_level_paths_of_bindings() {
+        assert_eq!(
+            top_level_path("/user/hand/left/input/trigger/value"),
+            Some(LEFT_HAND)
+        );
+        assert_eq!(
+            top_level_path("/user/hand/right/output/haptic"),
+            Some(RIGHT_HAND)
+        );
+        assert_eq!(top_level_path("/user/gamepad/input/a/click"), Some(GAMEPAD));
+        assert_eq!(top_level_path("/user/hand/left"), None);
+        assert_eq!(top_level_path("/interaction_profiles/input/x"), None);
+    }
+
+    #[test]
+    fn hands_of_paths() {
+        assert_eq!(
+            Hand::of_path("/user/hand/left/input/x/click"),
+            Some(Hand::Left)
+        );
+        assert_eq!(Hand::of_path(RIGHT_HAND), Some(Hand::Right));
+        assert_eq!(Hand::of_path("/user/hand/leftover"), None);
+        assert_eq!(Hand::of_path("/user/gamepad/input/a/click"), None);
+        assert_eq!(Hand::Left.other(), Hand::Right);
+    }
+}
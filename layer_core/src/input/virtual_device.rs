@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::recording::InputValue;
+
+use super::interaction_profiles::ComponentType;
+
+/// A device which only exists in the layer, e.g. a pedal read by a helper process. Its inputs can
+/// be bound like those of the controllers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VirtualDeviceConfig {
+    /// The top level path, e.g. `/user/virtual/pedal`
+    pub user_path: String,
+    pub components: Vec<VirtualComponentConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VirtualComponentConfig {
+    /// The path below the top level path, e.g. `/input/pedal/value`
+    pub path: String,
+    pub source: VirtualSource,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum VirtualSource {
+    /// Injected by another process through the control socket or the injection ring
+    External,
+    /// The sum of scaled inputs, clamped to -1 to 1
+    Axis { inputs: Vec<AxisInput> },
+    /// 1 while every input is pressed
+    Combo { inputs: Vec<String> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AxisInput {
+    pub binding: String,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+}
+
+fn default_scale() -> f32 {
+    1.
+}
+
+impl VirtualDeviceConfig {
+    /// The full paths of the device's components with their sources
+    pub fn bindings(&self) -> impl Iterator<Item = (String, &VirtualSource)> {
+        self.components.iter().map(move |component| {
+            (
+                format!("{}{}", self.user_path, component.path),
+                &component.source,
+            )
+        })
+    }
+}
+
+/// The type an input of the virtual devices is read as. Like for physical inputs a binding may
+/// leave out the `click` or `value` component or combine the `x` and `y` components of a stick.
+pub fn component_type(devices: &[VirtualDeviceConfig], binding: &str) -> Option<ComponentType> {
+    let exists = |path: &str| {
+        devices
+            .iter()
+            .flat_map(VirtualDeviceConfig::bindings)
+            .any(|(component, _)| component == path)
+    };
+    if exists(binding)
+        || exists(&format!("{}/click", binding))
+        || exists(&format!("{}/value", binding))
+    {
+        Some(ComponentType::Float)
+    } else if exists(&format!("{}/x", binding)) && exists(&format!("{}/y", binding)) {
+        Some(ComponentType::Vector2f)
+    } else {
+        None
+    }
+}
+
+/// Combines the values of the inputs of a computed component
+pub fn evaluate(source: &VirtualSource, values: &[f32]) -> f32 {
+    match source {
+        VirtualSource::External => 0.,
+        VirtualSource::Axis { inputs } => inputs
+            .iter()
+            .zip(values)
+            .map(|(input, value)| input.scale * value)
+            .sum::<f32>()
+            .clamp(-1., 1.),
+        VirtualSource::Combo { .. } => f32::from(u8::from(
+            !values.is_empty() && values.iter().all(|value| *value > 0.5),
+        )),
+    }
+}
+
+/// The inputs of the virtual devices, updated by the session every `xrSyncActions`
+pub struct VirtualInput {
+    devices: Vec<VirtualDeviceConfig>,
+    values: Mutex<HashMap<String, f32>>,
+    /// Values injected by other processes since the last sync
+    injected: Mutex<HashMap<String, f32>>,
+}
+
+impl VirtualInput {
+    pub fn new(devices: Vec<VirtualDeviceConfig>) -> Self {
+        Self {
+            devices,
+            values: Default::default(),
+            injected: Default::default(),
+        }
+    }
+
+    /// Sets a component whose source is external at the next sync, later values replace earlier
+    /// ones
+    pub fn inject(&self, binding: &str, value: f32) -> Result<(), String> {
+        let is_external = self
+            .devices
+            .iter()
+            .flat_map(VirtualDeviceConfig::bindings)
+            .any(|(component, source)| {
+                component == binding && matches!(source, VirtualSource::External)
+            });
+        if !is_external {
+            return Err(format!(
+                "`{}` is not an external input of a virtual device",
+                binding
+            ));
+        }
+        if !value.is_finite() {
+            return Err(format!("`{}` cannot be set to {}", binding, value));
+        }
+        self.injected.lock().insert(binding.to_owned(), value);
+        Ok(())
+    }
+
+    /// Applies the injected values and the computed components
+    pub fn update(&self, computed: HashMap<String, f32>) {
+        let mut values = self.values.lock();
+        values.extend(self.injected.lock().drain());
+        values.extend(computed);
+    }
+
+    pub fn value(&self, binding: &str) -> Option<InputValue> {
+        super::input_value(&self.values.lock(), binding)
+    }
+
+    pub fn is_pressed(&self, binding: &str) -> bool {
+        self.value(binding).map_or(false, InputValue::is_pressed)
+    }
+}
@@ -0,0 +1,727 @@
+use std::{collections::HashMap, sync::Arc};
+
+use openxr::sys as xr;
+use thunderdome::{Arena, Index};
+
+use crate::{
+    input::{
+        binding_analysis, interaction_profiles,
+        suggested_bindings::{SuggestedBinding, SuggestedBindings},
+        user_paths,
+    },
+    manifest,
+    wrappers::{
+        instance::InstanceWrapper,
+        layer_action::{self, LayerAction},
+        layer_action_set::{self, LayerActionSet},
+    },
+};
+
+use super::injection::InjectionRing;
+use super::protocol::{
+    ActionState, ActionStateInfo, Binding, BindingModification, BindingReport, ExportedBinding,
+    InstanceInfo, InteractionProfileBindings, OverrideState, Request, Response,
+};
+
+pub fn handle(instance: &Arc<InstanceWrapper>, request: Request) -> Response {
+    let result = match request {
+        Request::ListInstances => Ok(list_instances()),
+        Request::ListActionSets => list_action_sets(instance),
+        Request::ListSuggestedBindings => list_suggested_bindings(instance),
+        Request::ExportBindings => export_bindings(instance),
+        Request::GetActionStates => get_action_states(instance),
+        Request::CaptureInput { timeout_ms } => capture_input(instance, timeout_ms),
+        Request::SetBindings {
+            interaction_profile,
+            bindings,
+        } => set_bindings(instance, &interaction_profile, &bindings),
+        Request::StartRecording { path } => start_recording(instance, path),
+        Request::StopRecording => stop_recording(instance),
+        Request::InjectInput { source, values } => inject_input(instance, &source, &values),
+        Request::OpenInjectionRing => open_injection_ring(instance),
+        Request::OverrideAction {
+            action_set,
+            action,
+            sub_action_path,
+            state,
+            frames,
+        } => override_action(
+            instance,
+            &action_set,
+            &action,
+            sub_action_path.as_deref(),
+            state,
+            frames,
+        ),
+        Request::ClearOverrides { action_set, action } => {
+            clear_overrides(instance, action_set.as_deref(), action.as_deref())
+        }
+        Request::AnalyzeBindings {
+            interaction_profile,
+        } => binding_reports(instance, interaction_profile.as_deref())
+            .map(|reports| Response::BindingReports { reports }),
+    };
+
+    result.unwrap_or_else(Response::error)
+}
+
+fn list_instances() -> Response {
+    use crate::wrappers::XrHandle;
+
+    Response::Instances {
+        instances: xr::Instance::all_wrappers()
+            .iter()
+            .map(|instance| {
+                let info = &instance.application_info;
+                InstanceInfo {
+                    handle: instance.handle.into_raw(),
+                    application_name: crate::str_from_bytes_until_nul(&info.application_name[..])
+                        .unwrap_or_default()
+                        .to_owned(),
+                    application_version: info.application_version,
+                    engine_name: crate::str_from_bytes_until_nul(&info.engine_name[..])
+                        .unwrap_or_default()
+                        .to_owned(),
+                    engine_version: info.engine_version,
+                    runtime: instance.runtime.name().to_owned(),
+                    sessions: instance
+                        .sessions
+                        .iter()
+                        .map(|session| session.key().into_raw())
+                        .collect(),
+                }
+            })
+            .collect(),
+    }
+}
+
+fn actions(action_set: &LayerActionSet) -> Result<Vec<(xr::Action, Arc<LayerAction>)>, String> {
+    let all_actions = layer_action::all();
+    action_set
+        .actions
+        .lock()
+        .iter()
+        .map(|handle| {
+            layer_action::get(&all_actions, *handle)
+                .map(|action| (*handle, action.clone()))
+                .map_err(|err| err.to_string())
+        })
+        .collect()
+}
+
+fn path_string(instance: &InstanceWrapper, path: xr::Path) -> Result<Option<String>, String> {
+    if path == xr::Path::NULL {
+        return Ok(None);
+    }
+    instance
+        .inner
+        .path_to_string(path)
+        .map(Some)
+        .map_err(|err| err.to_string())
+}
+
+fn list_action_sets(instance: &Arc<InstanceWrapper>) -> Result<Response, String> {
+    let all_actions = layer_action::all();
+    let action_sets = instance
+        .action_sets()
+        .iter()
+        .map(|(_, action_set)| manifest::action_set_info(instance, action_set, &all_actions))
+        .collect::<Result<_, _>>()
+        .map_err(|err| err.to_string())?;
+
+    Ok(Response::ActionSets { action_sets })
+}
+
+/// The names of an action's set and of the action itself
+fn action_names(
+    all_action_sets: &Arena<Arc<LayerActionSet>>,
+    all_actions: &Arena<Arc<LayerAction>>,
+    handle: xr::Action,
+) -> Result<(String, String), String> {
+    let action = layer_action::get(all_actions, handle).map_err(|err| err.to_string())?;
+    let action_set = Index::from_bits(action.action_set.into_raw())
+        .and_then(|index| all_action_sets.get(index))
+        .ok_or("action set has been destroyed")?;
+    Ok((action_set.name.clone(), action.name.clone()))
+}
+
+fn list_suggested_bindings(instance: &Arc<InstanceWrapper>) -> Result<Response, String> {
+    let all_action_sets = layer_action_set::all();
+    let all_actions = layer_action::all();
+
+    let names = |handle| action_names(&all_action_sets, &all_actions, handle);
+
+    let mut interaction_profiles = Vec::new();
+
+    for suggested in instance.suggested_bindings.lock().values() {
+        let mut bindings = Vec::new();
+
+        for binding in &suggested.bindings {
+            if let SuggestedBinding::SimpleBinding {
+                action,
+                sub_action_path,
+                binding,
+                ..
+            } = binding
+            {
+                let (action_set, action) = names(xr::Action::from_raw(action.to_bits()))?;
+                bindings.push(Binding {
+                    action_set,
+                    action,
+                    binding: binding.clone(),
+                    sub_action_path: path_string(instance, *sub_action_path)?,
+                });
+            }
+        }
+
+        for (action, binding) in instance
+            .native_actions
+            .bindings_for(&suggested.interaction_profile)
+        {
+            let (action_set, action) = names(action)?;
+            bindings.push(Binding {
+                action_set,
+                action,
+                binding,
+                sub_action_path: None,
+            });
+        }
+
+        interaction_profiles.push(InteractionProfileBindings {
+            interaction_profile: suggested.interaction_profile.clone(),
+            bindings,
+        });
+    }
+
+    interaction_profiles.sort_by(|a, b| a.interaction_profile.cmp(&b.interaction_profile));
+
+    Ok(Response::SuggestedBindings {
+        interaction_profiles,
+    })
+}
+
+fn export_bindings(instance: &Arc<InstanceWrapper>) -> Result<Response, String> {
+    Ok(Response::ExportedBindings {
+        bindings: exported_bindings(instance)?,
+    })
+}
+
+/// The effective bindings of every interaction profile the application suggested
+pub(crate) fn exported_bindings(
+    instance: &Arc<InstanceWrapper>,
+) -> Result<Vec<ExportedBinding>, String> {
+    let all_action_sets = layer_action_set::all();
+    let all_actions = layer_action::all();
+    let mut exported = Vec::new();
+
+    for suggested in instance.suggested_bindings.lock().values() {
+        let interaction_profile = &suggested.interaction_profile;
+
+        for binding in &suggested.bindings {
+            let (action, sub_action_path, binding, mut modifications) = match binding {
+                SuggestedBinding::SimpleBinding {
+                    action,
+                    sub_action_path,
+                    binding,
+                    ..
+                } => (action, sub_action_path, binding.clone(), Vec::new()),
+                SuggestedBinding::Advanced {
+                    action,
+                    sub_action_path,
+                    kind,
+                } => {
+                    let (binding, modification) = kind.exported();
+                    (action, sub_action_path, binding, vec![modification])
+                }
+                _ => continue,
+            };
+
+            let sub_action_path = path_string(instance, *sub_action_path)?;
+            if sub_action_path.is_some()
+                && sub_action_path.as_deref() != user_paths::top_level_path(&binding)
+            {
+                modifications.push(BindingModification::SubactionPath {
+                    sub_action_path: sub_action_path.unwrap_or_default(),
+                });
+            }
+
+            let (action_set, action) = action_names(
+                &all_action_sets,
+                &all_actions,
+                xr::Action::from_raw(action.to_bits()),
+            )?;
+            exported.push(ExportedBinding {
+                interaction_profile: interaction_profile.clone(),
+                action_set,
+                action,
+                binding,
+                modifications,
+            });
+        }
+
+        for (action, binding) in instance.native_actions.bindings_for(interaction_profile) {
+            let (action_set, action) = action_names(&all_action_sets, &all_actions, action)?;
+            exported.push(ExportedBinding {
+                interaction_profile: interaction_profile.clone(),
+                action_set,
+                action,
+                binding,
+                modifications: Vec::new(),
+            });
+        }
+    }
+
+    exported.sort_by(|a, b| {
+        (&a.interaction_profile, &a.action_set, &a.action, &a.binding).cmp(&(
+            &b.interaction_profile,
+            &b.action_set,
+            &b.action,
+            &b.binding,
+        ))
+    });
+
+    Ok(exported)
+}
+
+/// Checks the effective bindings of an interaction profile, by default of those currently bound
+/// to the hands or, before the runtime has chosen any, of every profile the application suggested
+pub(crate) fn binding_reports(
+    instance: &Arc<InstanceWrapper>,
+    interaction_profile: Option<&str>,
+) -> Result<Vec<BindingReport>, String> {
+    let bindings = exported_bindings(instance)?;
+    let all_actions = layer_action::all();
+    let action_sets = instance
+        .action_sets()
+        .iter()
+        .map(|(_, action_set)| manifest::action_set_info(instance, action_set, &all_actions))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| err.to_string())?;
+
+    let mut profiles = Vec::new();
+    match interaction_profile {
+        Some(interaction_profile) => profiles.push(interaction_profile.to_owned()),
+        None => {
+            for user_path in [user_paths::LEFT_HAND, user_paths::RIGHT_HAND] {
+                let user_path = instance
+                    .inner
+                    .string_to_path(user_path)
+                    .map_err(|err| err.to_string())?;
+                for session in instance.sessions.iter() {
+                    if let Ok(Some(current)) = session.current_interaction_profile(user_path) {
+                        profiles.push(current);
+                    }
+                }
+            }
+            if profiles.is_empty() {
+                profiles.extend(
+                    bindings
+                        .iter()
+                        .map(|binding| binding.interaction_profile.clone()),
+                );
+            }
+            profiles.sort();
+            profiles.dedup();
+        }
+    }
+
+    let reserved = instance.profile.reserved_bindings();
+    Ok(profiles
+        .iter()
+        .map(|interaction_profile| {
+            binding_analysis::analyze(interaction_profile, &action_sets, &bindings, &reserved)
+        })
+        .collect())
+}
+
+fn get_action_states(instance: &Arc<InstanceWrapper>) -> Result<Response, String> {
+    let mut states = Vec::new();
+
+    let sessions = instance
+        .sessions
+        .iter()
+        .map(|session| session.value().clone())
+        .collect::<Vec<_>>();
+
+    for (_, action_set) in instance.action_sets() {
+        for (handle, action) in actions(&action_set)? {
+            let sub_action_paths = if action.sub_action_paths.is_empty() {
+                vec![xr::Path::NULL]
+            } else {
+                action.sub_action_paths.clone()
+            };
+
+            for session in &sessions {
+                for sub_action_path in &sub_action_paths {
+                    let (is_active, state) = match action.action_type {
+                        xr::ActionType::BOOLEAN_INPUT => {
+                            let mut state = xr::ActionStateBoolean {
+                                ty: xr::ActionStateBoolean::TYPE,
+                                next: std::ptr::null_mut(),
+                                current_state: false.into(),
+                                changed_since_last_sync: false.into(),
+                                last_change_time: xr::Time::from_nanos(0),
+                                is_active: false.into(),
+                            };
+                            //Sessions which have not attached their action sets yet are skipped
+                            if session
+                                .xr_get_action_state_boolean(handle, *sub_action_path, &mut state)
+                                .is_err()
+                            {
+                                continue;
+                            }
+                            (
+                                state.is_active.into(),
+                                ActionState::Boolean {
+                                    state: state.current_state.into(),
+                                },
+                            )
+                        }
+                        xr::ActionType::FLOAT_INPUT => {
+                            let mut state = xr::ActionStateFloat {
+                                ty: xr::ActionStateFloat::TYPE,
+                                next: std::ptr::null_mut(),
+                                current_state: 0.,
+                                changed_since_last_sync: false.into(),
+                                last_change_time: xr::Time::from_nanos(0),
+                                is_active: false.into(),
+                            };
+                            if session
+                                .xr_get_action_state_float(handle, *sub_action_path, &mut state)
+                                .is_err()
+                            {
+                                continue;
+                            }
+                            (
+                                state.is_active.into(),
+                                ActionState::Float {
+                                    state: state.current_state,
+                                },
+                            )
+                        }
+                        xr::ActionType::VECTOR2F_INPUT => {
+                            let mut state = xr::ActionStateVector2f {
+                                ty: xr::ActionStateVector2f::TYPE,
+                                next: std::ptr::null_mut(),
+                                current_state: Default::default(),
+                                changed_since_last_sync: false.into(),
+                                last_change_time: xr::Time::from_nanos(0),
+                                is_active: false.into(),
+                            };
+                            if session
+                                .xr_get_action_state_vector2f(handle, *sub_action_path, &mut state)
+                                .is_err()
+                            {
+                                continue;
+                            }
+                            (
+                                state.is_active.into(),
+                                ActionState::Vector2f {
+                                    x: state.current_state.x,
+                                    y: state.current_state.y,
+                                },
+                            )
+                        }
+                        xr::ActionType::POSE_INPUT => {
+                            let mut state = xr::ActionStatePose {
+                                ty: xr::ActionStatePose::TYPE,
+                                next: std::ptr::null_mut(),
+                                is_active: false.into(),
+                            };
+                            if session
+                                .xr_get_action_state_pose(handle, *sub_action_path, &mut state)
+                                .is_err()
+                            {
+                                continue;
+                            }
+                            (state.is_active.into(), ActionState::Pose)
+                        }
+                        _ => continue,
+                    };
+
+                    states.push(ActionStateInfo {
+                        session: session.handle.into_raw(),
+                        action_set: action_set.name.clone(),
+                        action: action.name.clone(),
+                        sub_action_path: path_string(instance, *sub_action_path)?,
+                        is_active,
+                        state,
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(Response::ActionStates { states })
+}
+
+fn start_recording(
+    instance: &Arc<InstanceWrapper>,
+    path: Option<String>,
+) -> Result<Response, String> {
+    if instance.recorder.is_recording() {
+        return Err("the instance is already recording".to_owned());
+    }
+    let path = instance
+        .start_recording(path.map(std::path::PathBuf::from))
+        .map_err(|err| err.to_string())?;
+    Ok(Response::RecordingStarted {
+        path: path.display().to_string(),
+    })
+}
+
+fn stop_recording(instance: &Arc<InstanceWrapper>) -> Result<Response, String> {
+    let path = instance
+        .recorder
+        .stop()
+        .ok_or("the instance is not recording")?;
+    Ok(Response::RecordingStopped {
+        path: path.display().to_string(),
+    })
+}
+
+fn inject_input(
+    instance: &Arc<InstanceWrapper>,
+    source: &str,
+    values: &HashMap<String, f32>,
+) -> Result<Response, String> {
+    let virtual_input = instance
+        .input_sources
+        .virtual_input()
+        .ok_or("the profile has no virtual devices")?;
+    for (component, value) in values {
+        virtual_input.inject(&format!("{}{}", source, component), *value)?;
+    }
+    Ok(Response::InputInjected)
+}
+
+fn open_injection_ring(instance: &Arc<InstanceWrapper>) -> Result<Response, String> {
+    use crate::wrappers::XrHandle;
+
+    if instance.input_sources.virtual_input().is_none() {
+        return Err("the profile has no virtual devices".to_owned());
+    }
+    let ring = instance.injection_ring.get_or_try_init(|| {
+        InjectionRing::create(super::ring_path(instance.handle.into_raw()))
+            .map_err(|err| format!("failed to create the injection ring: {}", err))
+    })?;
+    Ok(Response::InjectionRing {
+        path: ring.path().display().to_string(),
+    })
+}
+
+fn override_action(
+    instance: &Arc<InstanceWrapper>,
+    action_set_name: &str,
+    action_name: &str,
+    sub_action_path: Option<&str>,
+    state: OverrideState,
+    frames: Option<u32>,
+) -> Result<Response, String> {
+    let (_, action_set) = instance
+        .action_sets()
+        .into_iter()
+        .find(|(_, action_set)| action_set.name == action_set_name)
+        .ok_or_else(|| format!("unknown action set `{}`", action_set_name))?;
+    let (handle, action) = actions(&action_set)?
+        .into_iter()
+        .find(|(_, action)| action.name == action_name)
+        .ok_or_else(|| format!("unknown action `{}/{}`", action_set_name, action_name))?;
+
+    let matches_type = match state {
+        OverrideState::Boolean { .. } => action.action_type == xr::ActionType::BOOLEAN_INPUT,
+        OverrideState::Float { .. } => action.action_type == xr::ActionType::FLOAT_INPUT,
+        OverrideState::Vector2f { .. } => action.action_type == xr::ActionType::VECTOR2F_INPUT,
+        OverrideState::Inactive => action.action_type != xr::ActionType::VIBRATION_OUTPUT,
+    };
+    if !matches_type {
+        return Err(format!(
+            "`{}/{}` cannot be overridden with that state",
+            action_set_name, action_name
+        ));
+    }
+    if frames == Some(0) {
+        return Err("an override must last at least one frame".to_owned());
+    }
+
+    let sub_action_path = match sub_action_path {
+        Some(name) => {
+            let path = instance
+                .inner
+                .string_to_path(name)
+                .map_err(|err| err.to_string())?;
+            if !action.sub_action_paths.contains(&path) {
+                return Err(format!(
+                    "`{}/{}` has no subaction path `{}`",
+                    action_set_name, action_name, name
+                ));
+            }
+            path
+        }
+        None => xr::Path::NULL,
+    };
+
+    instance
+        .action_overrides
+        .set(handle, sub_action_path, state, frames);
+    Ok(Response::ActionOverridden)
+}
+
+fn clear_overrides(
+    instance: &Arc<InstanceWrapper>,
+    action_set_name: Option<&str>,
+    action_name: Option<&str>,
+) -> Result<Response, String> {
+    let all_action_sets = layer_action_set::all();
+    let all_actions = layer_action::all();
+
+    let count = instance.action_overrides.clear(|handle| {
+        match action_names(&all_action_sets, &all_actions, handle) {
+            Ok((action_set, action)) => {
+                action_set_name.map_or(true, |name| name == action_set)
+                    && action_name.map_or(true, |name| name == action)
+            }
+            //Overrides of destroyed actions are dropped as well
+            Err(_) => true,
+        }
+    });
+    Ok(Response::OverridesCleared { count })
+}
+
+fn capture_input(instance: &Arc<InstanceWrapper>, timeout_ms: u64) -> Result<Response, String> {
+    let receiver = instance.input_capture.start();
+    let action = match receiver.recv_timeout(std::time::Duration::from_millis(timeout_ms)) {
+        Ok(action) => action,
+        Err(_) => {
+            instance.input_capture.cancel();
+            return Err("no input was pressed in time".to_owned());
+        }
+    };
+
+    let binding = instance
+        .layer_inputs
+        .capture
+        .iter()
+        .find(|(capture_action, _)| *capture_action == action)
+        .map(|(_, binding)| binding.clone())
+        .ok_or("captured an unknown action")?;
+
+    let top_level_path = user_paths::top_level_path(&binding).ok_or("invalid binding path")?;
+    let top_level_path = instance
+        .inner
+        .string_to_path(top_level_path)
+        .map_err(|err| err.to_string())?;
+    let interaction_profile = instance.sessions.iter().find_map(|session| {
+        session
+            .current_interaction_profile(top_level_path)
+            .ok()
+            .flatten()
+    });
+
+    Ok(Response::CapturedInput {
+        binding,
+        interaction_profile,
+    })
+}
+
+fn set_bindings(
+    instance: &Arc<InstanceWrapper>,
+    interaction_profile: &str,
+    bindings: &[Binding],
+) -> Result<Response, String> {
+    let su_interaction_profile = instance
+        .suinput_instance
+        .get_path(interaction_profile)
+        .map_err(|_| format!("unsupported interaction profile `{}`", interaction_profile))?;
+    let known_profile = interaction_profiles::get(interaction_profile);
+
+    let action_sets = instance.action_sets();
+    let mut suggested = Vec::with_capacity(bindings.len());
+
+    for binding in bindings {
+        let (_, action_set) = action_sets
+            .iter()
+            .find(|(_, action_set)| action_set.name == binding.action_set)
+            .ok_or_else(|| format!("unknown action set `{}`", binding.action_set))?;
+        let (handle, action) = actions(action_set)?
+            .into_iter()
+            .find(|(_, action)| action.name == binding.action)
+            .ok_or_else(|| format!("unknown action `{}/{}`", binding.action_set, binding.action))?;
+
+        if matches!(
+            action.action_type,
+            xr::ActionType::POSE_INPUT | xr::ActionType::VIBRATION_OUTPUT
+        ) {
+            return Err(format!(
+                "pose and haptic actions are bound by the runtime and cannot be changed: `{}/{}`",
+                binding.action_set, binding.action
+            ));
+        }
+
+        if let Some(profile) = known_profile {
+            if !profile.has_binding(&binding.binding)
+                && instance
+                    .profile
+                    .virtual_component(&binding.binding)
+                    .is_none()
+            {
+                return Err(format!(
+                    "`{}` does not exist on `{}`",
+                    binding.binding, interaction_profile
+                ));
+            }
+        }
+
+        let sub_action_path = match &binding.sub_action_path {
+            Some(path) => path.as_str(),
+            None => user_paths::top_level_path(&binding.binding)
+                .ok_or_else(|| format!("invalid binding path `{}`", binding.binding))?,
+        };
+        let sub_action_path = instance
+            .inner
+            .string_to_path(sub_action_path)
+            .map_err(|err| err.to_string())?;
+        if !action.sub_action_paths.is_empty()
+            && !action.sub_action_paths.contains(&sub_action_path)
+        {
+            return Err(format!(
+                "`{}/{}` has no subaction path for `{}`",
+                binding.action_set, binding.action, binding.binding
+            ));
+        }
+
+        suggested.push(SuggestedBinding::SimpleBinding {
+            action: Index::from_bits(handle.into_raw()).ok_or("invalid action handle")?,
+            sub_action_path,
+            binding: binding.binding.clone(),
+            folded: instance
+                .is_folded(sub_action_path)
+                .map_err(|err| err.to_string())?,
+        });
+    }
+
+    //Advanced bindings come from the profile and are kept
+    if let Some(current) = instance
+        .suggested_bindings
+        .lock()
+        .get(&su_interaction_profile)
+    {
+        suggested.extend(
+            current
+                .bindings
+                .iter()
+                .filter(|binding| matches!(binding, SuggestedBinding::Advanced { .. }))
+                .cloned(),
+        );
+    }
+
+    instance.pending_bindings.lock().insert(
+        su_interaction_profile,
+        SuggestedBindings {
+            interaction_profile: interaction_profile.to_owned(),
+            bindings: suggested,
+        },
+    );
+
+    Ok(Response::BindingsQueued)
+}
@@ -0,0 +1,269 @@
+//! A ring of input values kept in a file in the runtime directory, for processes which inject
+//! input too often to send a request over the control socket for every value. Both sides use
+//! positional reads and writes on the file rather than mapping it.
+//!
+//! The file starts with a header of the magic number, the format version and the number of
+//! records written so far, followed by [`CAPACITY`] slots. Record `n` is written to slot
+//! `n % CAPACITY` and carries `n + 1` as its sequence number. The writer clears the sequence
+//! number, writes the rest of the slot, stores the sequence number and then increments the count
+//! in the header. The layer reads the sequence number again after the rest of the slot and skips
+//! records whose sequence number does not match either time, as they have been overwritten or
+//! were being written. There is one writer at a time and all integers are
+//! little endian.
+
+use std::{fs::File, path::PathBuf};
+
+use parking_lot::Mutex;
+
+pub const MAGIC: u32 = u32::from_le_bytes(*b"SUIJ");
+pub const FORMAT_VERSION: u32 = 1;
+pub const CAPACITY: u64 = 256;
+/// The longest binding path a record can hold
+pub const MAX_BINDING_LEN: usize = 128;
+
+const HEADER_SIZE: u64 = 16;
+const COUNT_OFFSET: u64 = 8;
+/// Sequence number, value, binding length, padding and the binding path
+const SLOT_SIZE: u64 = 8 + 4 + 2 + 2 + MAX_BINDING_LEN as u64;
+
+fn slot_offset(record: u64) -> u64 {
+    HEADER_SIZE + (record % CAPACITY) * SLOT_SIZE
+}
+
+/// The layer's side of the ring buffer, removed again when dropped
+pub struct InjectionRing {
+    path: PathBuf,
+    file: File,
+    /// The number of records read so far
+    read: Mutex<u64>,
+}
+
+impl InjectionRing {
+    #[cfg(unix)]
+    pub fn create(path: PathBuf) -> std::io::Result<Self> {
+        use std::os::unix::fs::{FileExt, OpenOptionsExt};
+
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&path)?;
+        file.set_len(HEADER_SIZE + CAPACITY * SLOT_SIZE)?;
+        let mut header = [0; HEADER_SIZE as usize];
+        header[..4].copy_from_slice(&MAGIC.to_le_bytes());
+        header[4..8].copy_from_slice(&FORMAT_VERSION.to_le_bytes());
+        file.write_all_at(&header, 0)?;
+
+        Ok(Self {
+            path,
+            file,
+            read: Mutex::new(0),
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn create(_path: PathBuf) -> std::io::Result<Self> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "the injection ring requires positional file access",
+        ))
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    /// The records written since the last poll, oldest first. Records the writer has already
+    /// overwritten are lost.
+    #[cfg(unix)]
+    pub fn poll(&self) -> std::io::Result<Vec<(String, f32)>> {
+        use std::os::unix::fs::FileExt;
+
+        let mut count = [0; 8];
+        self.file.read_exact_at(&mut count, COUNT_OFFSET)?;
+        let count = u64::from_le_bytes(count);
+
+        let mut read = self.read.lock();
+        let start = (*read).max(count.saturating_sub(CAPACITY));
+        let mut records = Vec::new();
+        let mut slot = [0; SLOT_SIZE as usize];
+        let mut sequence = [0; 8];
+        for record in start..count {
+            self.file.read_exact_at(&mut slot, slot_offset(record))?;
+            let decoded = match decode_slot(&slot, record) {
+                Some(decoded) => decoded,
+                None => continue,
+            };
+            //The writer may have started on the slot again while it was read
+            self.file
+                .read_exact_at(&mut sequence, slot_offset(record))?;
+            if u64::from_le_bytes(sequence) == record + 1 {
+                records.push(decoded);
+            }
+        }
+        *read = count;
+        Ok(records)
+    }
+
+    #[cfg(not(unix))]
+    pub fn poll(&self) -> std::io::Result<Vec<(String, f32)>> {
+        Ok(Vec::new())
+    }
+}
+
+impl Drop for InjectionRing {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn decode_slot(slot: &[u8; SLOT_SIZE as usize], record: u64) -> Option<(String, f32)> {
+    let sequence = u64::from_le_bytes(slot[..8].try_into().ok()?);
+    if sequence != record + 1 {
+        return None;
+    }
+    let value = f32::from_le_bytes(slot[8..12].try_into().ok()?);
+    let len = u16::from_le_bytes(slot[12..14].try_into().ok()?) as usize;
+    let binding = std::str::from_utf8(slot[16..].get(..len)?).ok()?;
+    Some((binding.to_owned(), value))
+}
+
+/// The injecting process's side of the ring buffer
+pub struct RingWriter {
+    file: File,
+}
+
+impl RingWriter {
+    #[cfg(unix)]
+    pub fn open(path: &std::path::Path) -> std::io::Result<Self> {
+        use std::os::unix::fs::FileExt;
+
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)?;
+        let mut header = [0; 8];
+        file.read_exact_at(&mut header, 0)?;
+        if header[..4] != MAGIC.to_le_bytes() || header[4..] != FORMAT_VERSION.to_le_bytes() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "not an injection ring of a supported version",
+            ));
+        }
+        Ok(Self { file })
+    }
+
+    #[cfg(not(unix))]
+    pub fn open(_path: &std::path::Path) -> std::io::Result<Self> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "the injection ring requires positional file access",
+        ))
+    }
+
+    /// Appends a value for an input such as `/user/virtual/pedal/input/pedal/value`
+    #[cfg(unix)]
+    pub fn write(&self, binding: &str, value: f32) -> std::io::Result<()> {
+        use std::os::unix::fs::FileExt;
+
+        if binding.len() > MAX_BINDING_LEN {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("binding paths are limited to {} bytes", MAX_BINDING_LEN),
+            ));
+        }
+
+        let mut count = [0; 8];
+        self.file.read_exact_at(&mut count, COUNT_OFFSET)?;
+        let record = u64::from_le_bytes(count);
+
+        let mut slot = [0; SLOT_SIZE as usize];
+        slot[8..12].copy_from_slice(&value.to_le_bytes());
+        slot[12..14].copy_from_slice(&(binding.len() as u16).to_le_bytes());
+        slot[16..16 + binding.len()].copy_from_slice(binding.as_bytes());
+        let offset = slot_offset(record);
+        self.file.write_all_at(&0u64.to_le_bytes(), offset)?;
+        self.file.write_all_at(&slot[8..], offset + 8)?;
+        self.file
+            .write_all_at(&(record + 1).to_le_bytes(), offset)?;
+        self.file
+            .write_all_at(&(record + 1).to_le_bytes(), COUNT_OFFSET)
+    }
+
+    #[cfg(not(unix))]
+    pub fn write(&self, _binding: &str, _value: f32) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "the injection ring requires positional file access",
+        ))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn ring(name: &str) -> (InjectionRing, RingWriter) {
+        let path =
+            std::env::temp_dir().join(format!("injection-ring-{}-{}", std::process::id(), name));
+        let ring = InjectionRing::create(path).unwrap();
+        let writer = RingWriter::open(ring.path()).unwrap();
+        (ring, writer)
+    }
+
+    #[test]
+    fn written_slots_decode() {
+        use std::os::unix::fs::FileExt;
+
+        let (ring, writer) = ring("decode");
+        writer
+            .write("/user/virtual/pedal/input/pedal/value", 0.25)
+            .unwrap();
+        writer.write("/user/hand/left/input/x/click", 1.).unwrap();
+
+        let mut slot = [0; SLOT_SIZE as usize];
+        ring.file.read_exact_at(&mut slot, slot_offset(1)).unwrap();
+        assert_eq!(
+            decode_slot(&slot, 1),
+            Some(("/user/hand/left/input/x/click".to_owned(), 1.))
+        );
+        //A slot is only valid for the record that was written to it
+        assert_eq!(decode_slot(&slot, 1 + CAPACITY), None);
+    }
+
+    #[test]
+    fn poll_returns_new_records() {
+        let (ring, writer) = ring("poll");
+        writer
+            .write("/user/virtual/pedal/input/pedal/value", 0.5)
+            .unwrap();
+        assert_eq!(
+            ring.poll().unwrap(),
+            [("/user/virtual/pedal/input/pedal/value".to_owned(), 0.5)]
+        );
+        assert!(ring.poll().unwrap().is_empty());
+    }
+
+    #[test]
+    fn poll_skips_overwritten_records() {
+        let (ring, writer) = ring("overwritten");
+        for i in 0..CAPACITY + 2 {
+            writer
+                .write("/user/virtual/pedal/input/pedal/value", i as f32)
+                .unwrap();
+        }
+        let records = ring.poll().unwrap();
+        assert_eq!(records.len(), CAPACITY as usize);
+        assert_eq!(records[0].1, 2.);
+        assert_eq!(records.last().unwrap().1, (CAPACITY + 1) as f32);
+    }
+
+    #[test]
+    fn long_bindings_are_rejected() {
+        let (_ring, writer) = ring("long");
+        let binding = "a".repeat(MAX_BINDING_LEN + 1);
+        assert!(writer.write(&binding, 1.).is_err());
+    }
+}
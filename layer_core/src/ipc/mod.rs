@@ -0,0 +1,104 @@
+pub(crate) mod handler;
+pub mod injection;
+pub mod protocol;
+mod server;
+
+use std::path::PathBuf;
+
+pub use server::IpcServer;
+
+/// Control sockets are kept in `<runtime dir>/suinput_layer`, one per instance
+pub fn socket_dir() -> PathBuf {
+    dirs::runtime_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("suinput_layer")
+}
+
+/// Creates the socket directory accessible by the current user only. An existing directory must
+/// not be accessible by other users either, as anyone who can connect to the sockets controls the
+/// input of every application.
+#[cfg(unix)]
+fn create_socket_dir() -> std::io::Result<PathBuf> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    let dir = socket_dir();
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&dir)?;
+
+    let metadata = std::fs::symlink_metadata(&dir)?;
+    if !metadata.is_dir() || metadata.permissions().mode() & 0o077 != 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            format!(
+                "`{}` must be a directory only its owner can access",
+                dir.display()
+            ),
+        ));
+    }
+    Ok(dir)
+}
+
+pub fn socket_path(instance: u64) -> PathBuf {
+    socket_dir().join(format!("{}-{}.sock", std::process::id(), instance))
+}
+
+/// The injection ring of an instance, next to its control socket
+pub fn ring_path(instance: u64) -> PathBuf {
+    socket_dir().join(format!("{}-{}.ring", std::process::id(), instance))
+}
+
+/// The sockets of every running instance, stale sockets of crashed processes included
+pub fn socket_paths() -> Vec<PathBuf> {
+    let mut paths = std::fs::read_dir(socket_dir())
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().map_or(false, |ext| ext == "sock"))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    paths.sort();
+    paths
+}
+
+/// Sends a single request to the instance listening on `path`
+#[cfg(unix)]
+pub fn request(
+    path: &std::path::Path,
+    request: protocol::Request,
+) -> std::io::Result<protocol::Response> {
+    use std::io::{BufRead, BufReader, Write};
+
+    let mut stream = std::os::unix::net::UnixStream::connect(path)?;
+    let mut message = serde_json::to_vec(&protocol::RequestMessage {
+        version: protocol::PROTOCOL_VERSION,
+        request,
+    })?;
+    message.push(b'\n');
+    stream.write_all(&message)?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    let response: protocol::ResponseMessage = serde_json::from_str(&line)?;
+    if response.version != protocol::PROTOCOL_VERSION {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("unsupported protocol version {}", response.version),
+        ));
+    }
+    Ok(response.response)
+}
+
+#[cfg(not(unix))]
+pub fn request(
+    _path: &std::path::Path,
+    _request: protocol::Request,
+) -> std::io::Result<protocol::Response> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "the control socket requires unix domain sockets",
+    ))
+}
@@ -0,0 +1,320 @@
+//! The JSON protocol spoken over the control socket.
+//!
+//! Every message is a single line of JSON. Clients send a [`RequestMessage`] and receive exactly
+//! one [`ResponseMessage`] in return. Messages carry [`PROTOCOL_VERSION`] and are rejected when
+//! the versions differ.
+
+use std::collections::HashMap;
+
+use openxr::sys as xr;
+use serde::{Deserialize, Serialize};
+
+pub const PROTOCOL_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestMessage {
+    pub version: u32,
+    pub request: Request,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseMessage {
+    pub version: u32,
+    pub response: Response,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    ListInstances,
+    ListActionSets,
+    ListSuggestedBindings,
+    /// The effective bindings after remapping, in terms the application could suggest itself
+    ExportBindings,
+    GetActionStates,
+    /// Waits for the user to press a button or trigger and returns its path
+    CaptureInput {
+        timeout_ms: u64,
+    },
+    /// Replaces the bindings of every boolean, float and vector2f action for an interaction
+    /// profile, applied at the next `xrSyncActions`
+    SetBindings {
+        interaction_profile: String,
+        bindings: Vec<Binding>,
+    },
+    /// Records physical inputs, action states and locations to `path`, or a file in the data
+    /// directory if none is given
+    StartRecording {
+        #[serde(default)]
+        path: Option<String>,
+    },
+    StopRecording,
+    /// Sets external inputs of a virtual device at the next `xrSyncActions`. `source` is the top
+    /// level path of the device and `values` are keyed by component, e.g. `/input/pedal/value`.
+    InjectInput {
+        source: String,
+        values: HashMap<String, f32>,
+    },
+    /// Creates the injection ring of the instance if needed and returns its path, see
+    /// [`super::injection`]
+    OpenInjectionRing,
+    /// Forces an action to a state from the next `xrSyncActions` on, regardless of its bindings.
+    /// Without a subaction path every subaction path is overridden, without `frames` the
+    /// override lasts until cleared.
+    OverrideAction {
+        action_set: String,
+        action: String,
+        #[serde(default)]
+        sub_action_path: Option<String>,
+        state: OverrideState,
+        #[serde(default)]
+        frames: Option<u32>,
+    },
+    /// Removes the overrides of an action set or action, or all of them if neither is given
+    ClearOverrides {
+        #[serde(default)]
+        action_set: Option<String>,
+        #[serde(default)]
+        action: Option<String>,
+    },
+    /// Checks the effective bindings of an interaction profile, or of the profiles currently
+    /// bound to the hands if none is given
+    AnalyzeBindings {
+        #[serde(default)]
+        interaction_profile: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Response {
+    Instances {
+        instances: Vec<InstanceInfo>,
+    },
+    ActionSets {
+        action_sets: Vec<ActionSetInfo>,
+    },
+    SuggestedBindings {
+        interaction_profiles: Vec<InteractionProfileBindings>,
+    },
+    ExportedBindings {
+        bindings: Vec<ExportedBinding>,
+    },
+    ActionStates {
+        states: Vec<ActionStateInfo>,
+    },
+    BindingsQueued,
+    CapturedInput {
+        binding: String,
+        /// The interaction profile of the controller the input was pressed on
+        interaction_profile: Option<String>,
+    },
+    RecordingStarted {
+        path: String,
+    },
+    RecordingStopped {
+        path: String,
+    },
+    InputInjected,
+    InjectionRing {
+        path: String,
+    },
+    ActionOverridden,
+    OverridesCleared {
+        count: usize,
+    },
+    BindingReports {
+        reports: Vec<BindingReport>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+impl Response {
+    pub fn error(message: impl ToString) -> Self {
+        Response::Error {
+            message: message.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceInfo {
+    pub handle: u64,
+    pub application_name: String,
+    pub application_version: u32,
+    pub engine_name: String,
+    pub engine_version: u32,
+    pub runtime: String,
+    pub sessions: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionSetInfo {
+    pub name: String,
+    #[serde(default)]
+    pub localized_name: String,
+    /// Sets of a higher priority hide the bindings they share with sets of a lower one
+    #[serde(default)]
+    pub priority: u32,
+    pub actions: Vec<ActionInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionInfo {
+    pub name: String,
+    #[serde(default)]
+    pub localized_name: String,
+    pub action_type: ActionType,
+    pub sub_action_paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionType {
+    Boolean,
+    Float,
+    Vector2f,
+    Pose,
+    Vibration,
+}
+
+impl ActionType {
+    pub fn from_xr(action_type: xr::ActionType) -> Option<Self> {
+        Some(match action_type {
+            xr::ActionType::BOOLEAN_INPUT => ActionType::Boolean,
+            xr::ActionType::FLOAT_INPUT => ActionType::Float,
+            xr::ActionType::VECTOR2F_INPUT => ActionType::Vector2f,
+            xr::ActionType::POSE_INPUT => ActionType::Pose,
+            xr::ActionType::VIBRATION_OUTPUT => ActionType::Vibration,
+            _ => return None,
+        })
+    }
+
+    pub fn to_xr(self) -> xr::ActionType {
+        match self {
+            ActionType::Boolean => xr::ActionType::BOOLEAN_INPUT,
+            ActionType::Float => xr::ActionType::FLOAT_INPUT,
+            ActionType::Vector2f => xr::ActionType::VECTOR2F_INPUT,
+            ActionType::Pose => xr::ActionType::POSE_INPUT,
+            ActionType::Vibration => xr::ActionType::VIBRATION_OUTPUT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InteractionProfileBindings {
+    pub interaction_profile: String,
+    pub bindings: Vec<Binding>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Binding {
+    pub action_set: String,
+    pub action: String,
+    /// The physical input path, e.g. `/user/hand/right/input/a/click`
+    pub binding: String,
+    /// The subaction path the application reads the binding through, defaults to the top level
+    /// path of `binding`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub_action_path: Option<String>,
+}
+
+/// A binding as the application would suggest it to get the same input without the layer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportedBinding {
+    pub interaction_profile: String,
+    pub action_set: String,
+    pub action: String,
+    pub binding: String,
+    /// The `XR_KHR_binding_modification` structures to chain onto the suggestion, empty for plain
+    /// bindings
+    pub modifications: Vec<BindingModification>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BindingModification {
+    /// `XrInteractionProfileAnalogThresholdVALVE`
+    AnalogThreshold {
+        on_threshold: f32,
+        off_threshold: f32,
+    },
+    /// `XrInteractionProfileDpadBindingEXT`, the binding path ends in the dpad direction
+    DpadBinding {
+        center_region: f32,
+        wedge_angle: f32,
+        is_sticky: bool,
+        /// An input which must be held as well, this has no `XR_EXT_dpad_binding` equivalent
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        activator: Option<String>,
+    },
+    /// Only active while `modifier` is held, this has no OpenXR equivalent
+    Modifier { modifier: String },
+    /// The input reaches the application through another subaction path than the top level path
+    /// of the binding, this has no OpenXR equivalent
+    SubactionPath { sub_action_path: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionStateInfo {
+    pub session: u64,
+    pub action_set: String,
+    pub action: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub_action_path: Option<String>,
+    pub is_active: bool,
+    pub state: ActionState,
+}
+
+/// The state as the application sees it
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ActionState {
+    Boolean { state: bool },
+    Float { state: f32 },
+    Vector2f { x: f32, y: f32 },
+    Pose,
+}
+
+/// A state forced onto an action, the variant must match the type of the action unless it is
+/// `Inactive`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OverrideState {
+    Boolean {
+        state: bool,
+    },
+    Float {
+        state: f32,
+    },
+    Vector2f {
+        x: f32,
+        y: f32,
+    },
+    /// The action is inactive and the spaces of pose actions cannot be located
+    Inactive,
+}
+
+/// Problems with the effective bindings of an application on one interaction profile
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BindingReport {
+    pub interaction_profile: String,
+    /// Actions without any binding on the profile, as `<action set>/<action>`
+    pub unbound_actions: Vec<String>,
+    pub conflicts: Vec<BindingConflict>,
+    /// Buttons, triggers and sticks of the profile which neither the application nor the layer
+    /// reads, e.g. `/user/hand/left/input/x`
+    pub unused_inputs: Vec<String>,
+}
+
+/// A physical input bound to several actions of the same priority, which all receive it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BindingConflict {
+    /// The identifier of the input, e.g. `/user/hand/left/input/trigger`
+    pub binding: String,
+    pub priority: u32,
+    /// As `<action set>/<action>`
+    pub actions: Vec<String>,
+}
@@ -0,0 +1,140 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Weak,
+    },
+};
+
+use crate::wrappers::instance::InstanceWrapper;
+
+/// Serves the control socket of an instance on a background thread until dropped
+pub struct IpcServer {
+    path: PathBuf,
+    running: Arc<AtomicBool>,
+}
+
+impl IpcServer {
+    #[cfg(unix)]
+    pub fn start(instance: Weak<InstanceWrapper>, handle: u64) -> std::io::Result<Self> {
+        use std::os::unix::{fs::MetadataExt, net::UnixListener};
+
+        let dir = super::create_socket_dir()?;
+        let path = super::socket_path(handle);
+        let _ = std::fs::remove_file(&path);
+
+        let listener = UnixListener::bind(&path)?;
+        //Only the owner of the directory may listen in it
+        if std::fs::metadata(&path)?.uid() != std::fs::symlink_metadata(&dir)?.uid() {
+            let _ = std::fs::remove_file(&path);
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                format!("`{}` belongs to another user", dir.display()),
+            ));
+        }
+        //Polled so the thread notices when the server is dropped
+        listener.set_nonblocking(true)?;
+
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_running = running.clone();
+        std::thread::Builder::new()
+            .name("suinput_layer ipc".into())
+            .spawn(move || accept_loop(listener, instance, thread_running))?;
+
+        Ok(Self { path, running })
+    }
+
+    #[cfg(not(unix))]
+    pub fn start(_instance: Weak<InstanceWrapper>, _handle: u64) -> std::io::Result<Self> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "the control socket requires unix domain sockets",
+        ))
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(unix)]
+fn accept_loop(
+    listener: std::os::unix::net::UnixListener,
+    instance: Weak<InstanceWrapper>,
+    running: Arc<AtomicBool>,
+) {
+    use log::warn;
+
+    while running.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                let instance = instance.clone();
+                let spawned = std::thread::Builder::new()
+                    .name("suinput_layer ipc client".into())
+                    .spawn(move || {
+                        if let Err(err) = handle_connection(stream, instance) {
+                            warn!("IPC connection failed: {}", err);
+                        }
+                    });
+                if let Err(err) = spawned {
+                    warn!("Failed to spawn IPC connection thread: {}", err);
+                }
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
+                std::thread::sleep(std::time::Duration::from_millis(100))
+            }
+            Err(err) => {
+                warn!("IPC socket closed: {}", err);
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn handle_connection(
+    stream: std::os::unix::net::UnixStream,
+    instance: Weak<InstanceWrapper>,
+) -> std::io::Result<()> {
+    use std::io::{BufRead, BufReader, Write};
+
+    use super::protocol::{RequestMessage, Response, ResponseMessage, PROTOCOL_VERSION};
+
+    stream.set_nonblocking(false)?;
+    let mut writer = stream.try_clone()?;
+
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<RequestMessage>(&line) {
+            Ok(message) if message.version != PROTOCOL_VERSION => Response::error(format!(
+                "unsupported protocol version {}, expected {}",
+                message.version, PROTOCOL_VERSION
+            )),
+            Ok(message) => match instance.upgrade() {
+                Some(instance) => super::handler::handle(&instance, message.request),
+                None => Response::error("instance has been destroyed"),
+            },
+            Err(err) => Response::error(format!("invalid request: {}", err)),
+        };
+
+        let mut message = serde_json::to_vec(&ResponseMessage {
+            version: PROTOCOL_VERSION,
+            response,
+        })?;
+        message.push(b'\n');
+        writer.write_all(&message)?;
+    }
+
+    Ok(())
+}
@@ -1,21 +1,39 @@
 #![feature(cstr_from_bytes_until_nul)]
 
+mod debug_utils;
 mod entry;
 mod input;
+pub mod ipc;
+pub mod manifest;
+pub mod math;
 pub mod openxr_overrides;
+pub mod profile;
+pub mod recording;
+pub mod steamvr;
 pub mod wrappers;
 
-use std::ffi::CStr;
+use std::{ffi::CStr, path::PathBuf};
 
 use openxr::sys as xr;
 
 pub const LAYER_NAME: &str = "XR_APILAYER_SORENON_suinput_layer";
 
+/// Where profiles and other persistent layer data are kept, overridable with `SUINPUT_LAYER_DATA_DIR`
+pub fn data_dir() -> PathBuf {
+    match std::env::var_os("SUINPUT_LAYER_DATA_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => dirs::data_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("suinput_layer"),
+    }
+}
+
 pub fn initialize() -> (
     xr::pfn::GetInstanceProcAddr,
     xr::loader_interfaces::FnCreateApiLayerInstance,
 ) {
     wrappers::initialize();
+    input::driver_hooks::initialize();
     (
         openxr_overrides::get_instance_proc_addr,
         entry::create_api_layer_instance,
@@ -65,7 +83,7 @@ mod ffi_helpers {
         }
     }
     
-    fn place_cstr(out: &mut [std::os::raw::c_char], s: &str) {
+    pub fn place_cstr(out: &mut [std::os::raw::c_char], s: &str) {
         if s.len() + 1 > out.len() {
             panic!(
                 "string requires {} > {} bytes (including trailing null)",
@@ -0,0 +1,554 @@
+use std::{collections::HashMap, fs, path::PathBuf, sync::Arc};
+
+use openxr::sys as xr;
+use serde::{Deserialize, Serialize};
+use thunderdome::Arena;
+
+use crate::{
+    input::{binding_analysis, translation, user_paths},
+    ipc::protocol::{
+        ActionInfo, ActionSetInfo, ActionType, Binding, BindingReport, ExportedBinding,
+        InteractionProfileBindings,
+    },
+    profile::{self, ProfileError, RemapProfile},
+    wrappers::{
+        instance::InstanceWrapper,
+        layer_action::{self, LayerAction},
+        layer_action_set::LayerActionSet,
+    },
+};
+
+/// The actions and suggested bindings of one version of an application, written when it attaches
+/// its action sets so profiles can be edited without the application running
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionManifest {
+    pub application_name: String,
+    pub application_version: u32,
+    #[serde(default)]
+    pub engine_name: String,
+    #[serde(default)]
+    pub engine_version: u32,
+    pub action_sets: Vec<ActionSetInfo>,
+    /// The bindings as suggested by the application, before the profile is applied
+    pub interaction_profiles: Vec<InteractionProfileBindings>,
+}
+
+/// Manifests are stored as `<data dir>/manifests/<application name>/<application version>.json`
+pub fn application_dir(application_name: &str) -> PathBuf {
+    crate::data_dir()
+        .join("manifests")
+        .join(profile::sanitize_file_name(application_name))
+}
+
+pub fn manifest_path(application_name: &str, application_version: u32) -> PathBuf {
+    application_dir(application_name).join(format!("{}.json", application_version))
+}
+
+/// The versions of an application which have a manifest, oldest first
+pub fn list_versions(application_name: &str) -> Vec<u32> {
+    let mut versions = fs::read_dir(application_dir(application_name))
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| {
+                    let path = entry.path();
+                    if path.extension()? != "json" {
+                        return None;
+                    }
+                    path.file_stem()?.to_str()?.parse().ok()
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    versions.sort_unstable();
+    versions
+}
+
+pub(crate) fn action_set_info(
+    instance: &InstanceWrapper,
+    action_set: &LayerActionSet,
+    all_actions: &Arena<Arc<LayerAction>>,
+) -> Result<ActionSetInfo, xr::Result> {
+    let mut actions = Vec::new();
+    for handle in action_set.actions.lock().iter() {
+        let action = layer_action::get(all_actions, *handle)?;
+        actions.push(ActionInfo {
+            name: action.name.clone(),
+            localized_name: action.localized_name.clone(),
+            action_type: ActionType::from_xr(action.action_type)
+                .ok_or(xr::Result::ERROR_VALIDATION_FAILURE)?,
+            sub_action_paths: action
+                .sub_action_paths
+                .iter()
+                .map(|path| instance.inner.path_to_string(*path))
+                .collect::<Result<_, _>>()?,
+        });
+    }
+
+    Ok(ActionSetInfo {
+        name: action_set.name.clone(),
+        localized_name: action_set.localized_name.clone(),
+        priority: action_set.priority,
+        actions,
+    })
+}
+
+impl ActionManifest {
+    pub fn new(
+        instance: &InstanceWrapper,
+        action_sets: &HashMap<xr::ActionSet, Arc<LayerActionSet>>,
+    ) -> Result<Self, xr::Result> {
+        let info = &instance.application_info;
+        let all_actions = layer_action::all();
+
+        let mut action_set_infos = action_sets
+            .values()
+            .map(|action_set| action_set_info(instance, action_set, &all_actions))
+            .collect::<Result<Vec<_>, _>>()?;
+        action_set_infos.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut interaction_profiles = Vec::new();
+        for (interaction_profile, suggested) in instance.application_bindings.lock().iter() {
+            let mut bindings = Vec::with_capacity(suggested.len());
+            for (handle, binding) in suggested {
+                //Actions of sets which were never attached are left out
+                let action = match layer_action::get(&all_actions, *handle) {
+                    Ok(action) => action,
+                    Err(_) => continue,
+                };
+                let action_set = match action_sets.get(&action.action_set) {
+                    Some(action_set) => action_set,
+                    None => continue,
+                };
+                bindings.push(Binding {
+                    action_set: action_set.name.clone(),
+                    action: action.name.clone(),
+                    binding: binding.clone(),
+                    sub_action_path: None,
+                });
+            }
+            interaction_profiles.push(InteractionProfileBindings {
+                interaction_profile: interaction_profile.clone(),
+                bindings,
+            });
+        }
+        interaction_profiles.sort_by(|a, b| a.interaction_profile.cmp(&b.interaction_profile));
+
+        Ok(ActionManifest {
+            application_name: crate::str_from_bytes_until_nul(&info.application_name[..])?
+                .to_owned(),
+            application_version: info.application_version,
+            engine_name: crate::str_from_bytes_until_nul(&info.engine_name[..])?.to_owned(),
+            engine_version: info.engine_version,
+            action_sets: action_set_infos,
+            interaction_profiles,
+        })
+    }
+
+    pub fn load(path: &std::path::Path) -> Result<Self, ProfileError> {
+        let file = fs::File::open(path).map_err(ProfileError::Io)?;
+        serde_json::from_reader(std::io::BufReader::new(file)).map_err(ProfileError::Parse)
+    }
+
+    /// Writes the manifest to its path in the data directory
+    pub fn save(&self) -> Result<PathBuf, ProfileError> {
+        let path = manifest_path(&self.application_name, self.application_version);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(ProfileError::Io)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(ProfileError::Parse)?;
+        fs::write(&path, json).map_err(ProfileError::Io)?;
+        Ok(path)
+    }
+
+    pub fn action(&self, action_set: &str, action: &str) -> Option<&ActionInfo> {
+        self.action_sets
+            .iter()
+            .find(|info| info.name == action_set)?
+            .actions
+            .iter()
+            .find(|info| info.name == action)
+    }
+
+    /// Describes every action which was added, removed or changed between two versions
+    pub fn diff(&self, newer: &ActionManifest) -> Vec<String> {
+        let mut differences = Vec::new();
+
+        for action_set in &self.action_sets {
+            for action in &action_set.actions {
+                let name = format!("{}/{}", action_set.name, action.name);
+                match newer.action(&action_set.name, &action.name) {
+                    None => differences.push(format!("- {}", name)),
+                    Some(new) if new.action_type != action.action_type => {
+                        differences.push(format!(
+                            "~ {}: {:?} -> {:?}",
+                            name, action.action_type, new.action_type
+                        ))
+                    }
+                    Some(new) if new.sub_action_paths != action.sub_action_paths => differences
+                        .push(format!(
+                            "~ {}: subaction paths [{}] -> [{}]",
+                            name,
+                            action.sub_action_paths.join(" "),
+                            new.sub_action_paths.join(" ")
+                        )),
+                    Some(_) => {}
+                }
+            }
+        }
+
+        for action_set in &newer.action_sets {
+            for action in &action_set.actions {
+                if self.action(&action_set.name, &action.name).is_none() {
+                    differences.push(format!("+ {}/{}", action_set.name, action.name));
+                }
+            }
+        }
+
+        differences
+    }
+
+    /// The parts of a profile which refer to actions the application no longer has or which can
+    /// no longer receive their bindings
+    pub fn broken_remaps(&self, profile: &RemapProfile) -> Vec<String> {
+        let mut broken = Vec::new();
+
+        for behavior in &profile.behaviors {
+            match self.action(&behavior.action_set, &behavior.action) {
+                None => broken.push(format!(
+                    "behavior of `{}/{}` refers to an action which does not exist",
+                    behavior.action_set, behavior.action
+                )),
+                Some(action) if action.action_type != ActionType::Boolean => broken.push(format!(
+                    "behavior of `{}/{}` needs a boolean action",
+                    behavior.action_set, behavior.action
+                )),
+                Some(_) => {}
+            }
+        }
+
+        for rebind in &profile.rebinds {
+            let name = format!(
+                "`{}/{}` on `{}`",
+                rebind.action_set, rebind.action, rebind.interaction_profile
+            );
+            let action = match self.action(&rebind.action_set, &rebind.action) {
+                Some(action) => action,
+                None => {
+                    broken.push(format!(
+                        "rebind of {} refers to an action which does not exist",
+                        name
+                    ));
+                    continue;
+                }
+            };
+            if matches!(action.action_type, ActionType::Pose | ActionType::Vibration) {
+                broken.push(format!(
+                    "rebind of {}: pose and haptic actions cannot be rebound",
+                    name
+                ));
+            }
+            if action.sub_action_paths.is_empty() {
+                continue;
+            }
+            for binding in &rebind.bindings {
+                if !user_paths::top_level_path(binding).map_or(false, |path| {
+                    action.sub_action_paths.iter().any(|p| p == path)
+                }) {
+                    broken.push(format!(
+                        "rebind of {}: the action has no subaction path for `{}`",
+                        name, binding
+                    ));
+                }
+            }
+        }
+
+        for advanced in &profile.advanced_bindings {
+            match self.action(&advanced.action_set, &advanced.action) {
+                None => broken.push(format!(
+                    "{} binding of `{}/{}` refers to an action which does not exist",
+                    advanced.kind.name(),
+                    advanced.action_set,
+                    advanced.action
+                )),
+                Some(action) if action.action_type != ActionType::Boolean => broken.push(format!(
+                    "{} binding of `{}/{}` needs a boolean action",
+                    advanced.kind.name(),
+                    advanced.action_set,
+                    advanced.action
+                )),
+                Some(_) => {}
+            }
+        }
+
+        broken
+    }
+
+    /// The bindings the application receives with a profile applied, as far as they are known
+    /// without the runtime
+    pub fn effective_bindings(&self, profile: &RemapProfile) -> Vec<ExportedBinding> {
+        let reserved = profile.reserved_bindings();
+        let mut effective = Vec::new();
+
+        for suggested in &self.interaction_profiles {
+            let interaction_profile = &suggested.interaction_profile;
+
+            for action_set in &self.action_sets {
+                for action in &action_set.actions {
+                    let exported = |binding: String| ExportedBinding {
+                        interaction_profile: interaction_profile.clone(),
+                        action_set: action_set.name.clone(),
+                        action: action.name.clone(),
+                        binding,
+                        modifications: Vec::new(),
+                    };
+                    match profile.rebind(interaction_profile, &action_set.name, &action.name) {
+                        Some(rebind)
+                            if !matches!(
+                                action.action_type,
+                                ActionType::Pose | ActionType::Vibration
+                            ) =>
+                        {
+                            effective.extend(
+                                rebind
+                                    .bindings
+                                    .iter()
+                                    .filter(|binding| !reserved.contains(&binding.as_str()))
+                                    .cloned()
+                                    .map(exported),
+                            )
+                        }
+                        _ => effective.extend(
+                            suggested
+                                .bindings
+                                .iter()
+                                .filter(|binding| {
+                                    binding.action_set == action_set.name
+                                        && binding.action == action.name
+                                })
+                                .filter_map(|binding| {
+                                    translation::translate_path(
+                                        profile,
+                                        Some(interaction_profile.as_str()),
+                                        &binding.binding,
+                                        action.action_type.to_xr(),
+                                    )
+                                    .ok()
+                                })
+                                .map(|binding| exported(binding.into_owned())),
+                        ),
+                    }
+                }
+            }
+
+            for advanced in profile
+                .advanced_bindings
+                .iter()
+                .filter(|advanced| advanced.interaction_profile == *interaction_profile)
+                .filter(|advanced| {
+                    self.action(&advanced.action_set, &advanced.action)
+                        .is_some()
+                })
+            {
+                let (binding, modification) = advanced.kind.exported();
+                effective.push(ExportedBinding {
+                    interaction_profile: interaction_profile.clone(),
+                    action_set: advanced.action_set.clone(),
+                    action: advanced.action.clone(),
+                    binding,
+                    modifications: vec![modification],
+                });
+            }
+        }
+
+        effective
+    }
+
+    /// Checks the effective bindings of every interaction profile the application suggested
+    pub fn binding_reports(&self, profile: &RemapProfile) -> Vec<BindingReport> {
+        let bindings = self.effective_bindings(profile);
+        let reserved = profile.reserved_bindings();
+        self.interaction_profiles
+            .iter()
+            .map(|suggested| {
+                binding_analysis::analyze(
+                    &suggested.interaction_profile,
+                    &self.action_sets,
+                    &bindings,
+                    &reserved,
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{
+        input::{
+            advanced_binding::{BindingKind, DpadDirection},
+            behavior::ButtonBehavior,
+        },
+        ipc::protocol::BindingModification,
+        profile::{ActionBehavior, AdvancedBinding, Rebind},
+    };
+
+    use super::*;
+
+    const TOUCH_CONTROLLER: &str = "/interaction_profiles/oculus/touch_controller";
+
+    fn load_test_data(name: &str) -> ActionManifest {
+        ActionManifest::load(
+            &Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("testdata")
+                .join(name),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn diff_lists_changed_actions() {
+        let older = load_test_data("manifest_v1.json");
+        let newer = load_test_data("manifest_v2.json");
+        assert_eq!(
+            older.diff(&newer),
+            [
+                "~ gameplay/jump: Boolean -> Float",
+                "~ gameplay/move: subaction paths [/user/hand/left] -> [/user/hand/left \
+                 /user/hand/right]",
+                "- gameplay/grab",
+                "+ gameplay/crouch",
+                "+ vehicle/drive",
+            ]
+        );
+        assert!(older.diff(&older).is_empty());
+    }
+
+    #[test]
+    fn broken_remaps() {
+        let manifest = load_test_data("manifest_v2.json");
+        let rebind = |action: &str, bindings: &[&str]| Rebind {
+            interaction_profile: TOUCH_CONTROLLER.to_owned(),
+            action_set: "gameplay".to_owned(),
+            action: action.to_owned(),
+            bindings: bindings
+                .iter()
+                .map(|binding| (*binding).to_owned())
+                .collect(),
+        };
+        let profile = RemapProfile {
+            behaviors: ["jump", "grab", "crouch"]
+                .into_iter()
+                .map(|action| ActionBehavior {
+                    action_set: "gameplay".to_owned(),
+                    action: action.to_owned(),
+                    behavior: ButtonBehavior::Toggle,
+                })
+                .collect(),
+            rebinds: vec![
+                rebind("hand", &["/user/hand/left/input/aim/pose"]),
+                rebind(
+                    "move",
+                    &[
+                        "/user/hand/right/input/thumbstick",
+                        "/user/gamepad/input/thumbstick_left",
+                    ],
+                ),
+                rebind("grab", &[]),
+                //Actions without subaction paths take any binding
+                rebind("crouch", &["/user/gamepad/input/b/click"]),
+            ],
+            advanced_bindings: vec![AdvancedBinding {
+                interaction_profile: TOUCH_CONTROLLER.to_owned(),
+                action_set: "vehicle".to_owned(),
+                action: "drive".to_owned(),
+                kind: BindingKind::Modifier {
+                    modifier: "/user/hand/left/input/x/click".to_owned(),
+                    binding: "/user/hand/left/input/y/click".to_owned(),
+                },
+            }],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            manifest.broken_remaps(&profile),
+            [
+                "behavior of `gameplay/jump` needs a boolean action",
+                "behavior of `gameplay/grab` refers to an action which does not exist",
+                "rebind of `gameplay/hand` on `/interaction_profiles/oculus/touch_controller`: \
+                 pose and haptic actions cannot be rebound",
+                "rebind of `gameplay/move` on `/interaction_profiles/oculus/touch_controller`: \
+                 the action has no subaction path for `/user/gamepad/input/thumbstick_left`",
+                "rebind of `gameplay/grab` on `/interaction_profiles/oculus/touch_controller` \
+                 refers to an action which does not exist",
+                "modifier binding of `vehicle/drive` needs a boolean action",
+            ]
+        );
+    }
+
+    #[test]
+    fn effective_bindings() {
+        let manifest = load_test_data("manifest_v1.json");
+        let profile = RemapProfile {
+            left_handed: true,
+            rebinds: vec![Rebind {
+                interaction_profile: TOUCH_CONTROLLER.to_owned(),
+                action_set: "gameplay".to_owned(),
+                action: "jump".to_owned(),
+                bindings: vec!["/user/hand/right/input/b/click".to_owned()],
+            }],
+            advanced_bindings: vec![AdvancedBinding {
+                interaction_profile: TOUCH_CONTROLLER.to_owned(),
+                action_set: "menu".to_owned(),
+                action: "open".to_owned(),
+                kind: BindingKind::Dpad {
+                    binding: "/user/hand/left/input/thumbstick".to_owned(),
+                    direction: DpadDirection::Up,
+                    center_region: 0.5,
+                    wedge_angle: std::f32::consts::FRAC_PI_2,
+                    sticky: false,
+                    activator: None,
+                },
+            }],
+            ..Default::default()
+        };
+
+        let bindings = manifest
+            .effective_bindings(&profile)
+            .into_iter()
+            .map(|binding| {
+                assert_eq!(binding.interaction_profile, TOUCH_CONTROLLER);
+                (
+                    format!("{}/{}", binding.action_set, binding.action),
+                    binding.binding,
+                    binding.modifications,
+                )
+            })
+            .collect::<Vec<_>>();
+        let plain = |action: &str, binding: &str| (action.to_owned(), binding.to_owned(), vec![]);
+        assert_eq!(
+            bindings,
+            [
+                //Rebinds are used as they are, suggestions are mirrored
+                plain("gameplay/jump", "/user/hand/right/input/b/click"),
+                plain("gameplay/move", "/user/hand/right/input/thumbstick"),
+                plain("gameplay/hand", "/user/hand/right/input/grip/pose"),
+                plain("gameplay/hand", "/user/hand/left/input/grip/pose"),
+                plain("gameplay/grab", "/user/hand/right/input/squeeze/value"),
+                plain("menu/open", "/user/hand/right/input/system/click"),
+                (
+                    "menu/open".to_owned(),
+                    "/user/hand/left/input/thumbstick/dpad_up".to_owned(),
+                    vec![BindingModification::DpadBinding {
+                        center_region: 0.5,
+                        wedge_angle: std::f32::consts::FRAC_PI_2,
+                        is_sticky: false,
+                        activator: None,
+                    }],
+                ),
+            ]
+        );
+    }
+}
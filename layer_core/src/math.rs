@@ -0,0 +1,185 @@
+use openxr::sys as xr;
+use serde::{Deserialize, Serialize};
+
+pub const IDENTITY: xr::Posef = xr::Posef {
+    orientation: xr::Quaternionf {
+        x: 0.,
+        y: 0.,
+        z: 0.,
+        w: 1.,
+    },
+    position: xr::Vector3f {
+        x: 0.,
+        y: 0.,
+        z: 0.,
+    },
+};
+
+/// A rigid transform as written in profiles, with the rotation given as pitch, yaw and roll in degrees
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Transform {
+    pub position: [f32; 3],
+    pub rotation: [f32; 3],
+}
+
+impl Transform {
+    pub fn to_pose(&self) -> xr::Posef {
+        let [pitch, yaw, roll] = self.rotation.map(f32::to_radians);
+        let [x, y, z] = self.position;
+
+        //Applied in the order yaw, pitch, roll to match the conventions used by most games
+        let orientation = quat_mul(
+            quat_mul(
+                quat_from_axis_angle(vec3(0., 1., 0.), yaw),
+                quat_from_axis_angle(vec3(1., 0., 0.), pitch),
+            ),
+            quat_from_axis_angle(vec3(0., 0., 1.), roll),
+        );
+
+        xr::Posef {
+            orientation,
+            position: vec3(x, y, z),
+        }
+    }
+}
+
+pub fn vec3(x: f32, y: f32, z: f32) -> xr::Vector3f {
+    xr::Vector3f { x, y, z }
+}
+
+pub fn add(a: xr::Vector3f, b: xr::Vector3f) -> xr::Vector3f {
+    vec3(a.x + b.x, a.y + b.y, a.z + b.z)
+}
+
+pub fn sub(a: xr::Vector3f, b: xr::Vector3f) -> xr::Vector3f {
+    vec3(a.x - b.x, a.y - b.y, a.z - b.z)
+}
+
+pub fn scale(a: xr::Vector3f, s: f32) -> xr::Vector3f {
+    vec3(a.x * s, a.y * s, a.z * s)
+}
+
+pub fn cross(a: xr::Vector3f, b: xr::Vector3f) -> xr::Vector3f {
+    vec3(
+        a.y * b.z - a.z * b.y,
+        a.z * b.x - a.x * b.z,
+        a.x * b.y - a.y * b.x,
+    )
+}
+
+pub fn length(a: xr::Vector3f) -> f32 {
+    (a.x * a.x + a.y * a.y + a.z * a.z).sqrt()
+}
+
+pub fn quat_from_axis_angle(axis: xr::Vector3f, angle: f32) -> xr::Quaternionf {
+    let (sin, cos) = (angle / 2.).sin_cos();
+    xr::Quaternionf {
+        x: axis.x * sin,
+        y: axis.y * sin,
+        z: axis.z * sin,
+        w: cos,
+    }
+}
+
+pub fn quat_mul(a: xr::Quaternionf, b: xr::Quaternionf) -> xr::Quaternionf {
+    xr::Quaternionf {
+        x: a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+        y: a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+        z: a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w,
+        w: a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z,
+    }
+}
+
+pub fn quat_conjugate(q: xr::Quaternionf) -> xr::Quaternionf {
+    xr::Quaternionf {
+        x: -q.x,
+        y: -q.y,
+        z: -q.z,
+        w: q.w,
+    }
+}
+
+pub fn quat_normalize(q: xr::Quaternionf) -> xr::Quaternionf {
+    let length = (q.x * q.x + q.y * q.y + q.z * q.z + q.w * q.w).sqrt();
+    if length == 0. {
+        return IDENTITY.orientation;
+    }
+    xr::Quaternionf {
+        x: q.x / length,
+        y: q.y / length,
+        z: q.z / length,
+        w: q.w / length,
+    }
+}
+
+pub fn rotate(q: xr::Quaternionf, v: xr::Vector3f) -> xr::Vector3f {
+    let u = vec3(q.x, q.y, q.z);
+    let t = scale(cross(u, v), 2.);
+    add(add(v, scale(t, q.w)), cross(u, t))
+}
+
+pub fn lerp(a: xr::Vector3f, b: xr::Vector3f, t: f32) -> xr::Vector3f {
+    add(a, scale(sub(b, a), t))
+}
+
+/// Normalized linear interpolation along the shorter arc
+pub fn nlerp(a: xr::Quaternionf, b: xr::Quaternionf, t: f32) -> xr::Quaternionf {
+    let dot = a.x * b.x + a.y * b.y + a.z * b.z + a.w * b.w;
+    let sign = if dot < 0. { -1. } else { 1. };
+    quat_normalize(xr::Quaternionf {
+        x: a.x + (b.x * sign - a.x) * t,
+        y: a.y + (b.y * sign - a.y) * t,
+        z: a.z + (b.z * sign - a.z) * t,
+        w: a.w + (b.w * sign - a.w) * t,
+    })
+}
+
+/// The rotation as an axis scaled by its angle in radians
+pub fn rotation_vector(q: xr::Quaternionf) -> xr::Vector3f {
+    let q = if q.w < 0. {
+        xr::Quaternionf {
+            x: -q.x,
+            y: -q.y,
+            z: -q.z,
+            w: -q.w,
+        }
+    } else {
+        q
+    };
+    let axis = vec3(q.x, q.y, q.z);
+    let sin = length(axis);
+    if sin < f32::EPSILON {
+        return scale(axis, 2.);
+    }
+    scale(axis, 2. * sin.atan2(q.w) / sin)
+}
+
+/// Returns `a * b`, the pose `b` expressed relative to `a`
+pub fn pose_mul(a: xr::Posef, b: xr::Posef) -> xr::Posef {
+    xr::Posef {
+        orientation: quat_mul(a.orientation, b.orientation),
+        position: add(a.position, rotate(a.orientation, b.position)),
+    }
+}
+
+pub fn pose_inverse(pose: xr::Posef) -> xr::Posef {
+    let orientation = quat_conjugate(pose.orientation);
+    xr::Posef {
+        orientation,
+        position: rotate(orientation, scale(pose.position, -1.)),
+    }
+}
+
+/// Reflects a pose across the YZ plane, turning a left hand pose into a right hand one
+pub fn mirror_x(pose: xr::Posef) -> xr::Posef {
+    xr::Posef {
+        orientation: xr::Quaternionf {
+            x: pose.orientation.x,
+            y: -pose.orientation.y,
+            z: -pose.orientation.z,
+            w: pose.orientation.w,
+        },
+        position: vec3(-pose.position.x, pose.position.y, pose.position.z),
+    }
+}
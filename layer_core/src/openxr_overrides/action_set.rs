@@ -27,7 +27,7 @@ unsafe extern "system" fn create_action(
         Some(wrapper_ref) => wrapper_ref,
         None => return xr::Result::ERROR_HANDLE_INVALID,
     };
-    match wrapper.xr_create_action(&*create_info, &mut *action) {
+    match wrapper.xr_create_action(action_set, &*create_info, &mut *action) {
         Ok(res) => res,
         Err(res) => res,
     }
@@ -0,0 +1,52 @@
+use openxr::sys as xr;
+
+use crate::wrappers::XrHandle;
+
+pub(super) unsafe fn get_interceptors(name: &str) -> Option<xr::pfn::VoidFunction> {
+    use std::mem::transmute;
+    use xr::pfn::*;
+    Some(match name {
+        "xrCreateDebugUtilsMessengerEXT" => {
+            transmute(xr_create_debug_utils_messenger as CreateDebugUtilsMessengerEXT)
+        }
+        "xrDestroyDebugUtilsMessengerEXT" => {
+            transmute(xr_destroy_debug_utils_messenger as DestroyDebugUtilsMessengerEXT)
+        }
+        "xrSetDebugUtilsObjectNameEXT" => {
+            transmute(xr_set_debug_utils_object_name as SetDebugUtilsObjectNameEXT)
+        }
+        _ => return None,
+    })
+}
+
+unsafe extern "system" fn xr_create_debug_utils_messenger(
+    instance: xr::Instance,
+    create_info: *const xr::DebugUtilsMessengerCreateInfoEXT,
+    messenger: *mut xr::DebugUtilsMessengerEXT,
+) -> xr::Result {
+    instance
+        .run(|instance| instance.xr_create_debug_utils_messenger(&*create_info, &mut *messenger))
+}
+
+unsafe extern "system" fn xr_destroy_debug_utils_messenger(
+    messenger: xr::DebugUtilsMessengerEXT,
+) -> xr::Result {
+    //Messengers do not know their instance
+    let instance = xr::Instance::all_wrappers()
+        .iter()
+        .find(|instance| instance.debug_messengers.contains(messenger))
+        .map(|instance| *instance.key());
+    match instance {
+        Some(instance) => {
+            instance.run(|instance| instance.xr_destroy_debug_utils_messenger(messenger))
+        }
+        None => xr::Result::ERROR_HANDLE_INVALID,
+    }
+}
+
+unsafe extern "system" fn xr_set_debug_utils_object_name(
+    instance: xr::Instance,
+    name_info: *const xr::DebugUtilsObjectNameInfoEXT,
+) -> xr::Result {
+    instance.run(|instance| instance.xr_set_debug_utils_object_name(&*name_info))
+}
@@ -6,6 +6,7 @@ pub(super) unsafe fn get_interceptors(name: &str) -> Option<pfn::VoidFunction> {
     use std::mem::transmute;
     use xr::pfn::*;
     Some(match name {
+        "xrDestroyInstance" => transmute(xr_destroy_instance as DestroyInstance),
         "xrGetSystem" => transmute(xr_get_system as GetSystem),
         "xrCreateSession" => transmute(xr_create_session as CreateSession),
         "xrCreateActionSet" => transmute(xr_create_action_set as CreateActionSet),
@@ -16,6 +17,20 @@ pub(super) unsafe fn get_interceptors(name: &str) -> Option<pfn::VoidFunction> {
     })
 }
 
+unsafe extern "system" fn xr_destroy_instance(instance: xr::Instance) -> xr::Result {
+    let inner = match xr::Instance::all_wrappers().get(&instance) {
+        Some(wrapper) => {
+            //Stops the control socket so clients do not see a destroyed instance
+            wrapper.ipc_server.lock().take();
+            wrapper.inner.clone()
+        }
+        None => return xr::Result::ERROR_HANDLE_INVALID,
+    };
+    crate::input::driver_hooks::unregister_instance(instance);
+
+    (inner.core.destroy_instance)(instance)
+}
+
 unsafe extern "system" fn xr_get_system(
     instance: xr::Instance,
     get_info: *const xr::SystemGetInfo,
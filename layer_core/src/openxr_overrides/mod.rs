@@ -1,7 +1,9 @@
 mod action;
 mod action_set;
+mod debug_utils;
 pub mod instance;
 mod session;
+mod space;
 
 use std::{ffi::CStr, os::raw::c_char};
 
@@ -13,11 +15,13 @@ use crate::wrappers::instance::InstanceWrapper;
 use crate::wrappers::XrHandle;
 use crate::ToResult;
 
-const INTERCEPTORS: [unsafe fn(&str) -> Option<pfn::VoidFunction>; 4] = [
+const INTERCEPTORS: [unsafe fn(&str) -> Option<pfn::VoidFunction>; 6] = [
     instance::get_interceptors,
+    debug_utils::get_interceptors,
     session::get_interceptors,
     action_set::get_interceptors,
     action::get_interceptors,
+    space::get_interceptors,
 ];
 
 pub(crate) unsafe extern "system" fn get_instance_proc_addr(
@@ -1,6 +1,6 @@
 use openxr::sys as xr;
 
-use crate::wrappers::XrHandle;
+use crate::{recording::RecordedState, wrappers::XrHandle};
 
 pub(super) unsafe fn get_interceptors(name: &str) -> Option<xr::pfn::VoidFunction> {
     use std::mem::transmute;
@@ -9,7 +9,8 @@ pub(super) unsafe fn get_interceptors(name: &str) -> Option<xr::pfn::VoidFunctio
         "xrAttachSessionActionSets" => {
             transmute(xr_attach_session_action_sets as AttachSessionActionSets)
         }
-        "xrSyncAction" => transmute(xr_sync_actions as SyncActions),
+        "xrSyncActions" => transmute(xr_sync_actions as SyncActions),
+        "xrWaitFrame" => transmute(xr_wait_frame as WaitFrame),
         "xrGetActionStateBoolean" => {
             transmute(xr_get_action_state_boolean as GetActionStateBoolean)
         }
@@ -17,6 +18,12 @@ pub(super) unsafe fn get_interceptors(name: &str) -> Option<xr::pfn::VoidFunctio
         "xrGetActionStateVector2f" => {
             transmute(xr_get_action_state_vector2f as GetActionStateVector2f)
         }
+        "xrGetActionStatePose" => transmute(xr_get_action_state_pose as GetActionStatePose),
+        "xrCreateReferenceSpace" => transmute(xr_create_reference_space as CreateReferenceSpace),
+        "xrLocateViews" => transmute(xr_locate_views as LocateViews),
+        "xrCreateActionSpace" => transmute(xr_create_action_space as CreateActionSpace),
+        "xrApplyHapticFeedback" => transmute(xr_apply_haptic_feedback as ApplyHapticFeedback),
+        "xrStopHapticFeedback" => transmute(xr_stop_haptic_feedback as StopHapticFeedback),
         _ => return None,
     })
 }
@@ -47,6 +54,14 @@ unsafe extern "system" fn xr_sync_actions(
     })
 }
 
+unsafe extern "system" fn xr_wait_frame(
+    session: xr::Session,
+    frame_wait_info: *const xr::FrameWaitInfo,
+    frame_state: *mut xr::FrameState,
+) -> xr::Result {
+    session.run(|session| session.xr_wait_frame(frame_wait_info, &mut *frame_state))
+}
+
 unsafe extern "system" fn xr_get_action_state_boolean(
     session: xr::Session,
     get_info: *const xr::ActionStateGetInfo,
@@ -54,7 +69,22 @@ unsafe extern "system" fn xr_get_action_state_boolean(
 ) -> xr::Result {
     let get_info = &*get_info;
     session.run(|session| {
-        session.xr_get_action_state_boolean(get_info.action, get_info.subaction_path, &mut *state)
+        let result = session.xr_get_action_state_boolean(
+            get_info.action,
+            get_info.subaction_path,
+            &mut *state,
+        )?;
+        let state = &*state;
+        session.record_state(
+            get_info.action,
+            get_info.subaction_path,
+            RecordedState::Boolean {
+                is_active: state.is_active.into(),
+                state: state.current_state.into(),
+                changed: state.changed_since_last_sync.into(),
+            },
+        );
+        Ok(result)
     })
 }
 
@@ -63,7 +93,25 @@ unsafe extern "system" fn xr_get_action_state_float(
     get_info: *const xr::ActionStateGetInfo,
     state: *mut xr::ActionStateFloat,
 ) -> xr::Result {
-    todo!()
+    let get_info = &*get_info;
+    session.run(|session| {
+        let result = session.xr_get_action_state_float(
+            get_info.action,
+            get_info.subaction_path,
+            &mut *state,
+        )?;
+        let state = &*state;
+        session.record_state(
+            get_info.action,
+            get_info.subaction_path,
+            RecordedState::Float {
+                is_active: state.is_active.into(),
+                state: state.current_state,
+                changed: state.changed_since_last_sync.into(),
+            },
+        );
+        Ok(result)
+    })
 }
 
 unsafe extern "system" fn xr_get_action_state_vector2f(
@@ -71,5 +119,96 @@ unsafe extern "system" fn xr_get_action_state_vector2f(
     get_info: *const xr::ActionStateGetInfo,
     state: *mut xr::ActionStateVector2f,
 ) -> xr::Result {
-    todo!()
+    let get_info = &*get_info;
+    session.run(|session| {
+        let result = session.xr_get_action_state_vector2f(
+            get_info.action,
+            get_info.subaction_path,
+            &mut *state,
+        )?;
+        let state = &*state;
+        session.record_state(
+            get_info.action,
+            get_info.subaction_path,
+            RecordedState::Vector2f {
+                is_active: state.is_active.into(),
+                state: [state.current_state.x, state.current_state.y],
+                changed: state.changed_since_last_sync.into(),
+            },
+        );
+        Ok(result)
+    })
+}
+
+unsafe extern "system" fn xr_get_action_state_pose(
+    session: xr::Session,
+    get_info: *const xr::ActionStateGetInfo,
+    state: *mut xr::ActionStatePose,
+) -> xr::Result {
+    let get_info = &*get_info;
+    session.run(|session| {
+        let result = session.xr_get_action_state_pose(
+            get_info.action,
+            get_info.subaction_path,
+            &mut *state,
+        )?;
+        session.record_state(
+            get_info.action,
+            get_info.subaction_path,
+            RecordedState::Pose {
+                is_active: (*state).is_active.into(),
+            },
+        );
+        Ok(result)
+    })
+}
+
+unsafe extern "system" fn xr_create_reference_space(
+    session: xr::Session,
+    create_info: *const xr::ReferenceSpaceCreateInfo,
+    space: *mut xr::Space,
+) -> xr::Result {
+    session.run(|session| session.xr_create_reference_space(&*create_info, &mut *space))
+}
+
+unsafe extern "system" fn xr_locate_views(
+    session: xr::Session,
+    view_locate_info: *const xr::ViewLocateInfo,
+    view_state: *mut xr::ViewState,
+    view_capacity_input: u32,
+    view_count_output: *mut u32,
+    views: *mut xr::View,
+) -> xr::Result {
+    session.run(|session| {
+        session.xr_locate_views(
+            &*view_locate_info,
+            view_state,
+            view_capacity_input,
+            view_count_output,
+            views,
+        )
+    })
+}
+
+unsafe extern "system" fn xr_create_action_space(
+    session: xr::Session,
+    create_info: *const xr::ActionSpaceCreateInfo,
+    space: *mut xr::Space,
+) -> xr::Result {
+    session.run(|session| session.xr_create_action_space(&*create_info, &mut *space))
+}
+
+unsafe extern "system" fn xr_apply_haptic_feedback(
+    session: xr::Session,
+    haptic_action_info: *const xr::HapticActionInfo,
+    haptic_feedback: *const xr::HapticBaseHeader,
+) -> xr::Result {
+    session.run(|session| session.xr_apply_haptic_feedback(&*haptic_action_info, haptic_feedback))
+}
+
+unsafe extern "system" fn xr_stop_haptic_feedback(
+    session: xr::Session,
+    haptic_action_info: *const xr::HapticActionInfo,
+) -> xr::Result {
+    session.run(|session| session.xr_stop_haptic_feedback(&*haptic_action_info))
 }
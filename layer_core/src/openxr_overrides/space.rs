@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use openxr::sys as xr;
+
+use crate::wrappers::{instance::InnerInstance, XrHandle, XrWrapper};
+
+pub(super) unsafe fn get_interceptors(name: &str) -> Option<xr::pfn::VoidFunction> {
+    use std::mem::transmute;
+    use xr::pfn::*;
+    Some(match name {
+        "xrDestroySpace" => transmute(xr_destroy_space as DestroySpace),
+        "xrLocateSpace" => transmute(xr_locate_space as LocateSpace),
+        _ => return None,
+    })
+}
+
+/// Spaces created without going through the layer (e.g. by extensions) have no wrapper, so
+/// calls on them are forwarded to the only instance. With several instances their owner is
+/// unknown and `None` is returned.
+pub(super) fn inner_instance_for(space: xr::Space) -> Option<Arc<InnerInstance>> {
+    match xr::Space::all_wrappers().get(&space) {
+        Some(wrapper) => Some(wrapper.inner_instance().clone()),
+        None => {
+            let instances = xr::Instance::all_wrappers();
+            if instances.len() != 1 {
+                return None;
+            }
+            let instance = instances.iter().next()?;
+            Some(instance.inner_instance().clone())
+        }
+    }
+}
+
+unsafe extern "system" fn xr_destroy_space(space: xr::Space) -> xr::Result {
+    let inner = match inner_instance_for(space) {
+        Some(inner) => inner,
+        None => return xr::Result::ERROR_HANDLE_INVALID,
+    };
+
+    xr::Space::all_wrappers().remove(&space);
+
+    (inner.core.destroy_space)(space)
+}
+
+unsafe extern "system" fn xr_locate_space(
+    space: xr::Space,
+    base_space: xr::Space,
+    time: xr::Time,
+    location: *mut xr::SpaceLocation,
+) -> xr::Result {
+    if !xr::Space::all_wrappers().contains_key(&space) {
+        if xr::Space::all_wrappers().contains_key(&base_space) {
+            return base_space
+                .run(|base_space| base_space.xr_locate_as_base_space(space, time, &mut *location));
+        }
+        return match inner_instance_for(base_space) {
+            Some(inner) => (inner.core.locate_space)(space, base_space, time, location),
+            None => xr::Result::ERROR_HANDLE_INVALID,
+        };
+    }
+
+    space.run(|space| {
+        let result = space.xr_locate_space(base_space, time, &mut *location)?;
+        if let Some(instance) = space
+            .session
+            .upgrade()
+            .and_then(|session| session.instance.upgrade())
+        {
+            instance.recorder.record_location(
+                &instance,
+                space.handle,
+                base_space,
+                time,
+                &*location,
+            );
+        }
+        Ok(result)
+    })
+}
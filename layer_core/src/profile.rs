@@ -0,0 +1,718 @@
+use std::{fmt::Display, fs, path::PathBuf};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    input::{
+        advanced_binding::BindingKind,
+        behavior::ButtonBehavior,
+        desktop::DesktopConfig,
+        eye_gaze::{EyeGazeConfig, GazeFallback},
+        gamepad::{self, GamepadConfig},
+        hand_tracking::HandTrackingConfig,
+        interaction_profiles::{self, ComponentType},
+        playspace::PlayspaceMoverConfig,
+        pose_filter::{PoseFilterConfig, Smoothing},
+        user_paths::{self, Hand},
+        virtual_device::{self, VirtualDeviceConfig, VirtualSource},
+    },
+    math::Transform,
+};
+
+pub const DEFAULT_PROFILE_NAME: &str = "default";
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RemapProfile {
+    pub behaviors: Vec<ActionBehavior>,
+    /// Swaps `/user/hand/left` and `/user/hand/right` for every binding of the application
+    pub left_handed: bool,
+    pub one_handed: Option<OneHandedConfig>,
+    /// Corrections applied to the poses of controllers, the first matching entry is used
+    pub pose_offsets: Vec<PoseOffset>,
+    /// Smoothing of located action spaces, the first matching entry is used
+    pub pose_filters: Vec<PoseFilter>,
+    pub playspace_mover: Option<PlayspaceMoverConfig>,
+    /// Bindings chosen by the user which replace the application's suggestions
+    pub rebinds: Vec<Rebind>,
+    /// Thresholds, dpads and modifier combinations driving boolean actions, used in addition to
+    /// the other bindings of the action
+    pub advanced_bindings: Vec<AdvancedBinding>,
+    /// Keyboard and mouse input, read in addition to the controllers
+    pub desktop: Option<DesktopConfig>,
+    /// Gamepad input, read in addition to the controllers
+    pub gamepad: Option<GamepadConfig>,
+    /// Controller input from tracked hands, used for hands which hold no controller
+    pub hand_tracking: Option<HandTrackingConfig>,
+    /// Eye gaze as a controller's aim and head or controller aim as the eye gaze
+    pub eye_gaze: Option<EyeGazeConfig>,
+    /// Devices which only exist in the layer, bindable like the controllers
+    pub virtual_devices: Vec<VirtualDeviceConfig>,
+}
+
+/// Replaces the suggested bindings of a boolean, float or vector2f action for one interaction profile
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rebind {
+    pub interaction_profile: String,
+    pub action_set: String,
+    pub action: String,
+    /// Physical input paths, an empty list unbinds the action
+    pub bindings: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdvancedBinding {
+    pub interaction_profile: String,
+    pub action_set: String,
+    pub action: String,
+    #[serde(flatten)]
+    pub kind: BindingKind,
+}
+
+/// Selects the physical pose bindings a setting applies to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BindingSelector {
+    /// Only apply to this interaction profile, e.g. `/interaction_profiles/valve/index_controller`
+    #[serde(default)]
+    pub interaction_profile: Option<String>,
+    #[serde(default)]
+    pub hand: Option<Hand>,
+    /// The pose component, e.g. `/input/grip/pose`
+    pub component: String,
+}
+
+impl AdvancedBinding {
+    fn validate(&self, issues: &mut Vec<String>, reserved: &[&str], remap: &RemapProfile) {
+        let name = format!(
+            "{} binding of `{}/{}` on `{}`",
+            self.kind.name(),
+            self.action_set,
+            self.action,
+            self.interaction_profile
+        );
+        let profile = match interaction_profiles::get(&self.interaction_profile) {
+            Some(profile) => profile,
+            None => {
+                issues.push(format!("{} uses an unknown interaction profile", name));
+                return;
+            }
+        };
+
+        let inputs = self.kind.inputs();
+        for (i, input) in inputs.iter().enumerate() {
+            let expected: &[ComponentType] = match (&self.kind, i) {
+                (BindingKind::Threshold { .. }, _) => &[ComponentType::Float],
+                (BindingKind::Dpad { .. }, 0) => &[ComponentType::Vector2f],
+                _ => &[ComponentType::Boolean, ComponentType::Float],
+            };
+            let ty = remap
+                .virtual_component(input)
+                .or_else(|| profile.component(input).map(|component| component.ty));
+            match ty {
+                Some(ty) if expected.contains(&ty) => {}
+                Some(_) => issues.push(format!("{}: `{}` has the wrong input type", name, input)),
+                None => issues.push(format!(
+                    "{}: `{}` does not exist on the interaction profile",
+                    name, input
+                )),
+            }
+            if reserved.contains(input) {
+                issues.push(format!("{}: `{}` is reserved by the layer", name, input));
+            }
+        }
+
+        match &self.kind {
+            BindingKind::Threshold {
+                on_threshold,
+                off_threshold,
+                ..
+            } if !(0. ..=1.).contains(on_threshold)
+                || !(0. ..=1.).contains(off_threshold)
+                || off_threshold > on_threshold =>
+            {
+                issues.push(format!(
+                    "{}: thresholds must be between 0 and 1 with the off threshold not above the on threshold",
+                    name
+                ))
+            }
+            BindingKind::Dpad {
+                center_region,
+                wedge_angle,
+                ..
+            } if !(0. ..1.).contains(center_region)
+                || *wedge_angle <= 0.
+                || *wedge_angle > std::f32::consts::PI =>
+            {
+                issues.push(format!(
+                    "{}: the center region must be below 1 and the wedge angle at most pi",
+                    name
+                ))
+            }
+            _ => {}
+        }
+    }
+}
+
+impl BindingSelector {
+    fn validate(&self, issues: &mut Vec<String>, what: &str) {
+        let profiles = match &self.interaction_profile {
+            Some(path) => match interaction_profiles::get(path) {
+                Some(profile) => vec![profile],
+                None => {
+                    issues.push(format!(
+                        "{} uses unknown interaction profile `{}`",
+                        what, path
+                    ));
+                    return;
+                }
+            },
+            None => interaction_profiles::all().collect(),
+        };
+
+        let exists = profiles.iter().any(|profile| {
+            profile.components.iter().any(|component| {
+                component.ty == ComponentType::Pose
+                    && component.path == self.component
+                    && self
+                        .hand
+                        .map_or(true, |hand| component.exists_on(hand.user_path()))
+            })
+        });
+        if !exists {
+            issues.push(format!(
+                "{} component `{}` is not a pose of the selected controllers",
+                what, self.component
+            ));
+        }
+    }
+
+    pub fn matches(&self, interaction_profile: &str, binding: &str) -> bool {
+        self.interaction_profile
+            .as_ref()
+            .map_or(true, |profile| profile == interaction_profile)
+            && self.hand.map_or(true, |hand| {
+                user_paths::strip_user_path(binding, hand.user_path()).is_some()
+            })
+            && binding.ends_with(&self.component)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoseOffset {
+    #[serde(flatten)]
+    pub selector: BindingSelector,
+    pub offset: Transform,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoseFilter {
+    #[serde(flatten)]
+    pub selector: BindingSelector,
+    pub filter: PoseFilterConfig,
+}
+
+/// Folds the bindings of both hands onto a single controller
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OneHandedConfig {
+    /// The hand which is physically used
+    pub hand: Hand,
+    /// How the actions of the other hand are reached
+    pub switch: OneHandedSwitch,
+    /// Derive the pose of the other hand from the used hand, otherwise it is tracked normally
+    #[serde(default)]
+    pub derived_pose: Option<DerivedPose>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OneHandedSwitch {
+    /// The other hand's actions are used while `binding` is held
+    Modifier { binding: String },
+    /// Each press of `binding` switches between the hands
+    Cycle { binding: String },
+}
+
+impl OneHandedSwitch {
+    pub fn binding(&self) -> &str {
+        match self {
+            OneHandedSwitch::Modifier { binding } => binding,
+            OneHandedSwitch::Cycle { binding } => binding,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DerivedPose {
+    /// The other hand follows the used hand at a fixed offset
+    FixedOffset { offset: Transform },
+    /// The other hand mirrors the used hand across the user's head
+    Mirrored,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionBehavior {
+    pub action_set: String,
+    pub action: String,
+    pub behavior: ButtonBehavior,
+}
+
+#[derive(Debug)]
+pub enum ProfileError {
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+}
+
+impl Display for ProfileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProfileError::Io(err) => write!(f, "io error: {}", err),
+            ProfileError::Parse(err) => write!(f, "parse error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ProfileError {}
+
+/// Profiles are stored as `<data dir>/profiles/<application name>/<profile name>.json`
+pub fn application_dir(application_name: &str) -> PathBuf {
+    crate::data_dir()
+        .join("profiles")
+        .join(sanitize_file_name(application_name))
+}
+
+pub fn profile_path(application_name: &str, profile_name: &str) -> PathBuf {
+    application_dir(application_name).join(format!("{}.json", sanitize_file_name(profile_name)))
+}
+
+/// The name of the profile selected for an application, stored in `<application dir>/active`
+pub fn active_profile_name(application_name: &str) -> String {
+    fs::read_to_string(application_dir(application_name).join("active"))
+        .map(|name| name.trim().to_owned())
+        .ok()
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| DEFAULT_PROFILE_NAME.to_owned())
+}
+
+/// The applications which have a profile directory
+pub fn list_applications() -> Vec<String> {
+    list_dir(&crate::data_dir().join("profiles"), |entry| {
+        entry.file_type().map_or(false, |ty| ty.is_dir())
+    })
+}
+
+/// The names of an application's profiles
+pub fn list_profiles(application_name: &str) -> Vec<String> {
+    let mut names = list_dir(&application_dir(application_name), |entry| {
+        entry.path().extension().map_or(false, |ext| ext == "json")
+    });
+    for name in &mut names {
+        name.truncate(name.len() - ".json".len());
+    }
+    names
+}
+
+fn list_dir(dir: &std::path::Path, filter: impl Fn(&fs::DirEntry) -> bool) -> Vec<String> {
+    let mut names = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| filter(entry))
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+/// Selects the profile used the next time the application starts
+pub fn set_active_profile(application_name: &str, profile_name: &str) -> std::io::Result<()> {
+    let dir = application_dir(application_name);
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("active"), profile_name)
+}
+
+pub fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+impl RemapProfile {
+    pub fn load(path: &std::path::Path) -> Result<Self, ProfileError> {
+        let file = fs::File::open(path).map_err(ProfileError::Io)?;
+        serde_json::from_reader(std::io::BufReader::new(file)).map_err(ProfileError::Parse)
+    }
+
+    pub fn save(&self, path: &std::path::Path) -> Result<(), ProfileError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(ProfileError::Io)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(ProfileError::Parse)?;
+        fs::write(path, json).map_err(ProfileError::Io)
+    }
+
+    /// Problems which make parts of the profile ineffective, the layer logs these when loading
+    pub fn validate(&self) -> Vec<String> {
+        let mut issues = Vec::new();
+
+        for (i, behavior) in self.behaviors.iter().enumerate() {
+            if self.behaviors[..i].iter().any(|other| {
+                other.action_set == behavior.action_set && other.action == behavior.action
+            }) {
+                issues.push(format!(
+                    "behavior for `{}/{}` is configured more than once, only the first is used",
+                    behavior.action_set, behavior.action
+                ));
+            }
+            match behavior.behavior {
+                ButtonBehavior::Turbo { rate_hz } if rate_hz <= 0. => issues.push(format!(
+                    "turbo rate of `{}/{}` must be positive",
+                    behavior.action_set, behavior.action
+                )),
+                ButtonBehavior::DoubleTap { window_ms: 0 } => issues.push(format!(
+                    "double tap window of `{}/{}` must not be zero",
+                    behavior.action_set, behavior.action
+                )),
+                _ => {}
+            }
+        }
+
+        if let Some(one_handed) = &self.one_handed {
+            let binding = one_handed.switch.binding();
+            self.validate_binding(&mut issues, "one handed switch", binding);
+            if Hand::of_path(binding) == Some(one_handed.hand.other()) {
+                issues.push(format!(
+                    "one handed switch `{}` is on the hand which is not used",
+                    binding
+                ));
+            }
+        }
+
+        if let Some(playspace_mover) = &self.playspace_mover {
+            self.validate_binding(&mut issues, "playspace mover", &playspace_mover.binding);
+            if Hand::of_path(&playspace_mover.binding).is_none() {
+                issues.push(format!(
+                    "playspace mover binding `{}` must belong to a hand",
+                    playspace_mover.binding
+                ));
+            }
+        }
+
+        let reserved = self.reserved_bindings();
+        for (i, binding) in reserved.iter().enumerate() {
+            if reserved[..i].contains(binding) {
+                issues.push(format!("`{}` is reserved more than once", binding));
+            }
+        }
+
+        for (i, rebind) in self.rebinds.iter().enumerate() {
+            let name = format!(
+                "`{}/{}` on `{}`",
+                rebind.action_set, rebind.action, rebind.interaction_profile
+            );
+            if self.rebinds[..i].iter().any(|other| {
+                other.interaction_profile == rebind.interaction_profile
+                    && other.action_set == rebind.action_set
+                    && other.action == rebind.action
+            }) {
+                issues.push(format!("rebind of {} is configured more than once", name));
+            }
+            let profile = interaction_profiles::get(&rebind.interaction_profile);
+            if profile.is_none() {
+                issues.push(format!(
+                    "rebind of {} uses an unknown interaction profile",
+                    name
+                ));
+            }
+            for binding in &rebind.bindings {
+                if profile.map_or(false, |profile| !profile.has_binding(binding))
+                    && self.virtual_component(binding).is_none()
+                {
+                    issues.push(format!(
+                        "rebind of {}: `{}` does not exist on the interaction profile",
+                        name, binding
+                    ));
+                }
+                if reserved.contains(&binding.as_str()) {
+                    issues.push(format!(
+                        "rebind of {}: `{}` is reserved by the layer",
+                        name, binding
+                    ));
+                }
+            }
+        }
+
+        for advanced in &self.advanced_bindings {
+            advanced.validate(&mut issues, &reserved, self);
+        }
+
+        if let Some(desktop) = &self.desktop {
+            for key in &desktop.keys {
+                self.validate_binding(&mut issues, &format!("key `{}`", key.key), &key.binding);
+            }
+            for axis in &desktop.axes {
+                self.validate_binding(
+                    &mut issues,
+                    &format!("mouse axis `{}`", axis.axis),
+                    &axis.binding,
+                );
+                if !axis.axis.starts_with("REL_") {
+                    issues.push(format!(
+                        "mouse axis `{}` is not a relative axis like `REL_WHEEL`",
+                        axis.axis
+                    ));
+                }
+            }
+        }
+
+        if let Some(gamepad) = &self.gamepad {
+            for binding in &gamepad.bindings {
+                if !gamepad::is_input(&binding.gamepad) {
+                    issues.push(format!("`{}` is not a gamepad input", binding.gamepad));
+                }
+                self.validate_binding(
+                    &mut issues,
+                    &format!("gamepad `{}`", binding.gamepad),
+                    &binding.binding,
+                );
+            }
+        }
+
+        if let Some(hand_tracking) = &self.hand_tracking {
+            for (name, range) in [("pinch", hand_tracking.pinch), ("fist", hand_tracking.fist)] {
+                if range.open <= range.closed || range.closed < 0. {
+                    issues.push(format!(
+                        "hand tracking {} needs an open distance above a non-negative closed one",
+                        name
+                    ));
+                }
+            }
+            if !(-1. ..=1.).contains(&hand_tracking.palm_facing) {
+                issues.push("hand tracking palm facing must be a cosine from -1 to 1".to_owned());
+            }
+        }
+
+        if let Some(eye_gaze) = &self.eye_gaze {
+            if let Some(trigger) = eye_gaze.aim.as_ref().and_then(|aim| aim.trigger.as_ref()) {
+                self.validate_binding(&mut issues, "gaze trigger", trigger);
+            }
+            if let (Some(aim), GazeFallback::Aim(hand)) = (&eye_gaze.aim, eye_gaze.fallback) {
+                if aim.hand == hand {
+                    issues.push(
+                        "the gaze cannot fall back to the aim of the hand it aims".to_owned(),
+                    );
+                }
+            }
+        }
+
+        self.validate_virtual_devices(&mut issues);
+
+        for pose_offset in &self.pose_offsets {
+            pose_offset.selector.validate(&mut issues, "pose offset");
+        }
+
+        for pose_filter in &self.pose_filters {
+            pose_filter.selector.validate(&mut issues, "pose filter");
+            let filter = &pose_filter.filter;
+            match filter.smoothing {
+                Some(Smoothing::OneEuro {
+                    min_cutoff,
+                    beta,
+                    derivative_cutoff,
+                }) if min_cutoff <= 0. || beta < 0. || derivative_cutoff <= 0. => {
+                    issues.push(format!(
+                        "one euro filter for `{}` needs positive cutoffs and a non-negative beta",
+                        pose_filter.selector.component
+                    ))
+                }
+                Some(Smoothing::Exponential { time_constant_ms }) if time_constant_ms < 0. => {
+                    issues.push(format!(
+                        "exponential filter for `{}` has a negative time constant",
+                        pose_filter.selector.component
+                    ))
+                }
+                _ => {}
+            }
+            if filter.max_prediction_ms.map_or(false, |ms| ms < 0.) {
+                issues.push(format!(
+                    "prediction clamp for `{}` must not be negative",
+                    pose_filter.selector.component
+                ));
+            }
+        }
+
+        issues
+    }
+
+    /// Loads the active profile of an application, falling back to an empty profile
+    pub fn load_for_application(application_name: &str) -> Self {
+        let path = profile_path(application_name, &active_profile_name(application_name));
+
+        match Self::load(&path) {
+            Ok(profile) => {
+                info!("Loaded remap profile `{}`", path.display());
+                for issue in profile.validate() {
+                    warn!("{}: {}", path.display(), issue);
+                }
+                profile
+            }
+            Err(ProfileError::Io(err)) if err.kind() == std::io::ErrorKind::NotFound => {
+                Default::default()
+            }
+            Err(err) => {
+                warn!("Failed to load remap profile `{}`: {}", path.display(), err);
+                Default::default()
+            }
+        }
+    }
+
+    fn validate_binding(&self, issues: &mut Vec<String>, what: &str, binding: &str) {
+        if self.virtual_component(binding).is_none()
+            && !interaction_profiles::all().any(|profile| profile.has_binding(binding))
+        {
+            issues.push(format!(
+                "{} binding `{}` is not an input of any known interaction profile or virtual device",
+                what, binding
+            ));
+        }
+    }
+
+    fn validate_virtual_devices(&self, issues: &mut Vec<String>) {
+        for (i, device) in self.virtual_devices.iter().enumerate() {
+            let user_path = &device.user_path;
+            if !user_path.starts_with("/user/") || user_path.ends_with('/') {
+                issues.push(format!(
+                    "virtual device `{}` needs a top level path like `/user/virtual/pedal`",
+                    user_path
+                ));
+            }
+            if interaction_profiles::all()
+                .flat_map(|profile| profile.user_paths.iter())
+                .any(|known| {
+                    user_paths::strip_user_path(known, user_path).is_some()
+                        || user_paths::strip_user_path(user_path, known).is_some()
+                })
+                || self.virtual_devices[..i].iter().any(|other| {
+                    user_paths::strip_user_path(&other.user_path, user_path).is_some()
+                        || user_paths::strip_user_path(user_path, &other.user_path).is_some()
+                })
+            {
+                issues.push(format!(
+                    "virtual device `{}` overlaps the path of another device",
+                    user_path
+                ));
+            }
+
+            for (j, component) in device.components.iter().enumerate() {
+                let name = format!("virtual input `{}{}`", user_path, component.path);
+                if !component.path.starts_with("/input/") {
+                    issues.push(format!("{} must be below `/input/`", name));
+                }
+                if device.components[..j]
+                    .iter()
+                    .any(|other| other.path == component.path)
+                {
+                    issues.push(format!("{} is configured more than once", name));
+                }
+                let inputs = match &component.source {
+                    VirtualSource::External => continue,
+                    VirtualSource::Axis { inputs } => inputs
+                        .iter()
+                        .map(|input| input.binding.as_str())
+                        .collect::<Vec<_>>(),
+                    VirtualSource::Combo { inputs } => inputs.iter().map(String::as_str).collect(),
+                };
+                if inputs.is_empty() {
+                    issues.push(format!("{} has no inputs", name));
+                }
+                for input in inputs {
+                    self.validate_binding(issues, &name, input);
+                }
+            }
+        }
+    }
+
+    /// The type of an input of the virtual devices, `None` for other inputs
+    pub fn virtual_component(&self, binding: &str) -> Option<ComponentType> {
+        virtual_device::component_type(&self.virtual_devices, binding)
+    }
+
+    /// Physical bindings the layer uses for itself and which are never given to the application
+    pub fn reserved_bindings(&self) -> Vec<&str> {
+        let mut reserved = Vec::new();
+        if let Some(one_handed) = &self.one_handed {
+            reserved.push(one_handed.switch.binding());
+        }
+        if let Some(playspace_mover) = &self.playspace_mover {
+            reserved.push(&playspace_mover.binding);
+        }
+        if let Some(trigger) = self
+            .eye_gaze
+            .as_ref()
+            .and_then(|eye_gaze| eye_gaze.aim.as_ref())
+            .and_then(|aim| aim.trigger.as_ref())
+        {
+            reserved.push(trigger);
+        }
+        reserved
+    }
+
+    pub fn pose_offset(&self, interaction_profile: &str, binding: &str) -> Option<&Transform> {
+        self.pose_offsets
+            .iter()
+            .find(|pose_offset| pose_offset.selector.matches(interaction_profile, binding))
+            .map(|pose_offset| &pose_offset.offset)
+    }
+
+    pub fn pose_filter(
+        &self,
+        interaction_profile: &str,
+        binding: &str,
+    ) -> Option<&PoseFilterConfig> {
+        self.pose_filters
+            .iter()
+            .find(|pose_filter| pose_filter.selector.matches(interaction_profile, binding))
+            .map(|pose_filter| &pose_filter.filter)
+    }
+
+    /// Whether settings depend on the interaction profile currently bound to each hand
+    pub fn uses_current_interaction_profile(&self) -> bool {
+        !self.pose_offsets.is_empty() || !self.pose_filters.is_empty()
+    }
+
+    pub fn rebind(
+        &self,
+        interaction_profile: &str,
+        action_set: &str,
+        action: &str,
+    ) -> Option<&Rebind> {
+        self.rebinds.iter().find(|rebind| {
+            rebind.interaction_profile == interaction_profile
+                && rebind.action_set == action_set
+                && rebind.action == action
+        })
+    }
+
+    /// Adds a rebind, replacing any earlier one of the same action and interaction profile
+    pub fn set_rebind(&mut self, rebind: Rebind) {
+        self.rebinds.retain(|other| {
+            other.interaction_profile != rebind.interaction_profile
+                || other.action_set != rebind.action_set
+                || other.action != rebind.action
+        });
+        self.rebinds.push(rebind);
+    }
+
+    pub fn behavior(&self, action_set: &str, action: &str) -> Option<&ButtonBehavior> {
+        self.behaviors
+            .iter()
+            .find(|behavior| behavior.action_set == action_set && behavior.action == action)
+            .map(|behavior| &behavior.behavior)
+    }
+}
@@ -0,0 +1,885 @@
+//! Recording of the input an application received, used to reproduce bug reports.
+//!
+//! A recording is a file of JSON records, one per line, starting with a [`Record::Header`].
+//! Actions, spaces and physical inputs are named once by an id record and referred to by id
+//! afterwards. Physical inputs are written every `xrSyncActions` but only when their value changed.
+//!
+//! A [`Replay`] feeds the physical inputs of a recording back to the layer in place of the
+//! runtime, one recorded sync per `xrSyncActions`.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use log::{info, warn};
+use openxr::sys as xr;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use thunderdome::Index;
+
+use crate::wrappers::{
+    instance::InstanceWrapper, layer_action, layer_action_set, space::SpaceKind, XrHandle,
+};
+
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Recording starts with the instance when set to the path of the file to write
+pub const RECORD_ENV_VAR: &str = "SUINPUT_LAYER_RECORD";
+
+/// Physical input is read from the recording at this path instead of the runtime when set
+pub const REPLAY_ENV_VAR: &str = "SUINPUT_LAYER_REPLAY";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Record {
+    Header {
+        format_version: u32,
+        application_name: String,
+        application_version: u32,
+        runtime: String,
+        /// The name of the remap profile which was active
+        profile: String,
+    },
+    /// Names an action of the application together with the subaction path it was queried with
+    Action {
+        id: u32,
+        action_set: String,
+        action: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sub_action_path: Option<String>,
+    },
+    Space {
+        id: u32,
+        space: RecordedSpace,
+    },
+    /// Names a physical input, e.g. `/user/hand/left/input/trigger/value`
+    Input {
+        id: u32,
+        binding: String,
+    },
+    /// The physical inputs which changed since the previous sync
+    Sync {
+        time: i64,
+        inputs: Vec<(u32, InputValue)>,
+    },
+    /// An action state returned to the application
+    State {
+        time: i64,
+        action: u32,
+        state: RecordedState,
+    },
+    /// A space location returned to the application
+    Location {
+        time: i64,
+        space: u32,
+        base_space: u32,
+        flags: u64,
+        /// Position followed by the orientation quaternion
+        pose: [f32; 7],
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum InputValue {
+    Boolean(bool),
+    Float(f32),
+    Vector2f([f32; 2]),
+}
+
+impl InputValue {
+    /// Float inputs are pressed from half way, like the runtimes convert them for boolean actions
+    pub fn is_pressed(self) -> bool {
+        match self {
+            InputValue::Boolean(pressed) => pressed,
+            InputValue::Float(value) => value >= 0.5,
+            InputValue::Vector2f(_) => false,
+        }
+    }
+
+    pub fn as_float(self) -> f32 {
+        match self {
+            InputValue::Boolean(pressed) => pressed as u8 as f32,
+            InputValue::Float(value) => value,
+            InputValue::Vector2f(_) => 0.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RecordedState {
+    Boolean {
+        is_active: bool,
+        state: bool,
+        changed: bool,
+    },
+    Float {
+        is_active: bool,
+        state: f32,
+        changed: bool,
+    },
+    Vector2f {
+        is_active: bool,
+        state: [f32; 2],
+        changed: bool,
+    },
+    Pose {
+        is_active: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RecordedSpace {
+    Action {
+        action: u32,
+    },
+    Reference {
+        reference_space_type: i32,
+    },
+    /// A space which was not created through the layer
+    Unknown,
+}
+
+/// Recordings started over IPC without a path are stored as
+/// `<data dir>/recordings/<application name>/<unix time>.jsonl`
+pub fn default_path(application_name: &str) -> PathBuf {
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    crate::data_dir()
+        .join("recordings")
+        .join(crate::profile::sanitize_file_name(application_name))
+        .join(format!("{}.jsonl", seconds))
+}
+
+/// Reads every record of a recording, checking the format version of the header
+pub fn read(path: &Path) -> std::io::Result<Vec<Record>> {
+    let invalid = |message: String| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+
+    let mut records = Vec::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        records.push(serde_json::from_str::<Record>(&line)?);
+    }
+
+    match records.first() {
+        Some(Record::Header { format_version, .. }) if *format_version == FORMAT_VERSION => {
+            Ok(records)
+        }
+        Some(Record::Header { format_version, .. }) => Err(invalid(format!(
+            "unsupported recording format version {}",
+            format_version
+        ))),
+        _ => Err(invalid("the recording has no header".to_owned())),
+    }
+}
+
+#[derive(Default)]
+pub struct Recorder {
+    writer: Mutex<Option<RecordingWriter>>,
+}
+
+struct RecordingWriter {
+    path: PathBuf,
+    file: BufWriter<File>,
+    actions: HashMap<(xr::Action, xr::Path), u32>,
+    spaces: HashMap<xr::Space, u32>,
+    /// The last value written for each of the layer's physical inputs, `None` before the first sync
+    inputs: Option<Vec<InputValue>>,
+}
+
+impl RecordingWriter {
+    fn write(&mut self, record: &Record) -> std::io::Result<()> {
+        serde_json::to_writer(&mut self.file, record)?;
+        self.file.write_all(b"\n")
+    }
+
+    fn action_id(
+        &mut self,
+        instance: &InstanceWrapper,
+        action: xr::Action,
+        sub_action_path: xr::Path,
+    ) -> std::io::Result<u32> {
+        if let Some(id) = self.actions.get(&(action, sub_action_path)) {
+            return Ok(*id);
+        }
+
+        let id = self.actions.len() as u32;
+        self.actions.insert((action, sub_action_path), id);
+
+        let (action_set, action) = {
+            let all_actions = layer_action::all();
+            let all_action_sets = layer_action_set::all();
+            match layer_action::get(&all_actions, action) {
+                Ok(action) => (
+                    Index::from_bits(action.action_set.into_raw())
+                        .and_then(|index| all_action_sets.get(index))
+                        .map(|action_set| action_set.name.clone())
+                        .unwrap_or_default(),
+                    action.name.clone(),
+                ),
+                Err(_) => Default::default(),
+            }
+        };
+        let sub_action_path = if sub_action_path == xr::Path::NULL {
+            None
+        } else {
+            instance.inner.path_to_string(sub_action_path).ok()
+        };
+
+        self.write(&Record::Action {
+            id,
+            action_set,
+            action,
+            sub_action_path,
+        })?;
+        Ok(id)
+    }
+
+    fn space_id(&mut self, instance: &InstanceWrapper, space: xr::Space) -> std::io::Result<u32> {
+        if let Some(id) = self.spaces.get(&space) {
+            return Ok(*id);
+        }
+
+        let wrapper = xr::Space::all_wrappers()
+            .get(&space)
+            .map(|wrapper| wrapper.clone());
+        let recorded = match wrapper.as_deref().map(|wrapper| &wrapper.kind) {
+            Some(SpaceKind::Action {
+                action,
+                sub_action_path,
+                ..
+            }) => RecordedSpace::Action {
+                action: self.action_id(instance, *action, *sub_action_path)?,
+            },
+            Some(SpaceKind::Reference {
+                reference_space_type,
+                ..
+            }) => RecordedSpace::Reference {
+                reference_space_type: reference_space_type.into_raw(),
+            },
+            None => RecordedSpace::Unknown,
+        };
+
+        let id = self.spaces.len() as u32;
+        self.spaces.insert(space, id);
+        self.write(&Record::Space {
+            id,
+            space: recorded,
+        })?;
+        Ok(id)
+    }
+}
+
+impl Recorder {
+    pub fn start(&self, path: &Path, header: Record) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut writer = RecordingWriter {
+            path: path.to_owned(),
+            file: BufWriter::new(File::create(path)?),
+            actions: HashMap::new(),
+            spaces: HashMap::new(),
+            inputs: None,
+        };
+        writer.write(&header)?;
+        *self.writer.lock() = Some(writer);
+        Ok(())
+    }
+
+    /// Stops the recording, returning the path it was written to
+    pub fn stop(&self) -> Option<PathBuf> {
+        let mut writer = self.writer.lock().take()?;
+        if let Err(err) = writer.file.flush() {
+            warn!(
+                "Failed to write recording `{}`: {}",
+                writer.path.display(),
+                err
+            );
+        }
+        Some(writer.path)
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.writer.lock().is_some()
+    }
+
+    /// Runs `f` on the open recording, a failed write ends the recording
+    fn with_writer(&self, f: impl FnOnce(&mut RecordingWriter) -> std::io::Result<()>) {
+        let mut guard = self.writer.lock();
+        let writer = match guard.as_mut() {
+            Some(writer) => writer,
+            None => return,
+        };
+        if let Err(err) = f(writer) {
+            warn!(
+                "Stopped recording `{}` after a failed write: {}",
+                writer.path.display(),
+                err
+            );
+            *guard = None;
+        }
+    }
+
+    /// Writes the physical inputs read during a sync and flushes the file
+    pub fn record_sync<'a>(
+        &self,
+        bindings: impl IntoIterator<Item = &'a str>,
+        values: Vec<InputValue>,
+        time: xr::Time,
+    ) {
+        self.with_writer(|writer| {
+            let changed = match &writer.inputs {
+                Some(previous) => values
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, value)| previous.get(*i) != Some(*value))
+                    .map(|(i, value)| (i as u32, *value))
+                    .collect(),
+                None => {
+                    for (id, binding) in bindings.into_iter().enumerate() {
+                        writer.write(&Record::Input {
+                            id: id as u32,
+                            binding: binding.to_owned(),
+                        })?;
+                    }
+                    values
+                        .iter()
+                        .enumerate()
+                        .map(|(i, value)| (i as u32, *value))
+                        .collect()
+                }
+            };
+            writer.inputs = Some(values);
+            writer.write(&Record::Sync {
+                time: time.as_nanos(),
+                inputs: changed,
+            })?;
+            writer.file.flush()
+        })
+    }
+
+    pub fn record_state(
+        &self,
+        instance: &InstanceWrapper,
+        action: xr::Action,
+        sub_action_path: xr::Path,
+        time: xr::Time,
+        state: RecordedState,
+    ) {
+        self.with_writer(|writer| {
+            let action = writer.action_id(instance, action, sub_action_path)?;
+            writer.write(&Record::State {
+                time: time.as_nanos(),
+                action,
+                state,
+            })
+        })
+    }
+
+    pub fn record_location(
+        &self,
+        instance: &InstanceWrapper,
+        space: xr::Space,
+        base_space: xr::Space,
+        time: xr::Time,
+        location: &xr::SpaceLocation,
+    ) {
+        self.with_writer(|writer| {
+            let space = writer.space_id(instance, space)?;
+            let base_space = writer.space_id(instance, base_space)?;
+            let pose = location.pose;
+            writer.write(&Record::Location {
+                time: time.as_nanos(),
+                space,
+                base_space,
+                flags: location.location_flags.into_raw(),
+                pose: [
+                    pose.position.x,
+                    pose.position.y,
+                    pose.position.z,
+                    pose.orientation.x,
+                    pose.orientation.y,
+                    pose.orientation.z,
+                    pose.orientation.w,
+                ],
+            })
+        })
+    }
+}
+
+/// The physical inputs of a recording, applied one sync at a time
+pub struct Replay {
+    path: PathBuf,
+    application_name: String,
+    bindings: HashMap<u32, String>,
+    syncs: Vec<(i64, Vec<(u32, InputValue)>)>,
+    next: usize,
+    values: HashMap<String, InputValue>,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let mut application_name = String::new();
+        let mut bindings = HashMap::new();
+        let mut syncs = Vec::new();
+        for record in read(path)? {
+            match record {
+                Record::Header {
+                    application_name: name,
+                    ..
+                } => application_name = name,
+                Record::Input { id, binding } => {
+                    bindings.insert(id, binding);
+                }
+                Record::Sync { time, inputs } => syncs.push((time, inputs)),
+                _ => {}
+            }
+        }
+
+        Ok(Replay {
+            path: path.to_owned(),
+            application_name,
+            bindings,
+            syncs,
+            next: 0,
+            values: HashMap::new(),
+        })
+    }
+
+    pub fn application_name(&self) -> &str {
+        &self.application_name
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.syncs.len()
+    }
+
+    /// Applies the next recorded sync and returns the predicted display time it was recorded at.
+    /// Once the recording ends every input is released and `None` is returned.
+    pub fn advance(&mut self) -> Option<xr::Time> {
+        let (time, inputs) = match self.syncs.get(self.next) {
+            Some(sync) => sync,
+            None => {
+                if !self.values.is_empty() {
+                    info!("Finished replaying `{}`", self.path.display());
+                    self.values.clear();
+                }
+                return None;
+            }
+        };
+        self.next += 1;
+
+        for (id, value) in inputs {
+            match self.bindings.get(id) {
+                Some(binding) => {
+                    self.values.insert(binding.clone(), *value);
+                }
+                None => warn!("Replay refers to unknown input {}", id),
+            }
+        }
+        Some(xr::Time::from_nanos(*time))
+    }
+
+    /// The value of a physical input, a binding without a component such as
+    /// `/user/hand/left/input/trigger` reads its `click` or `value` component like a runtime would
+    pub fn value(&self, binding: &str) -> Option<InputValue> {
+        self.values.get(binding).copied().or_else(|| {
+            ["click", "value"]
+                .iter()
+                .find_map(|component| self.values.get(&format!("{}/{}", binding, component)))
+                .copied()
+        })
+    }
+
+    pub fn is_pressed(&self, binding: &str) -> bool {
+        self.value(binding).map_or(false, InputValue::is_pressed)
+    }
+}
+
+/// The action states of a recording grouped by the sync they followed, with the names of their
+/// actions resolved so recordings of different runs can be compared
+fn states_per_sync(records: &[Record]) -> Vec<Vec<(String, RecordedState)>> {
+    let mut actions = HashMap::new();
+    let mut syncs = vec![Vec::new()];
+    for record in records {
+        match record {
+            Record::Action {
+                id,
+                action_set,
+                action,
+                sub_action_path,
+            } => {
+                let mut name = format!("{}/{}", action_set, action);
+                if let Some(sub_action_path) = sub_action_path {
+                    name.push_str(&format!(" {}", sub_action_path));
+                }
+                actions.insert(*id, name);
+            }
+            Record::Sync { .. } => syncs.push(Vec::new()),
+            Record::State { action, state, .. } => syncs.last_mut().unwrap().push((
+                actions
+                    .get(action)
+                    .cloned()
+                    .unwrap_or_else(|| action.to_string()),
+                *state,
+            )),
+            _ => {}
+        }
+    }
+    syncs
+}
+
+/// Describes every action state which differs between two recordings, frame by frame
+pub fn compare_states(expected: &[Record], actual: &[Record]) -> Vec<String> {
+    let expected = states_per_sync(expected);
+    let actual = states_per_sync(actual);
+
+    let mut differences = Vec::new();
+    if expected.len() != actual.len() {
+        differences.push(format!(
+            "expected {} syncs, found {}",
+            expected.len() - 1,
+            actual.len() - 1
+        ));
+    }
+    for (sync, (expected, actual)) in expected.iter().zip(&actual).enumerate() {
+        for (i, (name, state)) in expected.iter().enumerate() {
+            match actual.get(i) {
+                Some((actual_name, actual_state)) if actual_name == name => {
+                    if actual_state != state {
+                        differences.push(format!(
+                            "sync {}: {} expected {:?}, found {:?}",
+                            sync, name, state, actual_state
+                        ));
+                    }
+                }
+                Some((actual_name, _)) => differences.push(format!(
+                    "sync {}: expected a state of {}, found {}",
+                    sync, name, actual_name
+                )),
+                None => differences.push(format!("sync {}: missing state of {}", sync, name)),
+            }
+        }
+        for (name, _) in actual.iter().skip(expected.len()) {
+            differences.push(format!("sync {}: unexpected state of {}", sync, name));
+        }
+    }
+    differences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_data(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata")
+            .join(name)
+    }
+
+    #[test]
+    fn replay_applies_one_sync_at_a_time() {
+        let mut replay = Replay::load(&test_data("replay.jsonl")).unwrap();
+        assert_eq!(replay.application_name(), "hello_xr");
+        assert_eq!(replay.value("/user/hand/left/input/trigger/value"), None);
+
+        assert_eq!(replay.advance(), Some(xr::Time::from_nanos(1000)));
+        assert_eq!(
+            replay.value("/user/hand/left/input/trigger"),
+            Some(InputValue::Float(0.2))
+        );
+        assert!(!replay.is_pressed("/user/hand/right/input/a"));
+
+        assert_eq!(replay.advance(), Some(xr::Time::from_nanos(2000)));
+        assert!(replay.is_pressed("/user/hand/left/input/trigger/value"));
+        assert!(replay.is_pressed("/user/hand/right/input/a"));
+
+        //Inputs missing from a sync keep their value
+        assert_eq!(replay.advance(), Some(xr::Time::from_nanos(3000)));
+        assert_eq!(
+            replay.value("/user/hand/left/input/thumbstick"),
+            Some(InputValue::Vector2f([0.5, -0.25]))
+        );
+        assert!(replay.is_pressed("/user/hand/right/input/a"));
+
+        assert!(replay.is_finished());
+        assert_eq!(replay.advance(), None);
+        assert_eq!(replay.value("/user/hand/right/input/a/click"), None);
+    }
+
+    #[test]
+    fn recording_matches_itself() {
+        let records = read(&test_data("replay.jsonl")).unwrap();
+        assert!(compare_states(&records, &records).is_empty());
+    }
+
+    #[test]
+    fn differences_match_golden_file() {
+        let expected = read(&test_data("replay.jsonl")).unwrap();
+        let actual = read(&test_data("replay_diverged.jsonl")).unwrap();
+        let golden = std::fs::read_to_string(test_data("replay_diverged.txt")).unwrap();
+        assert_eq!(
+            compare_states(&expected, &actual),
+            golden.lines().collect::<Vec<_>>()
+        );
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("recording-{}-{}.jsonl", std::process::id(), name))
+    }
+
+    fn header(format_version: u32) -> Record {
+        Record::Header {
+            format_version,
+            application_name: "hello_xr".to_owned(),
+            application_version: 1,
+            runtime: "SteamVR/OpenXR".to_owned(),
+            profile: "default".to_owned(),
+        }
+    }
+
+    #[test]
+    fn recorded_syncs_read_back() {
+        let path = temp_path("syncs");
+        let bindings = [
+            "/user/hand/left/input/trigger/value",
+            "/user/hand/right/input/a/click",
+        ];
+        let recorder = Recorder::default();
+        recorder.start(&path, header(FORMAT_VERSION)).unwrap();
+        assert!(recorder.is_recording());
+        recorder.record_sync(
+            bindings,
+            vec![InputValue::Float(0.2), InputValue::Boolean(false)],
+            xr::Time::from_nanos(1000),
+        );
+        recorder.record_sync(
+            bindings,
+            vec![InputValue::Float(0.2), InputValue::Boolean(true)],
+            xr::Time::from_nanos(2000),
+        );
+        assert_eq!(recorder.stop(), Some(path.clone()));
+        assert!(!recorder.is_recording());
+
+        let records = read(&path).unwrap();
+        assert_eq!(
+            records,
+            [
+                header(FORMAT_VERSION),
+                Record::Input {
+                    id: 0,
+                    binding: bindings[0].to_owned(),
+                },
+                Record::Input {
+                    id: 1,
+                    binding: bindings[1].to_owned(),
+                },
+                Record::Sync {
+                    time: 1000,
+                    inputs: vec![(0, InputValue::Float(0.2)), (1, InputValue::Boolean(false))],
+                },
+                //Only changed inputs are written after the first sync
+                Record::Sync {
+                    time: 2000,
+                    inputs: vec![(1, InputValue::Boolean(true))],
+                },
+            ]
+        );
+
+        let mut replay = Replay::load(&path).unwrap();
+        replay.advance();
+        replay.advance();
+        assert_eq!(replay.value(bindings[0]), Some(InputValue::Float(0.2)));
+        assert!(replay.is_pressed(bindings[1]));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn records_keep_their_values() {
+        let records = [
+            Record::Action {
+                id: 0,
+                action_set: "gameplay".to_owned(),
+                action: "move".to_owned(),
+                sub_action_path: None,
+            },
+            Record::Space {
+                id: 0,
+                space: RecordedSpace::Action { action: 0 },
+            },
+            Record::Space {
+                id: 1,
+                space: RecordedSpace::Reference {
+                    reference_space_type: 2,
+                },
+            },
+            Record::Space {
+                id: 2,
+                space: RecordedSpace::Unknown,
+            },
+            Record::Sync {
+                time: 1000,
+                inputs: vec![(0, InputValue::Vector2f([0.5, -0.25]))],
+            },
+            Record::State {
+                time: 1000,
+                action: 0,
+                state: RecordedState::Vector2f {
+                    is_active: true,
+                    state: [0.5, -0.25],
+                    changed: true,
+                },
+            },
+            Record::State {
+                time: 1000,
+                action: 0,
+                state: RecordedState::Pose { is_active: false },
+            },
+            Record::Location {
+                time: 1000,
+                space: 0,
+                base_space: 1,
+                flags: 0xf,
+                pose: [0., 1.5, -0.25, 0., 0., 0., 1.],
+            },
+        ];
+        for record in records {
+            let json = serde_json::to_string(&record).unwrap();
+            assert_eq!(
+                serde_json::from_str::<Record>(&json).unwrap(),
+                record,
+                "{}",
+                json
+            );
+        }
+    }
+
+    #[test]
+    fn read_checks_the_header() {
+        let path = temp_path("header");
+        for (records, is_valid) in [
+            (vec![header(FORMAT_VERSION)], true),
+            (vec![header(FORMAT_VERSION + 1)], false),
+            (
+                vec![Record::Input {
+                    id: 0,
+                    binding: "/user/hand/right/input/a/click".to_owned(),
+                }],
+                false,
+            ),
+            (vec![], false),
+        ] {
+            let lines = records
+                .iter()
+                .map(|record| serde_json::to_string(record).unwrap() + "\n")
+                .collect::<String>();
+            std::fs::write(&path, lines).unwrap();
+            assert_eq!(read(&path).is_ok(), is_valid, "{:?}", records);
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn compared_states() {
+        let action = |id: u32, action: &str| Record::Action {
+            id,
+            action_set: "gameplay".to_owned(),
+            action: action.to_owned(),
+            sub_action_path: None,
+        };
+        let sync = || Record::Sync {
+            time: 0,
+            inputs: Vec::new(),
+        };
+        let state = |action: u32, state: bool| Record::State {
+            time: 0,
+            action,
+            state: RecordedState::Boolean {
+                is_active: true,
+                state,
+                changed: false,
+            },
+        };
+        let expected = [
+            action(0, "jump"),
+            action(1, "crouch"),
+            sync(),
+            state(0, true),
+            state(1, false),
+        ];
+
+        for (actual, differences) in [
+            //Ids may differ between runs
+            (
+                vec![
+                    action(1, "jump"),
+                    action(0, "crouch"),
+                    sync(),
+                    state(1, true),
+                    state(0, false),
+                ],
+                vec![],
+            ),
+            (
+                vec![
+                    action(0, "jump"),
+                    action(1, "crouch"),
+                    sync(),
+                    state(0, false),
+                    state(1, false),
+                ],
+                vec![
+                    "sync 1: gameplay/jump expected Boolean { is_active: true, state: true, \
+                     changed: false }, found Boolean { is_active: true, state: false, changed: \
+                     false }",
+                ],
+            ),
+            (
+                vec![
+                    action(0, "jump"),
+                    action(1, "crouch"),
+                    sync(),
+                    state(1, false),
+                ],
+                vec![
+                    "sync 1: expected a state of gameplay/jump, found gameplay/crouch",
+                    "sync 1: missing state of gameplay/crouch",
+                ],
+            ),
+            (
+                vec![
+                    action(0, "jump"),
+                    action(1, "crouch"),
+                    sync(),
+                    state(0, true),
+                    state(1, false),
+                    state(1, false),
+                ],
+                vec!["sync 1: unexpected state of gameplay/crouch"],
+            ),
+            (
+                vec![
+                    action(0, "jump"),
+                    action(1, "crouch"),
+                    sync(),
+                    state(0, true),
+                    state(1, false),
+                    sync(),
+                ],
+                vec!["expected 1 syncs, found 2"],
+            ),
+        ] {
+            assert_eq!(compare_states(&expected, &actual), differences);
+        }
+    }
+}
@@ -0,0 +1,652 @@
+//! Converts SteamVR Input binding files (`bindings_*.json`) into remap profiles.
+//!
+//! SteamVR refers to OpenXR actions as `/actions/<action set>/in/<action>` and groups the inputs of
+//! a physical component into a source with a mode. Each mode is mapped onto rebinds, advanced
+//! bindings and button behaviors, everything else is reported as an issue.
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    input::{
+        advanced_binding::{BindingKind, DpadDirection},
+        behavior::ButtonBehavior,
+        interaction_profiles::{self, ComponentType, InteractionProfile},
+    },
+    profile::{ActionBehavior, AdvancedBinding, Rebind, RemapProfile},
+};
+
+/// Used for analog inputs in button mode when the file does not set a threshold
+const DEFAULT_ACTIVATE_THRESHOLD: f32 = 0.8;
+const DEFAULT_DEACTIVATE_THRESHOLD: f32 = 0.7;
+const DEFAULT_LONG_PRESS_MS: u32 = 500;
+const DEFAULT_DOUBLE_TAP_MS: u32 = 300;
+
+pub struct SteamVrImport {
+    pub profile: RemapProfile,
+    /// Constructs which were not translated or only approximated
+    pub issues: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct BindingFile {
+    controller_type: String,
+    #[serde(default)]
+    bindings: BTreeMap<String, ActionSetBindings>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct ActionSetBindings {
+    sources: Vec<Source>,
+    chords: Vec<Chord>,
+    poses: Vec<Value>,
+    haptics: Vec<Value>,
+    skeleton: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+struct Source {
+    path: String,
+    mode: String,
+    #[serde(default)]
+    parameters: BTreeMap<String, Value>,
+    #[serde(default)]
+    inputs: BTreeMap<String, Output>,
+}
+
+#[derive(Deserialize)]
+struct Output {
+    output: String,
+}
+
+#[derive(Deserialize)]
+struct Chord {
+    output: String,
+    /// Pairs of a source path and one of its inputs
+    inputs: Vec<(String, String)>,
+}
+
+/// The interaction profile matching a SteamVR controller type
+pub fn interaction_profile(controller_type: &str) -> Option<&'static str> {
+    Some(match controller_type {
+        "knuckles" => "/interaction_profiles/valve/index_controller",
+        "oculus_touch" => "/interaction_profiles/oculus/touch_controller",
+        "vive_controller" => "/interaction_profiles/htc/vive_controller",
+        "holographic_controller" => "/interaction_profiles/microsoft/motion_controller",
+        "hpmotioncontroller" => "/interaction_profiles/hp/mixed_reality_controller",
+        _ => return None,
+    })
+}
+
+pub fn import(json: &str) -> Result<SteamVrImport, serde_json::Error> {
+    let file: BindingFile = serde_json::from_str(json)?;
+
+    let interaction_profile =
+        match interaction_profile(&file.controller_type).and_then(interaction_profiles::get) {
+            Some(interaction_profile) => interaction_profile,
+            None => {
+                return Ok(SteamVrImport {
+                    profile: RemapProfile::default(),
+                    issues: vec![format!(
+                        "controller type `{}` has no matching interaction profile",
+                        file.controller_type
+                    )],
+                })
+            }
+        };
+    let mut importer = Importer {
+        interaction_profile,
+        simple: BTreeMap::new(),
+        profile: RemapProfile::default(),
+        issues: Vec::new(),
+    };
+
+    for (action_set, bindings) in &file.bindings {
+        for source in &bindings.sources {
+            importer.source(source);
+        }
+        for chord in &bindings.chords {
+            importer.chord(chord);
+        }
+        for (section, count) in [
+            ("pose", bindings.poses.len()),
+            ("haptic", bindings.haptics.len()),
+            ("skeleton", bindings.skeleton.len()),
+        ] {
+            if count > 0 {
+                importer.issues.push(format!(
+                    "{} {} binding(s) of `{}` were skipped, these are bound by the runtime",
+                    count, section, action_set
+                ));
+            }
+        }
+    }
+
+    Ok(importer.finish())
+}
+
+struct Importer {
+    interaction_profile: &'static InteractionProfile,
+    /// Plain bindings of every action which appears in the file, keyed by action set and action
+    simple: BTreeMap<(String, String), Vec<String>>,
+    profile: RemapProfile,
+    issues: Vec<String>,
+}
+
+impl Importer {
+    /// Splits `/actions/<action set>/in/<action>`
+    fn action(&mut self, output: &str) -> Option<(String, String)> {
+        let parsed = output
+            .strip_prefix("/actions/")
+            .and_then(|rest| rest.split_once("/in/"))
+            .filter(|(action_set, action)| !action_set.is_empty() && !action.is_empty());
+        match parsed {
+            Some((action_set, action)) => Some((action_set.to_owned(), action.to_owned())),
+            None => {
+                self.issues
+                    .push(format!("`{}` is not an input action path", output));
+                None
+            }
+        }
+    }
+
+    /// Maps a SteamVR source path and input onto the OpenXR binding path
+    fn binding(&self, source: &str, input: Option<&str>) -> (String, Option<ComponentType>) {
+        let (user_path, component) = match source.split_once("/input/") {
+            Some(split) => split,
+            None => return (source.to_owned(), None),
+        };
+        let component = match component {
+            "joystick" => "thumbstick",
+            "grip" => "squeeze",
+            "application_menu" => "menu",
+            component => component,
+        };
+        let suffix = match input {
+            Some("pull") | Some("value") => "/value",
+            Some("click") => "/click",
+            Some("touch") => "/touch",
+            Some("force") => "/force",
+            _ => "",
+        };
+        let binding = format!("{}/input/{}{}", user_path, component, suffix);
+        let ty = self
+            .interaction_profile
+            .component(&binding)
+            .map(|component| component.ty);
+        (binding, ty)
+    }
+
+    fn add_simple(&mut self, output: &str, binding: String) {
+        if let Some(action) = self.action(output) {
+            self.simple.entry(action).or_default().push(binding);
+        }
+    }
+
+    fn add_advanced(&mut self, output: &str, kind: BindingKind) {
+        if let Some((action_set, action)) = self.action(output) {
+            self.simple
+                .entry((action_set.clone(), action.clone()))
+                .or_default();
+            self.profile.advanced_bindings.push(AdvancedBinding {
+                interaction_profile: self.interaction_profile.path.to_owned(),
+                action_set,
+                action,
+                kind,
+            });
+        }
+    }
+
+    fn add_behavior(&mut self, output: &str, behavior: ButtonBehavior) {
+        let (action_set, action) = match self.action(output) {
+            Some(action) => action,
+            None => return,
+        };
+        if self.profile.behavior(&action_set, &action).is_some() {
+            self.issues.push(format!(
+                "`{}` uses several button behaviors, only the first is kept",
+                output
+            ));
+            return;
+        }
+        self.issues.push(format!(
+            "`{}` became a {:?} behavior, which applies to every binding of the action",
+            output, behavior
+        ));
+        self.profile.behaviors.push(ActionBehavior {
+            action_set,
+            action,
+            behavior,
+        });
+    }
+
+    fn unsupported(&mut self, source: &Source, what: &str) {
+        self.issues.push(format!(
+            "{} of `{}` in mode `{}` cannot be translated",
+            what, source.path, source.mode
+        ));
+    }
+
+    fn source(&mut self, source: &Source) {
+        match source.mode.as_str() {
+            "button" | "toggle_button" => {
+                for (input, output) in &source.inputs {
+                    let behavior = match (source.mode.as_str(), input.as_str()) {
+                        ("toggle_button", _) => Some(ButtonBehavior::Toggle),
+                        (_, "long") | (_, "held") => Some(ButtonBehavior::LongPress {
+                            min_duration_ms: DEFAULT_LONG_PRESS_MS,
+                        }),
+                        (_, "double") => Some(ButtonBehavior::DoubleTap {
+                            window_ms: DEFAULT_DOUBLE_TAP_MS,
+                        }),
+                        _ => None,
+                    };
+                    let input = match input.as_str() {
+                        "touch" => "touch",
+                        "click" | "long" | "held" | "double" => "click",
+                        _ => {
+                            self.unsupported(source, &format!("input `{}`", input));
+                            continue;
+                        }
+                    };
+                    if !self.button(source, input, &output.output) {
+                        continue;
+                    }
+                    if let Some(behavior) = behavior {
+                        self.add_behavior(&output.output, behavior);
+                    }
+                }
+            }
+            "trigger" => {
+                for (input, output) in &source.inputs {
+                    match input.as_str() {
+                        "pull" => self.component(source, Some("pull"), &output.output),
+                        "click" | "touch" => {
+                            self.button(source, input, &output.output);
+                        }
+                        _ => self.unsupported(source, &format!("input `{}`", input)),
+                    }
+                }
+            }
+            "joystick" | "trackpad" => {
+                for (input, output) in &source.inputs {
+                    match input.as_str() {
+                        "position" => self.component(source, None, &output.output),
+                        "click" | "touch" => {
+                            self.button(source, input, &output.output);
+                        }
+                        _ => self.unsupported(source, &format!("input `{}`", input)),
+                    }
+                }
+            }
+            "dpad" => self.dpad(source),
+            "force_sensor" => {
+                for (input, output) in &source.inputs {
+                    match input.as_str() {
+                        "force" => self.component(source, Some("force"), &output.output),
+                        _ => self.unsupported(source, &format!("input `{}`", input)),
+                    }
+                }
+            }
+            _ => self.unsupported(source, "the source"),
+        }
+    }
+
+    /// Binds a float or vector2f component, or a boolean one for click and touch inputs
+    fn component(&mut self, source: &Source, input: Option<&str>, output: &str) {
+        let (binding, ty) = self.binding(&source.path, input);
+        match ty {
+            Some(_) => self.add_simple(output, binding),
+            None => self.issues.push(format!(
+                "`{}` does not exist on `{}`",
+                binding, self.interaction_profile.path
+            )),
+        }
+    }
+
+    /// Binds a click or touch input, using a threshold on the value when the controller only has
+    /// an analog input. Returns whether anything was bound.
+    fn button(&mut self, source: &Source, input: &str, output: &str) -> bool {
+        let (binding, ty) = self.binding(&source.path, Some(input));
+        let threshold = |name: &str, default: f32| {
+            source
+                .parameters
+                .get(name)
+                .and_then(number)
+                .unwrap_or(default)
+        };
+
+        if ty.is_some() && !source.parameters.contains_key("click_activate_threshold") {
+            self.add_simple(output, binding);
+            return true;
+        }
+
+        let (value, value_ty) = self.binding(&source.path, Some("value"));
+        if input == "click" && value_ty == Some(ComponentType::Float) {
+            self.add_advanced(
+                output,
+                BindingKind::Threshold {
+                    binding: value,
+                    on_threshold: threshold("click_activate_threshold", DEFAULT_ACTIVATE_THRESHOLD),
+                    off_threshold: threshold(
+                        "click_deactivate_threshold",
+                        DEFAULT_DEACTIVATE_THRESHOLD,
+                    ),
+                },
+            );
+            return true;
+        }
+
+        if ty.is_some() {
+            self.issues.push(format!(
+                "the click thresholds of `{}` were ignored, it is a digital input",
+                source.path
+            ));
+            self.add_simple(output, binding);
+            return true;
+        }
+
+        self.issues.push(format!(
+            "`{}` does not exist on `{}`",
+            binding, self.interaction_profile.path
+        ));
+        false
+    }
+
+    fn dpad(&mut self, source: &Source) {
+        let (binding, ty) = self.binding(&source.path, None);
+        if ty != Some(ComponentType::Vector2f) {
+            self.issues.push(format!(
+                "dpad source `{}` is not a stick or trackpad of `{}`",
+                source.path, self.interaction_profile.path
+            ));
+            return;
+        }
+
+        let activator = match source.parameters.get("sub_mode").and_then(Value::as_str) {
+            Some(sub_mode @ ("click" | "touch")) => {
+                let (activator, ty) = self.binding(&source.path, Some(sub_mode));
+                //Sticks are not required to be pressed or touched to point into a direction
+                ty.map(|_| activator)
+            }
+            _ => None,
+        };
+        let center_region = source
+            .parameters
+            .get("deadzone_pct")
+            .and_then(number)
+            .map_or(0.5, |pct| pct / 100.);
+        let overlap = source
+            .parameters
+            .get("overlap_pct")
+            .and_then(number)
+            .map_or(0., |pct| pct / 100.);
+        let sticky = source
+            .parameters
+            .get("sticky")
+            .map_or(false, |sticky| match sticky {
+                Value::Bool(sticky) => *sticky,
+                Value::String(sticky) => sticky == "true",
+                _ => false,
+            });
+
+        for (input, output) in &source.inputs {
+            let direction = match input.as_str() {
+                "north" => DpadDirection::Up,
+                "south" => DpadDirection::Down,
+                "west" => DpadDirection::Left,
+                "east" => DpadDirection::Right,
+                "center" => DpadDirection::Center,
+                _ => {
+                    self.unsupported(source, &format!("input `{}`", input));
+                    continue;
+                }
+            };
+            self.add_advanced(
+                &output.output,
+                BindingKind::Dpad {
+                    binding: binding.clone(),
+                    direction,
+                    center_region,
+                    wedge_angle: (std::f32::consts::FRAC_PI_2 * (1. + overlap))
+                        .min(std::f32::consts::PI),
+                    sticky,
+                    activator: activator.clone(),
+                },
+            );
+        }
+    }
+
+    fn chord(&mut self, chord: &Chord) {
+        let bindings = chord
+            .inputs
+            .iter()
+            .map(|(path, input)| self.binding(path, Some(input)))
+            .collect::<Vec<_>>();
+
+        if let Some((binding, _)) = bindings.iter().find(|(_, ty)| {
+            !matches!(
+                ty,
+                Some(ComponentType::Boolean) | Some(ComponentType::Float)
+            )
+        }) {
+            self.issues.push(format!(
+                "chord for `{}` uses `{}` which is not a button of `{}`",
+                chord.output, binding, self.interaction_profile.path
+            ));
+            return;
+        }
+
+        match &bindings[..] {
+            [(modifier, _), (binding, _)] => self.add_advanced(
+                &chord.output,
+                BindingKind::Modifier {
+                    modifier: modifier.clone(),
+                    binding: binding.clone(),
+                },
+            ),
+            _ => self.issues.push(format!(
+                "chord for `{}` has {} inputs, only chords of two inputs are supported",
+                chord.output,
+                bindings.len()
+            )),
+        }
+    }
+
+    fn finish(mut self) -> SteamVrImport {
+        //The file describes every binding of the actions it mentions, so it replaces the
+        //application's suggestions even for actions only driven by advanced bindings
+        for ((action_set, action), bindings) in std::mem::take(&mut self.simple) {
+            self.profile.set_rebind(Rebind {
+                interaction_profile: self.interaction_profile.path.to_owned(),
+                action_set,
+                action,
+                bindings,
+            });
+        }
+
+        SteamVrImport {
+            profile: self.profile,
+            issues: self.issues,
+        }
+    }
+}
+
+/// SteamVR stores most parameters as strings
+fn number(value: &Value) -> Option<f32> {
+    match value {
+        Value::Number(number) => number.as_f64().map(|number| number as f32),
+        Value::String(string) => string.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::input::advanced_binding::{BindingKind, DpadDirection};
+
+    use super::*;
+
+    const INDEX_CONTROLLER: &str = "/interaction_profiles/valve/index_controller";
+
+    fn import_test_data(name: &str) -> SteamVrImport {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata")
+            .join(name);
+        import(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    fn rebind<'a>(import: &'a SteamVrImport, action: &str) -> Option<&'a [String]> {
+        import
+            .profile
+            .rebinds
+            .iter()
+            .find(|rebind| rebind.action_set == "main" && rebind.action == action)
+            .map(|rebind| {
+                assert_eq!(rebind.interaction_profile, INDEX_CONTROLLER);
+                &rebind.bindings[..]
+            })
+    }
+
+    fn advanced<'a>(import: &'a SteamVrImport, action: &str) -> Vec<&'a BindingKind> {
+        import
+            .profile
+            .advanced_bindings
+            .iter()
+            .filter(|binding| binding.action_set == "main" && binding.action == action)
+            .map(|binding| &binding.kind)
+            .collect()
+    }
+
+    #[test]
+    fn buttons() {
+        let import = import_test_data("steamvr_knuckles.json");
+        for (action, binding) in [
+            ("jump", "/user/hand/right/input/a/click"),
+            ("crouch", "/user/hand/right/input/a/click"),
+            ("sprint", "/user/hand/left/input/thumbstick/click"),
+        ] {
+            assert_eq!(rebind(&import, action), Some(&[binding.to_owned()][..]));
+        }
+        assert_eq!(
+            import.profile.behavior("main", "crouch"),
+            Some(&ButtonBehavior::LongPress {
+                min_duration_ms: DEFAULT_LONG_PRESS_MS
+            })
+        );
+        assert_eq!(import.profile.behavior("main", "jump"), None);
+    }
+
+    #[test]
+    fn triggers() {
+        let import = import_test_data("steamvr_knuckles.json");
+        assert_eq!(
+            rebind(&import, "shoot_value"),
+            Some(&["/user/hand/right/input/trigger/value".to_owned()][..])
+        );
+        assert_eq!(
+            rebind(&import, "shoot"),
+            Some(&["/user/hand/right/input/trigger/click".to_owned()][..])
+        );
+
+        //The squeeze has no click, so the click thresholds apply to its value
+        assert_eq!(rebind(&import, "grab"), Some(&[][..]));
+        assert_eq!(
+            advanced(&import, "grab"),
+            [&BindingKind::Threshold {
+                binding: "/user/hand/left/input/squeeze/value".to_owned(),
+                on_threshold: 0.5,
+                off_threshold: 0.4,
+            }]
+        );
+    }
+
+    #[test]
+    fn dpads() {
+        let import = import_test_data("steamvr_knuckles.json");
+        for (action, direction) in [
+            ("menu_up", DpadDirection::Up),
+            ("menu_down", DpadDirection::Down),
+        ] {
+            assert_eq!(rebind(&import, action), Some(&[][..]));
+            assert_eq!(
+                advanced(&import, action),
+                [&BindingKind::Dpad {
+                    binding: "/user/hand/left/input/trackpad".to_owned(),
+                    direction,
+                    center_region: 0.3,
+                    wedge_angle: std::f32::consts::FRAC_PI_2,
+                    sticky: true,
+                    activator: Some("/user/hand/left/input/trackpad/touch".to_owned()),
+                }]
+            );
+        }
+    }
+
+    #[test]
+    fn joysticks() {
+        let import = import_test_data("steamvr_knuckles.json");
+        assert_eq!(
+            rebind(&import, "move"),
+            Some(&["/user/hand/left/input/thumbstick".to_owned()][..])
+        );
+    }
+
+    #[test]
+    fn chords() {
+        let import = import_test_data("steamvr_knuckles.json");
+        assert_eq!(rebind(&import, "reload"), Some(&[][..]));
+        assert_eq!(
+            advanced(&import, "reload"),
+            [&BindingKind::Modifier {
+                modifier: "/user/hand/right/input/b/click".to_owned(),
+                binding: "/user/hand/right/input/trigger/click".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn translatable_file_only_reports_behaviors() {
+        let import = import_test_data("steamvr_knuckles.json");
+        assert_eq!(import.issues.len(), 1, "{:?}", import.issues);
+        assert!(import.issues[0].starts_with("`/actions/main/in/crouch` became a LongPress"));
+    }
+
+    #[test]
+    fn untranslatable_constructs_are_reported() {
+        let import = import_test_data("steamvr_untranslatable.json");
+        assert_eq!(
+            import.issues,
+            [
+                "the source of `/user/hand/left/input/x` in mode `scroll` cannot be translated",
+                "input `double` of `/user/hand/left/input/trigger` in mode `trigger` cannot be \
+                 translated",
+                "dpad source `/user/hand/left/input/trackpad` is not a stick or trackpad of \
+                 `/interaction_profiles/oculus/touch_controller`",
+                "`/actions/main/out/haptic` is not an input action path",
+                "chord for `/actions/main/in/reload` has 3 inputs, only chords of two inputs are \
+                 supported",
+                "chord for `/actions/main/in/aim` uses `/user/hand/right/input/thumbstick` which \
+                 is not a button of `/interaction_profiles/oculus/touch_controller`",
+                "1 pose binding(s) of `/actions/main` were skipped, these are bound by the runtime",
+            ]
+        );
+        assert!(import.profile.rebinds.is_empty());
+        assert!(import.profile.advanced_bindings.is_empty());
+    }
+
+    #[test]
+    fn unknown_controller_types_are_reported() {
+        let import = import(r#"{ "controller_type": "unknown_controller" }"#).unwrap();
+        assert_eq!(
+            import.issues,
+            ["controller type `unknown_controller` has no matching interaction profile"]
+        );
+        assert!(import.profile.rebinds.is_empty());
+    }
+}
@@ -1,4 +1,13 @@
-use std::{sync::{atomic::AtomicBool, Arc}, collections::HashMap};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    ffi::{CStr, CString},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicI64},
+        Arc,
+    },
+};
 
 use dashmap::DashMap;
 use log::{debug, info};
@@ -6,16 +15,42 @@ use once_cell::sync::OnceCell;
 use openxr::{sys as xr, Path};
 use openxr_driver::OpenXRDriver;
 use parking_lot::Mutex;
-use suinput::{instance::SuInstance, SuInputRuntime, SuPath, SuBindingLayout};
+use suinput::{instance::SuInstance, SuBindingLayout, SuInputRuntime, SuPath};
+use thunderdome::{Arena, Index};
 
-use crate::{str_from_bytes_until_nul, ToResult, input::suggested_bindings::SuggestedBindings};
+use crate::{
+    debug_utils::{self, DebugMessengers},
+    input::{
+        action_override::ActionOverrides,
+        capture::InputCapture,
+        hand_interaction,
+        input_sources::InputSources,
+        interaction_profiles,
+        layer_inputs::LayerInputs,
+        native_actions::NativeActions,
+        suggested_bindings::{SuggestedBinding, SuggestedBindings},
+        translation::{self, DroppedBinding},
+        user_paths,
+    },
+    ipc::{injection::InjectionRing, IpcServer},
+    profile::{self, Rebind, RemapProfile},
+    recording::{self, Record, Recorder},
+    str_from_bytes_until_nul, ToResult,
+};
 
 use super::{
+    layer_action::{self, LayerAction},
     layer_action_set::{self, LayerActionSet},
     session::SessionWrapper,
     XrHandle, XrWrapper,
 };
 
+const SUGGEST_FUNCTION_NAME: &str = "xrSuggestInteractionProfileBindings";
+
+/// Problems with suggested bindings, reported once the actions are unlocked since the
+/// application's debug messengers may call back into the layer
+type SuggestReports = Vec<(xr::DebugUtilsMessageSeverityFlagsEXT, String)>;
+
 pub struct InstanceWrapper {
     pub handle: xr::Instance,
     pub application_info: xr::ApplicationInfo,
@@ -27,6 +62,24 @@ pub struct InstanceWrapper {
     pub suinput_instance: SuInstance,
     pub suinput_driver: Mutex<OpenXRDriver>,
     pub suggested_bindings: Mutex<HashMap<SuPath, SuggestedBindings>>,
+    /// The bindings exactly as suggested by the application, keyed by interaction profile
+    pub application_bindings: Mutex<HashMap<String, Vec<(xr::Action, String)>>>,
+    /// Bindings pushed over IPC, applied at the next `xrSyncActions`
+    pub pending_bindings: Mutex<HashMap<SuPath, SuggestedBindings>>,
+    pub profile: RemapProfile,
+    pub native_actions: NativeActions,
+    pub input_sources: Arc<InputSources>,
+    pub layer_inputs: LayerInputs,
+    pub input_capture: InputCapture,
+    pub ipc_server: Mutex<Option<IpcServer>>,
+    /// Created on request of a process injecting input
+    pub injection_ring: OnceCell<InjectionRing>,
+    /// Action states forced over IPC
+    pub action_overrides: ActionOverrides,
+    pub debug_messengers: DebugMessengers,
+    pub recorder: Recorder,
+    /// Extensions enabled by the application which the layer implements in place of the runtime
+    pub emulated_extensions: Vec<&'static str>,
 }
 
 pub struct InnerInstance {
@@ -48,6 +101,18 @@ pub enum Runtime {
     Other(String),
 }
 
+impl Runtime {
+    pub fn name(&self) -> &str {
+        match self {
+            Runtime::SteamVR => "SteamVR/OpenXR",
+            Runtime::Oculus => "Oculus",
+            Runtime::WMR => "Windows Mixed Reality Runtime",
+            Runtime::Monado => "Monado(XRT) by Collabora et al",
+            Runtime::Other(name) => name,
+        }
+    }
+}
+
 impl XrWrapper for InstanceWrapper {
     fn inner_instance(&self) -> &Arc<InnerInstance> {
         &self.inner
@@ -71,9 +136,206 @@ impl InnerInstance {
             (self.core.path_to_string)(self.instance, path, input, output, buf)
         })
     }
+
+    pub fn string_to_path(&self, string: &str) -> Result<xr::Path, xr::Result> {
+        let string = CString::new(string).map_err(|_| xr::Result::ERROR_PATH_FORMAT_INVALID)?;
+        let mut path = xr::Path::NULL;
+        unsafe { (self.core.string_to_path)(self.instance, string.as_ptr(), &mut path) }
+            .result()?;
+        Ok(path)
+    }
 }
 
 impl InstanceWrapper {
+    pub fn translate_sub_action_path(
+        &self,
+        path: xr::Path,
+        action_type: xr::ActionType,
+    ) -> Result<xr::Path, xr::Result> {
+        if path == xr::Path::NULL {
+            return Ok(path);
+        }
+        let string = self.inner.path_to_string(path)?;
+        //The runtime rejects the top level path of an extension it does not have
+        if string == user_paths::EYES && self.emulates(interaction_profiles::EYE_GAZE_INTERACTION) {
+            return Ok(xr::Path::NULL);
+        }
+        match translation::translate_path(&self.profile, None, &string, action_type) {
+            Ok(Cow::Owned(translated)) => self.inner.string_to_path(&translated),
+            _ => Ok(path),
+        }
+    }
+
+    /// Whether an application subaction path has been folded onto the other hand in one handed mode
+    pub fn is_folded(&self, path: xr::Path) -> Result<bool, xr::Result> {
+        if path == xr::Path::NULL || self.profile.one_handed.is_none() {
+            return Ok(false);
+        }
+        Ok(translation::is_folded(
+            &self.profile,
+            &self.inner.path_to_string(path)?,
+        ))
+    }
+
+    /// Whether the layer evaluates an interaction profile itself instead of the runtime
+    pub fn emulates(&self, interaction_profile: &str) -> bool {
+        match interaction_profiles::extension(interaction_profile) {
+            Some(extension) => self.emulated_extensions.contains(&extension),
+            None => interaction_profiles::is_emulated(interaction_profile),
+        }
+    }
+
+    /// Whether palm poses are located by the layer from the grip pose
+    pub fn emulates_palm_pose(&self) -> bool {
+        self.emulated_extensions
+            .contains(&hand_interaction::PALM_POSE_EXTENSION)
+    }
+
+    /// Whether the application suggested bindings for an interaction profile emulated by the layer
+    pub fn has_emulated_bindings(&self) -> bool {
+        self.suggested_bindings
+            .lock()
+            .values()
+            .any(|bindings| self.emulates(&bindings.interaction_profile))
+    }
+
+    /// Suggests the bindings of the layer's own actions for the profiles of the runtime the
+    /// application did not suggest. Profiles of the hands are only suggested when the application
+    /// suggested none of the runtime's, e.g. only emulated profiles which then read the
+    /// controllers through them, as suggesting more profiles could change which one the runtime
+    /// picks for the hands.
+    pub fn suggest_layer_bindings(&self) -> Result<(), xr::Result> {
+        let application_bindings = self.application_bindings.lock();
+        let suggests_hands = application_bindings.keys().any(|interaction_profile| {
+            !self.emulates(interaction_profile)
+                && interaction_profiles::get(interaction_profile)
+                    .map_or(true, |profile| !interaction_profiles::is_handless(profile))
+        });
+
+        for profile in interaction_profiles::all() {
+            if self.emulates(profile.path)
+                || application_bindings.contains_key(profile.path)
+                || (suggests_hands && !interaction_profiles::is_handless(profile))
+            {
+                continue;
+            }
+            let bindings = self
+                .native_actions
+                .layer_bindings(&self.inner, profile.path)?;
+            if bindings.is_empty() {
+                continue;
+            }
+            let suggested_bindings = xr::InteractionProfileSuggestedBinding {
+                ty: xr::InteractionProfileSuggestedBinding::TYPE,
+                next: std::ptr::null(),
+                interaction_profile: self.inner.string_to_path(profile.path)?,
+                count_suggested_bindings: bindings.len() as u32,
+                suggested_bindings: bindings.as_ptr(),
+            };
+            //Profiles of extensions the application did not enable are rejected
+            if let Err(err) = unsafe {
+                (self.inner.core.suggest_interaction_profile_bindings)(
+                    self.handle,
+                    &suggested_bindings,
+                )
+            }
+            .result()
+            {
+                debug!(
+                    "Runtime rejected layer bindings for `{}`: {}",
+                    profile.path, err
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts recording the input of this instance, to `path` or the default location
+    pub fn start_recording(&self, path: Option<PathBuf>) -> std::io::Result<PathBuf> {
+        let application_name =
+            str_from_bytes_until_nul(&self.application_info.application_name[..])
+                .unwrap_or_default();
+        let path = path.unwrap_or_else(|| recording::default_path(application_name));
+        self.recorder.start(
+            &path,
+            Record::Header {
+                format_version: recording::FORMAT_VERSION,
+                application_name: application_name.to_owned(),
+                application_version: self.application_info.application_version,
+                runtime: self.runtime.name().to_owned(),
+                profile: profile::active_profile_name(application_name),
+            },
+        )?;
+        info!("Recording input to `{}`", path.display());
+        Ok(path)
+    }
+
+    /// The action sets created by this instance with their handles
+    pub fn action_sets(self: &Arc<Self>) -> Vec<(xr::ActionSet, Arc<LayerActionSet>)> {
+        let instance = Arc::downgrade(self);
+        layer_action_set::all()
+            .iter()
+            .filter(|(_, action_set)| action_set.instance.ptr_eq(&instance))
+            .map(|(index, action_set)| {
+                (xr::ActionSet::from_raw(index.to_bits()), action_set.clone())
+            })
+            .collect()
+    }
+
+    /// Looks up an action of this instance by the names of its action set and itself
+    fn find_action(
+        self: &Arc<Self>,
+        layer_actions: &Arena<Arc<LayerAction>>,
+        action_set: &str,
+        action: &str,
+    ) -> Option<xr::Action> {
+        self.action_sets()
+            .into_iter()
+            .filter(|(_, set)| set.name == action_set)
+            .find_map(|(_, set)| {
+                let actions = set.actions.lock();
+                actions.iter().copied().find(|handle| {
+                    layer_action::get(layer_actions, *handle)
+                        .map_or(false, |layer_action| layer_action.name == action)
+                })
+            })
+    }
+
+    /// The actions whose bindings for an interaction profile have been replaced by the profile
+    fn rebinds(
+        self: &Arc<Self>,
+        interaction_profile: &str,
+        layer_actions: &Arena<Arc<LayerAction>>,
+        reports: &mut SuggestReports,
+    ) -> Vec<(xr::Action, &Rebind)> {
+        let mut rebinds = Vec::new();
+        for (_, action_set) in self.action_sets() {
+            for handle in action_set.actions.lock().iter() {
+                let action = match layer_action::get(layer_actions, *handle) {
+                    Ok(action) => action,
+                    Err(_) => continue,
+                };
+                if let Some(rebind) =
+                    self.profile
+                        .rebind(interaction_profile, &action_set.name, &action.name)
+                {
+                    if self.native_actions.get(*handle).is_some() {
+                        reports.push((
+                            xr::DebugUtilsMessageSeverityFlagsEXT::WARNING,
+                            format!(
+                                "Pose and haptic actions cannot be rebound: {}",
+                                action.log_name()
+                            ),
+                        ));
+                        continue;
+                    }
+                    rebinds.push((*handle, rebind));
+                }
+            }
+        }
+        rebinds
+    }
+
     pub fn xr_get_system(
         self: &Arc<Self>,
         get_info: &xr::SystemGetInfo,
@@ -110,6 +372,14 @@ impl InstanceWrapper {
             instance: Arc::downgrade(self),
             inner_instance: self.inner.clone(),
             inner: OnceCell::new(),
+            predicted_display_time: AtomicI64::new(0),
+            view_space: OnceCell::new(),
+            local_space: OnceCell::new(),
+            stage_space: OnceCell::new(),
+            grip_space: OnceCell::new(),
+            hand_trackers: OnceCell::new(),
+            layer_action_spaces: Default::default(),
+            playspace: Default::default(),
         });
 
         *session = session_wrapper.handle;
@@ -133,7 +403,13 @@ impl InstanceWrapper {
         let handle = layer_action_set::all_mut().insert(Arc::new(LayerActionSet {
             instance: Arc::downgrade(self),
             inner: self.inner.clone(),
+            name: name.to_owned(),
+            localized_name: str_from_bytes_until_nul(&create_info.localized_action_set_name[..])?
+                .to_owned(),
+            priority: create_info.priority,
             su_action_set,
+            actions: Default::default(),
+            object_name: Default::default(),
         }));
 
         *handle_out = xr::ActionSet::from_raw(handle.to_bits());
@@ -141,20 +417,354 @@ impl InstanceWrapper {
         Ok(xr::Result::SUCCESS)
     }
 
+    /// Logs a problem and reports it to the application's debug messengers
+    pub fn report(
+        &self,
+        severity: xr::DebugUtilsMessageSeverityFlagsEXT,
+        function_name: &str,
+        message: &str,
+    ) {
+        debug_utils::log(severity, message);
+        self.debug_messengers
+            .submit(severity, function_name, message);
+    }
+
+    /// Reports a rejected suggestion and returns the error for the application
+    fn validation_error(reports: &mut SuggestReports, message: String) -> xr::Result {
+        reports.push((xr::DebugUtilsMessageSeverityFlagsEXT::ERROR, message));
+        xr::Result::ERROR_PATH_UNSUPPORTED
+    }
+
+    pub fn xr_create_debug_utils_messenger(
+        &self,
+        create_info: &xr::DebugUtilsMessengerCreateInfoEXT,
+        messenger: &mut xr::DebugUtilsMessengerEXT,
+    ) -> Result<xr::Result, xr::Result> {
+        let debug_utils = self
+            .inner
+            .exts
+            .ext_debug_utils
+            .as_ref()
+            .ok_or(xr::Result::ERROR_FUNCTION_UNSUPPORTED)?;
+        let result = unsafe {
+            (debug_utils.create_debug_utils_messenger)(self.handle, create_info, messenger)
+        }
+        .result()?;
+        self.debug_messengers.insert(*messenger, create_info);
+        Ok(result)
+    }
+
+    pub fn xr_destroy_debug_utils_messenger(
+        &self,
+        messenger: xr::DebugUtilsMessengerEXT,
+    ) -> Result<xr::Result, xr::Result> {
+        let debug_utils = self
+            .inner
+            .exts
+            .ext_debug_utils
+            .as_ref()
+            .ok_or(xr::Result::ERROR_FUNCTION_UNSUPPORTED)?;
+        self.debug_messengers.remove(messenger);
+        unsafe { (debug_utils.destroy_debug_utils_messenger)(messenger) }.result()
+    }
+
+    /// Names of actions and action sets are kept by the layer, as the runtime never sees their
+    /// handles
+    pub fn xr_set_debug_utils_object_name(
+        &self,
+        name_info: &xr::DebugUtilsObjectNameInfoEXT,
+    ) -> Result<xr::Result, xr::Result> {
+        let debug_utils = self
+            .inner
+            .exts
+            .ext_debug_utils
+            .as_ref()
+            .ok_or(xr::Result::ERROR_FUNCTION_UNSUPPORTED)?;
+
+        let object_name = if name_info.object_name.is_null() {
+            None
+        } else {
+            Some(
+                unsafe { CStr::from_ptr(name_info.object_name) }
+                    .to_string_lossy()
+                    .into_owned(),
+            )
+        };
+        let index = Index::from_bits(name_info.object_handle);
+
+        match name_info.object_type {
+            xr::ObjectType::ACTION => {
+                let actions = layer_action::all();
+                let action = index
+                    .and_then(|index| actions.get(index))
+                    .ok_or(xr::Result::ERROR_HANDLE_INVALID)?;
+                *action.object_name.lock() = object_name;
+                Ok(xr::Result::SUCCESS)
+            }
+            xr::ObjectType::ACTION_SET => {
+                let action_sets = layer_action_set::all();
+                let action_set = index
+                    .and_then(|index| action_sets.get(index))
+                    .ok_or(xr::Result::ERROR_HANDLE_INVALID)?;
+                *action_set.object_name.lock() = object_name;
+                Ok(xr::Result::SUCCESS)
+            }
+            _ => unsafe { (debug_utils.set_debug_utils_object_name)(self.handle, name_info) }
+                .result(),
+        }
+    }
+
     pub fn xr_suggest_interaction_profile_bindings(
         self: &Arc<Self>,
         interaction_profile: Path,
         suggested_bindings: &[xr::ActionSuggestedBinding],
+    ) -> Result<xr::Result, xr::Result> {
+        let mut reports = SuggestReports::new();
+        let result = self.suggest_interaction_profile_bindings(
+            interaction_profile,
+            suggested_bindings,
+            &mut reports,
+        );
+        for (severity, message) in reports {
+            self.report(severity, SUGGEST_FUNCTION_NAME, &message);
+        }
+        result
+    }
+
+    fn suggest_interaction_profile_bindings(
+        self: &Arc<Self>,
+        interaction_profile: Path,
+        suggested_bindings: &[xr::ActionSuggestedBinding],
+        reports: &mut SuggestReports,
     ) -> Result<xr::Result, xr::Result> {
         let interaction_profile_string = self.inner.path_to_string(interaction_profile)?;
         let su_interaction_profile_path = self
             .suinput_instance
             .get_path(&interaction_profile_string)
-            .map_err(|_| xr::Result::ERROR_PATH_UNSUPPORTED)?;
+            .map_err(|_| {
+                Self::validation_error(
+                    reports,
+                    format!(
+                        "Unsupported interaction profile `{}`",
+                        interaction_profile_string
+                    ),
+                )
+            })?;
+
+        let layer_actions = layer_action::all();
+        let mut bindings = Vec::with_capacity(suggested_bindings.len());
+        let mut native_bindings = Vec::new();
+        let mut native_binding_paths = Vec::new();
+
+        let rebinds = self.rebinds(&interaction_profile_string, &layer_actions, reports);
+        let mut application_bindings = Vec::with_capacity(suggested_bindings.len());
+
+        for suggested_binding in suggested_bindings {
+            let action = layer_action::get(&layer_actions, suggested_binding.action)?;
+            let binding_string = self.inner.path_to_string(suggested_binding.binding)?;
+            application_bindings.push((suggested_binding.action, binding_string.clone()));
+            if rebinds
+                .iter()
+                .any(|(handle, _)| *handle == suggested_binding.action)
+            {
+                continue;
+            }
+
+            let sub_action_path = user_paths::top_level_path(&binding_string).ok_or_else(|| {
+                Self::validation_error(
+                    reports,
+                    format!(
+                        "`{}` suggested for {} is not a binding path",
+                        binding_string,
+                        action.log_name()
+                    ),
+                )
+            })?;
+            let sub_action_path = self.inner.string_to_path(sub_action_path)?;
+
+            if !action.sub_action_paths.is_empty()
+                && !action.sub_action_paths.contains(&sub_action_path)
+            {
+                return Err(Self::validation_error(
+                    reports,
+                    format!(
+                        "`{}` suggested for {} is outside of the action's subaction paths",
+                        binding_string,
+                        action.log_name()
+                    ),
+                ));
+            }
+
+            let folded = translation::is_folded(&self.profile, &binding_string);
+            let (binding, physical_binding) = match translation::translate_path(
+                &self.profile,
+                Some(&interaction_profile_string[..]),
+                &binding_string,
+                action.action_type,
+            ) {
+                Ok(Cow::Borrowed(_)) => (suggested_binding.binding, binding_string),
+                Ok(Cow::Owned(translated)) => (self.inner.string_to_path(&translated)?, translated),
+                Err(DroppedBinding::Reserved) => {
+                    reports.push((
+                        xr::DebugUtilsMessageSeverityFlagsEXT::INFO,
+                        format!(
+                            "Dropped the suggested binding of {} to `{}`, the remap profile \
+                             reserves it",
+                            action.log_name(),
+                            binding_string
+                        ),
+                    ));
+                    continue;
+                }
+                Err(DroppedBinding::Missing(moved)) => {
+                    reports.push((
+                        xr::DebugUtilsMessageSeverityFlagsEXT::WARNING,
+                        format!(
+                            "Dropped the suggested binding of {} to `{}`, it was moved to `{}` \
+                             which `{}` does not have",
+                            action.log_name(),
+                            binding_string,
+                            moved,
+                            interaction_profile_string
+                        ),
+                    ));
+                    continue;
+                }
+            };
+
+            //Runtimes without XR_EXT_palm_pose locate the grip in place of the palm
+            let binding = match hand_interaction::palm_to_grip(&physical_binding) {
+                Some(grip) if self.emulates_palm_pose() => self.inner.string_to_path(&grip)?,
+                _ => binding,
+            };
 
-        
+            match self.native_actions.get(suggested_binding.action) {
+                Some(native_action) => {
+                    native_bindings.push(xr::ActionSuggestedBinding {
+                        action: native_action.handle,
+                        binding,
+                    });
+                    native_binding_paths.push((suggested_binding.action, physical_binding));
+                }
+                None => bindings.push(SuggestedBinding::SimpleBinding {
+                    action: Index::from_bits(suggested_binding.action.into_raw())
+                        .ok_or(xr::Result::ERROR_HANDLE_INVALID)?,
+                    sub_action_path,
+                    binding: physical_binding,
+                    folded,
+                }),
+            }
+        }
 
-            
+        let reserved = self.profile.reserved_bindings();
+        for (handle, rebind) in rebinds {
+            let action = layer_action::get(&layer_actions, handle)?;
+            for binding in &rebind.bindings {
+                let sub_action_path = match user_paths::top_level_path(binding) {
+                    Some(path) => self.inner.string_to_path(path)?,
+                    None => {
+                        reports.push((
+                            xr::DebugUtilsMessageSeverityFlagsEXT::WARNING,
+                            format!("Ignored rebind to invalid path `{}`", binding),
+                        ));
+                        continue;
+                    }
+                };
+                if reserved.contains(&binding.as_str())
+                    || (!action.sub_action_paths.is_empty()
+                        && !action.sub_action_paths.contains(&sub_action_path))
+                {
+                    reports.push((
+                        xr::DebugUtilsMessageSeverityFlagsEXT::WARNING,
+                        format!("Ignored rebind of {} to `{}`", action.log_name(), binding),
+                    ));
+                    continue;
+                }
+                bindings.push(SuggestedBinding::SimpleBinding {
+                    action: Index::from_bits(handle.into_raw())
+                        .ok_or(xr::Result::ERROR_HANDLE_INVALID)?,
+                    sub_action_path,
+                    binding: binding.clone(),
+                    folded: false,
+                });
+            }
+        }
+
+        for advanced in self
+            .profile
+            .advanced_bindings
+            .iter()
+            .filter(|advanced| advanced.interaction_profile == interaction_profile_string)
+        {
+            let handle =
+                match self.find_action(&layer_actions, &advanced.action_set, &advanced.action) {
+                    Some(handle) => handle,
+                    None => continue,
+                };
+            let sub_action_path = match user_paths::top_level_path(advanced.kind.binding()) {
+                Some(path) => self.inner.string_to_path(path)?,
+                None => continue,
+            };
+            bindings.push(SuggestedBinding::Advanced {
+                action: Index::from_bits(handle.into_raw())
+                    .ok_or(xr::Result::ERROR_HANDLE_INVALID)?,
+                sub_action_path,
+                kind: advanced.kind.clone(),
+            });
+        }
+
+        self.native_actions
+            .set_bindings(&interaction_profile_string, native_binding_paths);
+
+        native_bindings.extend(
+            self.native_actions
+                .layer_bindings(&self.inner, &interaction_profile_string)?,
+        );
+
+        if self.emulates(&interaction_profile_string) {
+            //The runtime may not support the profile at all, its inputs are evaluated by the layer
+            info!(
+                "Accepted bindings for emulated interaction profile `{}`",
+                interaction_profile_string
+            );
+        } else if !native_bindings.is_empty() {
+            let native_suggested_bindings = xr::InteractionProfileSuggestedBinding {
+                ty: xr::InteractionProfileSuggestedBinding::TYPE,
+                next: std::ptr::null(),
+                interaction_profile,
+                count_suggested_bindings: native_bindings.len() as u32,
+                suggested_bindings: native_bindings.as_ptr(),
+            };
+            unsafe {
+                (self.inner.core.suggest_interaction_profile_bindings)(
+                    self.handle,
+                    &native_suggested_bindings,
+                )
+            }
+            .result()
+            .map_err(|err| {
+                reports.push((
+                    xr::DebugUtilsMessageSeverityFlagsEXT::ERROR,
+                    format!(
+                        "The runtime rejected the bindings for `{}`: {}",
+                        interaction_profile_string, err
+                    ),
+                ));
+                err
+            })?;
+        }
+
+        self.application_bindings
+            .lock()
+            .insert(interaction_profile_string.clone(), application_bindings);
+
+        self.suggested_bindings.lock().insert(
+            su_interaction_profile_path,
+            SuggestedBindings {
+                interaction_profile: interaction_profile_string,
+                bindings,
+            },
+        );
 
         Ok(xr::Result::SUCCESS)
     }
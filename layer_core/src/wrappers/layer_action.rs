@@ -1,19 +1,29 @@
 use std::sync::{Arc, Weak};
 
 use openxr::sys as xr;
-use parking_lot::{RwLockReadGuard, RwLockWriteGuard};
+use parking_lot::{Mutex, RwLockReadGuard, RwLockWriteGuard};
 use suinput::{
     action_type::{Axis1d, Axis2d},
     SuAction, SuActionSet,
 };
 use thunderdome::{Arena, Index};
 
-use super::instance::{InnerInstance, InstanceWrapper};
+use super::{
+    instance::{InnerInstance, InstanceWrapper},
+    layer_action_set,
+};
 
 pub struct LayerAction {
     pub instance: Weak<InstanceWrapper>,
     pub inner: Arc<InnerInstance>,
+    pub name: String,
+    pub localized_name: String,
+    pub action_set: xr::ActionSet,
+    pub action_type: xr::ActionType,
+    pub sub_action_paths: Vec<xr::Path>,
     pub sub_actions: SubActions,
+    /// Set by the application with `xrSetDebugUtilsObjectNameEXT`
+    pub object_name: Mutex<Option<String>>,
 }
 
 pub fn all<'a>() -> RwLockReadGuard<'a, Arena<Arc<LayerAction>>> {
@@ -24,7 +34,10 @@ pub fn all_mut<'a>() -> RwLockWriteGuard<'a, Arena<Arc<LayerAction>>> {
     unsafe { super::ACTIONS.get().unwrap().write() }
 }
 
-pub fn get(arena: &Arena<Arc<LayerAction>>, handle: xr::Action) -> openxr::Result<&Arc<LayerAction>> {
+pub fn get(
+    arena: &Arena<Arc<LayerAction>>,
+    handle: xr::Action,
+) -> openxr::Result<&Arc<LayerAction>> {
     let index = match Index::from_bits(handle.into_raw()) {
         Some(index) => index,
         None => return Err(xr::Result::ERROR_HANDLE_INVALID),
@@ -32,6 +45,30 @@ pub fn get(arena: &Arena<Arc<LayerAction>>, handle: xr::Action) -> openxr::Resul
     arena.get(index).ok_or(xr::Result::ERROR_HANDLE_INVALID)
 }
 
+impl LayerAction {
+    /// Names the action in log messages as `<action set>/<action>`, followed by the names the
+    /// application gave both with `xrSetDebugUtilsObjectNameEXT`
+    pub fn log_name(&self) -> String {
+        let action_sets = layer_action_set::all();
+        let action_set =
+            Index::from_bits(self.action_set.into_raw()).and_then(|index| action_sets.get(index));
+        let action_set_name = action_set.map_or("", |action_set| action_set.name.as_str());
+
+        let action_set_object_name =
+            action_set.and_then(|action_set| action_set.object_name.lock().clone());
+        match (action_set_object_name, self.object_name.lock().clone()) {
+            (None, None) => format!("`{}/{}`", action_set_name, self.name),
+            (action_set_object_name, object_name) => format!(
+                "`{}/{}` ({}/{})",
+                action_set_name,
+                self.name,
+                action_set_object_name.as_deref().unwrap_or(action_set_name),
+                object_name.as_deref().unwrap_or(&self.name)
+            ),
+        }
+    }
+}
+
 pub enum SubActions {
     None(SingletonAction),
     Some(ManySubActions),
@@ -85,6 +122,98 @@ impl SubActions {
             _ => todo!("TODO handle unknown action type"),
         })
     }
+
+    pub fn get_boolean(&self, sub_action_path: xr::Path) -> openxr::Result<&SuAction<bool>> {
+        match self {
+            SubActions::None(action) => {
+                if sub_action_path != xr::Path::NULL {
+                    return Err(xr::Result::ERROR_PATH_INVALID);
+                }
+                match action {
+                    SingletonAction::Boolean(action) => Ok(action),
+                    _ => Err(xr::Result::ERROR_ACTION_TYPE_MISMATCH),
+                }
+            }
+            SubActions::Some(ManySubActions::Boolean(actions)) => {
+                find_sub_action(actions, sub_action_path).ok_or(xr::Result::ERROR_PATH_INVALID)
+            }
+            SubActions::Some(_) => Err(xr::Result::ERROR_ACTION_TYPE_MISMATCH),
+        }
+    }
+
+    pub fn get_float(&self, sub_action_path: xr::Path) -> openxr::Result<&SuAction<Axis1d>> {
+        match self {
+            SubActions::None(action) => {
+                if sub_action_path != xr::Path::NULL {
+                    return Err(xr::Result::ERROR_PATH_INVALID);
+                }
+                match action {
+                    SingletonAction::Float(action) => Ok(action),
+                    _ => Err(xr::Result::ERROR_ACTION_TYPE_MISMATCH),
+                }
+            }
+            SubActions::Some(ManySubActions::Float(actions)) => {
+                find_sub_action(actions, sub_action_path).ok_or(xr::Result::ERROR_PATH_INVALID)
+            }
+            SubActions::Some(_) => Err(xr::Result::ERROR_ACTION_TYPE_MISMATCH),
+        }
+    }
+
+    pub fn get_vector2f(&self, sub_action_path: xr::Path) -> openxr::Result<&SuAction<Axis2d>> {
+        match self {
+            SubActions::None(action) => {
+                if sub_action_path != xr::Path::NULL {
+                    return Err(xr::Result::ERROR_PATH_INVALID);
+                }
+                match action {
+                    SingletonAction::Vector2f(action) => Ok(action),
+                    _ => Err(xr::Result::ERROR_ACTION_TYPE_MISMATCH),
+                }
+            }
+            SubActions::Some(ManySubActions::Vector2f(actions)) => {
+                find_sub_action(actions, sub_action_path).ok_or(xr::Result::ERROR_PATH_INVALID)
+            }
+            SubActions::Some(_) => Err(xr::Result::ERROR_ACTION_TYPE_MISMATCH),
+        }
+    }
+
+    /// The subaction paths which can be passed to `get_boolean`
+    pub fn boolean_sub_action_paths(&self) -> Vec<xr::Path> {
+        match self {
+            SubActions::None(SingletonAction::Boolean(_)) => vec![xr::Path::NULL],
+            SubActions::Some(ManySubActions::Boolean(actions)) => {
+                actions.iter().map(|(path, _)| *path).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// The handle of the SuInput action which receives the bindings for `sub_action_path`
+    pub fn su_action_handle(&self, sub_action_path: xr::Path) -> Option<u64> {
+        match self {
+            SubActions::None(SingletonAction::Boolean(action)) => Some(action.handle()),
+            SubActions::None(SingletonAction::Float(action)) => Some(action.handle()),
+            SubActions::None(SingletonAction::Vector2f(action)) => Some(action.handle()),
+            SubActions::None(_) => None,
+            SubActions::Some(ManySubActions::Boolean(actions)) => {
+                find_sub_action(actions, sub_action_path).map(|action| action.handle())
+            }
+            SubActions::Some(ManySubActions::Float(actions)) => {
+                find_sub_action(actions, sub_action_path).map(|action| action.handle())
+            }
+            SubActions::Some(ManySubActions::Vector2f(actions)) => {
+                find_sub_action(actions, sub_action_path).map(|action| action.handle())
+            }
+            SubActions::Some(_) => None,
+        }
+    }
+}
+
+fn find_sub_action<T>(actions: &[(xr::Path, T)], sub_action_path: xr::Path) -> Option<&T> {
+    actions
+        .iter()
+        .find(|(path, _)| *path == sub_action_path)
+        .map(|(_, action)| action)
 }
 
 pub enum SingletonAction {
@@ -1,7 +1,7 @@
 use std::sync::{Arc, Weak};
 
 use openxr::sys as xr;
-use parking_lot::{RwLockReadGuard, RwLockWriteGuard};
+use parking_lot::{Mutex, RwLockReadGuard, RwLockWriteGuard};
 use suinput::SuActionSet;
 use thunderdome::Arena;
 
@@ -18,7 +18,13 @@ use super::{
 pub struct LayerActionSet {
     pub instance: Weak<InstanceWrapper>,
     pub inner: Arc<InnerInstance>,
+    pub name: String,
+    pub localized_name: String,
+    pub priority: u32,
     pub su_action_set: SuActionSet,
+    pub actions: Mutex<Vec<xr::Action>>,
+    /// Set by the application with `xrSetDebugUtilsObjectNameEXT`
+    pub object_name: Mutex<Option<String>>,
 }
 
 pub fn all<'a>() -> RwLockReadGuard<'a, Arena<Arc<LayerActionSet>>> {
@@ -32,18 +38,71 @@ pub fn all_mut<'a>() -> RwLockWriteGuard<'a, Arena<Arc<LayerActionSet>>> {
 impl LayerActionSet {
     pub fn xr_create_action(
         self: &Arc<Self>,
+        handle: xr::ActionSet,
         create_info: &xr::ActionCreateInfo,
         handle_out: &mut xr::Action,
     ) -> Result<xr::Result, xr::Result> {
         let name = str_from_bytes_until_nul(&create_info.action_name[..])?;
+        let instance = self
+            .instance
+            .upgrade()
+            .ok_or(xr::Result::ERROR_HANDLE_INVALID)?;
+
+        let sub_action_paths = if create_info.count_subaction_paths == 0 {
+            Vec::new()
+        } else {
+            unsafe {
+                std::slice::from_raw_parts(
+                    create_info.subaction_paths,
+                    create_info.count_subaction_paths as usize,
+                )
+            }
+            .to_vec()
+        };
 
         let index = layer_action::all_mut().insert(Arc::new(LayerAction {
             instance: self.instance.clone(),
             inner: self.inner.clone(),
+            name: name.to_owned(),
+            localized_name: str_from_bytes_until_nul(&create_info.localized_action_name[..])?
+                .to_owned(),
+            action_set: handle,
+            action_type: create_info.action_type,
+            sub_action_paths: sub_action_paths.clone(),
             sub_actions: SubActions::new(&self.su_action_set, create_info, name),
+            object_name: Default::default(),
         }));
 
         *handle_out = xr::Action::from_raw(index.to_bits());
+
+        if create_info.action_type == xr::ActionType::POSE_INPUT
+            || create_info.action_type == xr::ActionType::VIBRATION_OUTPUT
+        {
+            let result = sub_action_paths
+                .into_iter()
+                .map(|path| {
+                    Ok((
+                        path,
+                        instance.translate_sub_action_path(path, create_info.action_type)?,
+                    ))
+                })
+                .collect::<Result<Vec<_>, xr::Result>>()
+                .and_then(|sub_action_paths| {
+                    instance.native_actions.create_action(
+                        &self.inner,
+                        *handle_out,
+                        create_info.action_type,
+                        sub_action_paths,
+                    )
+                });
+            //The application never gets the handle of an action it failed to create
+            if let Err(err) = result {
+                layer_action::all_mut().remove(index);
+                return Err(err);
+            }
+        }
+
+        self.actions.lock().push(*handle_out);
         Ok(xr::Result::SUCCESS)
     }
 }
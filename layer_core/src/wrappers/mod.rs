@@ -2,6 +2,7 @@ pub mod instance;
 pub mod layer_action;
 pub mod layer_action_set;
 pub mod session;
+pub mod space;
 
 use std::{
     hash::Hash,
@@ -21,6 +22,7 @@ static mut INSTANCE_WRAPPERS: OnceCell<DashMap<xr::Instance, Arc<instance::Insta
     OnceCell::new();
 static mut SESSION_WRAPPERS: OnceCell<DashMap<xr::Session, Arc<session::SessionWrapper>>> =
     OnceCell::new();
+static mut SPACE_WRAPPERS: OnceCell<DashMap<xr::Space, Arc<space::SpaceWrapper>>> = OnceCell::new();
 static mut ACTION_SETS: OnceCell<RwLock<Arena<Arc<LayerActionSet>>>> = OnceCell::new();
 static mut ACTIONS: OnceCell<RwLock<Arena<Arc<LayerAction>>>> = OnceCell::new();
 
@@ -28,7 +30,9 @@ pub(crate) fn initialize() {
     unsafe {
         INSTANCE_WRAPPERS.get_or_init(DashMap::new);
         SESSION_WRAPPERS.get_or_init(DashMap::new);
+        SPACE_WRAPPERS.get_or_init(DashMap::new);
         ACTION_SETS.get_or_init(|| RwLock::new(Arena::new()));
+        ACTIONS.get_or_init(|| RwLock::new(Arena::new()));
     }
 }
 
@@ -1,29 +1,77 @@
 use std::{
-    collections::HashMap,
-    sync::{Arc, Weak},
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc, Weak,
+    },
 };
 
 use dashmap::DashMap;
+use log::{debug, info, warn};
 use once_cell::sync::OnceCell;
 use openxr::sys as xr;
+use parking_lot::Mutex;
 use suinput::{
     instance::{ApplicationInfo, ApplicationInstanceCreateInfo},
-    SuSession,
+    SuApplicationInstance, SuSession,
+};
+use thunderdome::{Arena, Index};
+
+use crate::{
+    input::{
+        self,
+        action_override::OverriddenState,
+        advanced_binding::{BindingInput, BindingKindState},
+        behavior::BehaviorState,
+        binding_analysis, driver_hooks,
+        eye_gaze::{self, GazeFallback},
+        hand_interaction,
+        hand_tracking::{self, HandJoints, HandPose},
+        interaction_profiles,
+        layer_inputs::PlayspaceMoverInputs,
+        one_handed::OneHandedState,
+        playspace::PlayspaceMover,
+        pose_filter::PoseFilterConfig,
+        reported_state::{self, ReportedState, ReportedStates},
+        suggested_bindings::SuggestedBinding,
+        user_paths::{self, Hand},
+        virtual_device::{self, VirtualSource},
+    },
+    ipc::{self, protocol::OverrideState},
+    manifest::ActionManifest,
+    math,
+    profile::RemapProfile,
+    recording::{InputValue, RecordedState},
+    ToResult,
 };
-use thunderdome::Index;
 
 use super::{
     instance::{InnerInstance, InstanceWrapper},
-    layer_action::{self, ManySubActions, SingletonAction},
+    layer_action::{self, LayerAction},
     layer_action_set::{self, LayerActionSet},
+    space::{self, SpaceKind, SpaceWrapper},
     XrHandle, XrWrapper,
 };
 
+const ATTACH_FUNCTION_NAME: &str = "xrAttachSessionActionSets";
+
 pub struct SessionWrapper {
     pub handle: xr::Session,
     pub instance: Weak<InstanceWrapper>,
     pub inner_instance: Arc<InnerInstance>,
     pub inner: OnceCell<InnerSession>,
+    /// The predicted display time returned by the most recent call to `xrWaitFrame`
+    pub predicted_display_time: AtomicI64,
+    pub view_space: OnceCell<xr::Space>,
+    pub local_space: OnceCell<xr::Space>,
+    pub stage_space: OnceCell<xr::Space>,
+    /// Locates the hand of the playspace mover
+    pub grip_space: OnceCell<xr::Space>,
+    /// Trackers of both hands, empty when hands cannot be tracked
+    pub hand_trackers: OnceCell<Vec<(Hand, xr::HandTrackerEXT)>>,
+    /// Spaces of the layer's own pose actions
+    pub layer_action_spaces: Mutex<HashMap<xr::Action, xr::Space>>,
+    pub playspace: Mutex<PlayspaceMover>,
 }
 
 impl XrWrapper for SessionWrapper {
@@ -44,8 +92,39 @@ impl XrHandle for xr::Session {
 }
 
 pub struct InnerSession {
+    application_instance: SuApplicationInstance,
     su_session: SuSession,
     action_sets: HashMap<xr::ActionSet, Arc<LayerActionSet>>,
+    behaviors: Mutex<HashMap<(xr::Action, xr::Path), BehaviorState>>,
+    one_handed: Mutex<OneHandedState>,
+    hand_paths: [(Hand, xr::Path); 2],
+    /// The interaction profile currently bound to each hand, refreshed every `xrSyncActions`
+    interaction_profiles: Mutex<HashMap<Hand, String>>,
+    advanced_bindings: Mutex<Vec<AdvancedBindingTarget>>,
+    /// The values of the inputs of emulated interaction profiles and of the trigger pulled by the
+    /// gaze trigger, the gamepad's are only read from the controllers while no gamepad is connected
+    emulated_inputs: Mutex<HashMap<String, f32>>,
+    reported_states: ReportedStates,
+}
+
+/// An advanced binding of the profile and the application action it drives
+struct AdvancedBindingTarget {
+    /// Index into `RemapProfile::advanced_bindings`
+    index: usize,
+    action_set: xr::ActionSet,
+    action: xr::Action,
+    sub_action_path: xr::Path,
+    state: BindingKindState,
+    pressed: bool,
+}
+
+impl InnerSession {
+    /// Whether an advanced binding of the action was pressed at the last sync
+    fn advanced_pressed(&self, action: xr::Action, sub_action_path: xr::Path) -> bool {
+        self.advanced_bindings.lock().iter().any(|target| {
+            target.pressed && target.action == action && target.sub_action_path == sub_action_path
+        })
+    }
 }
 
 impl SessionWrapper {
@@ -54,11 +133,18 @@ impl SessionWrapper {
         action_sets: &[xr::ActionSet],
     ) -> Result<xr::Result, xr::Result> {
         let mut did_set = false;
+        //Reported once the actions are unlocked, the application's messengers may call back into
+        //the layer
+        let mut reports = Vec::new();
 
-        self.inner.get_or_try_init(|| {
+        let result = self.inner.get_or_try_init(|| {
             did_set = true;
             let instance = self.instance.upgrade().unwrap();
 
+            //Bindings pushed before the session was attached simply replace the suggestions
+            let pending = std::mem::take(&mut *instance.pending_bindings.lock());
+            instance.suggested_bindings.lock().extend(pending);
+
             let all_action_sets = layer_action_set::all();
 
             let actions_sets = action_sets
@@ -73,6 +159,26 @@ impl SessionWrapper {
                     Ok((*handle, action_set.clone()))
                 })
                 .collect::<Result<HashMap<_, _>, xr::Result>>()?;
+            //Log lines look up the action set names again
+            drop(all_action_sets);
+
+            let all_actions = layer_action::all();
+
+            let binding_layouts = instance
+                .suggested_bindings
+                .lock()
+                .iter()
+                //Emulated profiles are unknown to SuInput, their bindings are evaluated by the layer
+                .filter(|(_, bindings)| !instance.emulates(&bindings.interaction_profile))
+                .map(|(interaction_profile, bindings)| {
+                    bindings.create_binding_layout(
+                        &instance,
+                        *interaction_profile,
+                        &all_actions,
+                        false,
+                    )
+                })
+                .collect::<Result<Vec<_>, xr::Result>>()?;
 
             let application_instance = instance.suinput_instance.create_application_instance(
                 &ApplicationInstanceCreateInfo {
@@ -87,28 +193,211 @@ impl SessionWrapper {
                         .values()
                         .map(|set| &set.su_action_set)
                         .collect::<Vec<_>>()[..],
-                    binding_layouts: &[],
+                    binding_layouts: &binding_layouts.iter().collect::<Vec<_>>()[..],
                 },
             );
 
             let su_session = application_instance.try_begin_session();
 
+            instance.suggest_layer_bindings()?;
+
             let driver = instance.suinput_driver.lock();
-            driver.bind_session(&su_session, self.handle, &[]);
+            let native_action_sets = instance
+                .native_actions
+                .action_set()
+                .into_iter()
+                .collect::<Vec<_>>();
+            driver_hooks::register_session(instance.handle, self.handle);
+            driver.bind_session(&su_session, self.handle, &native_action_sets);
+
+            let mut behaviors = HashMap::new();
+
+            for action_set in actions_sets.values() {
+                for handle in action_set.actions.lock().iter() {
+                    let action = layer_action::get(&all_actions, *handle)?;
+
+                    let behavior = match instance.profile.behavior(&action_set.name, &action.name) {
+                        Some(behavior) => behavior,
+                        None => continue,
+                    };
+
+                    let sub_action_paths = action.sub_actions.boolean_sub_action_paths();
+                    if sub_action_paths.is_empty() {
+                        reports.push(format!(
+                            "Button behavior configured for non-boolean action {}",
+                            action.log_name()
+                        ));
+                    }
+
+                    for path in sub_action_paths {
+                        behaviors.insert((*handle, path), BehaviorState::new(*behavior));
+                    }
+                }
+            }
+
+            let advanced_bindings = self.advanced_binding_targets(
+                &instance,
+                &actions_sets,
+                &all_actions,
+                &mut reports,
+            )?;
 
             Ok(InnerSession {
+                application_instance,
                 su_session,
                 action_sets: actions_sets,
+                behaviors: Mutex::new(behaviors),
+                one_handed: Default::default(),
+                hand_paths: [
+                    (
+                        Hand::Left,
+                        instance.inner.string_to_path(Hand::Left.user_path())?,
+                    ),
+                    (
+                        Hand::Right,
+                        instance.inner.string_to_path(Hand::Right.user_path())?,
+                    ),
+                ],
+                interaction_profiles: Default::default(),
+                advanced_bindings: Mutex::new(advanced_bindings),
+                emulated_inputs: Default::default(),
+                reported_states: Default::default(),
             })
-        })?;
+        });
+
+        if let Some(instance) = self.instance.upgrade() {
+            for report in reports {
+                instance.report(
+                    xr::DebugUtilsMessageSeverityFlagsEXT::WARNING,
+                    ATTACH_FUNCTION_NAME,
+                    &report,
+                );
+            }
+        }
+        result?;
 
         if did_set {
+            self.write_manifest();
+            self.log_binding_reports();
             Ok(xr::Result::SUCCESS)
         } else {
             Err(xr::Result::ERROR_ACTIONSETS_ALREADY_ATTACHED)
         }
     }
 
+    /// Finds the actions driven by the profile's advanced bindings
+    fn advanced_binding_targets(
+        &self,
+        instance: &InstanceWrapper,
+        action_sets: &HashMap<xr::ActionSet, Arc<LayerActionSet>>,
+        all_actions: &Arena<Arc<LayerAction>>,
+        reports: &mut Vec<String>,
+    ) -> Result<Vec<AdvancedBindingTarget>, xr::Result> {
+        let mut targets = Vec::new();
+
+        for (index, advanced) in instance.profile.advanced_bindings.iter().enumerate() {
+            let found = action_sets
+                .iter()
+                .filter(|(_, action_set)| action_set.name == advanced.action_set)
+                .flat_map(|(handle, action_set)| {
+                    action_set
+                        .actions
+                        .lock()
+                        .iter()
+                        .map(|action| (*handle, *action))
+                        .collect::<Vec<_>>()
+                })
+                .find_map(|(action_set, handle)| {
+                    let action = layer_action::get(all_actions, handle).ok()?;
+                    (action.name == advanced.action).then(|| (action_set, handle, action))
+                });
+            let (action_set, handle, action) = match found {
+                Some(found) => found,
+                None => {
+                    reports.push(format!(
+                        "Advanced binding for unknown action `{}/{}`",
+                        advanced.action_set, advanced.action
+                    ));
+                    continue;
+                }
+            };
+
+            if action.sub_actions.boolean_sub_action_paths().is_empty() {
+                reports.push(format!(
+                    "Advanced binding configured for non-boolean action {}",
+                    action.log_name()
+                ));
+                continue;
+            }
+
+            let sub_action_path = if action.sub_action_paths.is_empty() {
+                xr::Path::NULL
+            } else {
+                let path = user_paths::top_level_path(advanced.kind.binding())
+                    .map(|path| instance.inner.string_to_path(path))
+                    .transpose()?;
+                match path.filter(|path| action.sub_action_paths.contains(path)) {
+                    Some(path) => path,
+                    None => {
+                        reports.push(format!(
+                            "{} has no subaction path for `{}`",
+                            action.log_name(),
+                            advanced.kind.binding()
+                        ));
+                        continue;
+                    }
+                }
+            };
+
+            targets.push(AdvancedBindingTarget {
+                index,
+                action_set,
+                action: handle,
+                sub_action_path,
+                state: Default::default(),
+                pressed: false,
+            });
+        }
+
+        Ok(targets)
+    }
+
+    /// Saves the attached actions and the application's suggested bindings for config tools
+    fn write_manifest(&self) {
+        let (instance, inner) = match (self.instance.upgrade(), self.inner.get()) {
+            (Some(instance), Some(inner)) => (instance, inner),
+            _ => return,
+        };
+
+        let result = ActionManifest::new(&instance, &inner.action_sets)
+            .map_err(|err| err.to_string())
+            .and_then(|manifest| manifest.save().map_err(|err| err.to_string()));
+        match result {
+            Ok(path) => info!("Wrote action manifest `{}`", path.display()),
+            Err(err) => warn!("Failed to write action manifest: {}", err),
+        }
+    }
+
+    /// Logs problems with the effective bindings of every interaction profile the application
+    /// suggested
+    fn log_binding_reports(&self) {
+        let instance = match self.instance.upgrade() {
+            Some(instance) => instance,
+            None => return,
+        };
+        let application_name =
+            crate::str_from_bytes_until_nul(&instance.application_info.application_name[..])
+                .unwrap_or_default();
+        match ipc::handler::binding_reports(&instance, None) {
+            Ok(reports) => {
+                for report in &reports {
+                    binding_analysis::log(application_name, report);
+                }
+            }
+            Err(err) => warn!("Failed to analyze the bindings: {}", err),
+        }
+    }
+
     pub fn xr_sync_actions(
         &self,
         active_action_sets: &[xr::ActiveActionSet],
@@ -130,11 +419,1431 @@ impl SessionWrapper {
             .collect::<Option<Vec<_>>>()
             .ok_or(xr::Result::ERROR_ACTIONSET_NOT_ATTACHED)?;
 
+        let instance = self
+            .instance
+            .upgrade()
+            .ok_or(xr::Result::ERROR_HANDLE_INVALID)?;
+
+        self.apply_pending_bindings(&instance, inner);
+
+        //Replays reuse the recorded times so time based behaviors act the same
+        if let Some(time) = instance.input_sources.advance_replay() {
+            self.predicted_display_time
+                .store(time.as_nanos(), Ordering::Relaxed);
+        }
+
+        //SuInput's driver reads the input sources while it syncs
+        instance.input_sources.sync();
+        self.update_tracked_hands(&instance, inner)?;
+        driver_hooks::sync(
+            self.handle,
+            xr::Time::from_nanos(self.predicted_display_time.load(Ordering::Relaxed)),
+        );
         inner.su_session.sync(&active_sets[..]);
 
+        self.update_virtual_inputs(&instance)?;
+
+        if let (Some(one_handed), Some(switch)) = (
+            &instance.profile.one_handed,
+            instance.layer_inputs.one_handed_switch,
+        ) {
+            let pressed = self.layer_boolean(&instance, switch)?;
+            let mut state = inner.one_handed.lock();
+            if state.update(&one_handed.switch, pressed) {
+                self.switch_binding_layouts(&instance, inner, state.other_hand_active());
+            }
+        }
+
+        if let (Some(config), Some(inputs)) = (
+            &instance.profile.playspace_mover,
+            &instance.layer_inputs.playspace_mover,
+        ) {
+            self.update_playspace(&instance, inputs, config.lock_rotation)?;
+        }
+
+        if instance.input_capture.is_pending() {
+            let mut pressed = HashSet::new();
+            for (action, _) in &instance.layer_inputs.capture {
+                if self.layer_boolean(&instance, *action)? {
+                    pressed.insert(*action);
+                }
+            }
+            instance.input_capture.update(pressed);
+        }
+
+        //The layer only reads the bindings of the current profiles
+        if instance.profile.uses_current_interaction_profile()
+            || instance.input_sources.is_active()
+            || instance.has_emulated_bindings()
+            || instance.layer_inputs.gaze_trigger.is_some()
+        {
+            self.update_interaction_profiles(inner)?;
+        }
+
+        self.update_advanced_bindings(&instance, inner, active_action_sets)?;
+
+        let time = xr::Time::from_nanos(self.predicted_display_time.load(Ordering::Relaxed));
+
+        instance.action_overrides.sync(time);
+        inner.reported_states.sync(time);
+
+        if instance.recorder.is_recording() {
+            self.record_physical_inputs(&instance, time)?;
+        }
+
+        if instance.has_emulated_bindings() || instance.layer_inputs.gaze_trigger.is_some() {
+            self.update_emulated_inputs(&instance, inner)?;
+        }
+
+        let layer_actions = layer_action::all();
+
+        for ((handle, sub_action_path), behavior) in inner.behaviors.lock().iter_mut() {
+            let action = layer_action::get(&layer_actions, *handle)?;
+
+            let is_active = active_action_sets
+                .iter()
+                .any(|active_action_set| active_action_set.action_set == action.action_set);
+
+            let pressed = is_active
+                && (self.boolean_input(&instance, inner, action, *handle, *sub_action_path)?
+                    || inner.advanced_pressed(*handle, *sub_action_path));
+
+            behavior.update(pressed, time);
+        }
+
         Ok(xr::Result::SUCCESS)
     }
 
+    /// The state of a boolean action from its bindings alone, read from SuInput which sees the
+    /// input sources through its driver. The bindings SuInput does not read and the controllers
+    /// standing in for an emulated gamepad are added by the layer.
+    fn boolean_input(
+        &self,
+        instance: &InstanceWrapper,
+        inner: &InnerSession,
+        action: &LayerAction,
+        handle: xr::Action,
+        sub_action_path: xr::Path,
+    ) -> Result<bool, xr::Result> {
+        let pressed = inner
+            .su_session
+            .get_action_state(action.sub_actions.get_boolean(sub_action_path)?)
+            .expect("TODO handle error");
+
+        Ok(pressed
+            || self
+                .binding_values(instance, inner, handle, sub_action_path)?
+                .into_iter()
+                .any(InputValue::is_pressed))
+    }
+
+    /// The value of a float or vector2f action, read like `boolean_input`
+    fn analog_input(
+        &self,
+        instance: &InstanceWrapper,
+        inner: &InnerSession,
+        action: &LayerAction,
+        handle: xr::Action,
+        sub_action_path: xr::Path,
+    ) -> Result<InputValue, xr::Result> {
+        let is_vector2f = action.action_type == xr::ActionType::VECTOR2F_INPUT;
+
+        //NULL combines every subaction path of the action
+        let sub_action_paths =
+            if sub_action_path == xr::Path::NULL && !action.sub_action_paths.is_empty() {
+                action.sub_action_paths.clone()
+            } else {
+                vec![sub_action_path]
+            };
+
+        let mut values = self.binding_values(instance, inner, handle, sub_action_path)?;
+        for path in sub_action_paths {
+            values.push(if is_vector2f {
+                let state = inner
+                    .su_session
+                    .get_action_state(action.sub_actions.get_vector2f(path)?)
+                    .map_err(|_| xr::Result::ERROR_RUNTIME_FAILURE)?;
+                InputValue::Vector2f(state.value)
+            } else {
+                let state = inner
+                    .su_session
+                    .get_action_state(action.sub_actions.get_float(path)?)
+                    .map_err(|_| xr::Result::ERROR_RUNTIME_FAILURE)?;
+                InputValue::Float(state.value)
+            });
+        }
+
+        Ok(if is_vector2f {
+            InputValue::Vector2f(reported_state::combine_vector2f(values))
+        } else {
+            InputValue::Float(reported_state::combine_float(values))
+        })
+    }
+
+    /// The values the layer gives the physical inputs an action is bound to through
+    /// `sub_action_path`. The input sources only add to the bindings SuInput does not read.
+    fn binding_values(
+        &self,
+        instance: &InstanceWrapper,
+        inner: &InnerSession,
+        handle: xr::Action,
+        sub_action_path: xr::Path,
+    ) -> Result<Vec<InputValue>, xr::Result> {
+        let sources = &instance.input_sources;
+        let emulated_inputs = inner.emulated_inputs.lock();
+        let mut values = Vec::new();
+        if sources.is_active() || !emulated_inputs.is_empty() {
+            self.for_each_binding(
+                instance,
+                inner,
+                handle,
+                sub_action_path,
+                |binding, read_by_layer| {
+                    if read_by_layer {
+                        values.extend(sources.values(binding));
+                    }
+                    values.extend(input::input_value(&emulated_inputs, binding));
+                },
+            )?;
+        }
+        Ok(values)
+    }
+
+    /// Translates the controllers to the inputs of emulated interaction profiles and the gaze
+    /// trigger to the trigger of the hand aimed by the eyes. A connected gamepad drives the gamepad
+    /// bindings on its own.
+    fn update_emulated_inputs(
+        &self,
+        instance: &InstanceWrapper,
+        inner: &InnerSession,
+    ) -> Result<(), xr::Result> {
+        let sources = &instance.input_sources;
+        let mut values = HashMap::new();
+
+        let mut tables = Vec::with_capacity(2);
+        if !sources
+            .gamepad()
+            .map_or(false, |gamepad| gamepad.is_connected())
+        {
+            tables.push(&instance.layer_inputs.gamepad_emulation);
+        }
+        if instance.emulates(interaction_profiles::HAND_INTERACTION) {
+            tables.push(&instance.layer_inputs.hand_interaction);
+
+            for (hand, path) in inner.hand_paths {
+                let ready = sources
+                    .hands()
+                    .map_or(false, |hands| hands.is_tracked(hand))
+                    || self.current_interaction_profile(path)?.is_some();
+                for input in hand_interaction::ready_inputs(hand) {
+                    values.insert(input, f32::from(u8::from(ready)));
+                }
+            }
+        }
+
+        for (action, action_type, emulated) in tables.into_iter().flatten() {
+            let value = self
+                .layer_input(instance, *action, *action_type)?
+                .as_float();
+            values.insert(emulated.to_string(), value);
+        }
+
+        if let (Some(action), Some(aim)) = (
+            instance.layer_inputs.gaze_trigger,
+            instance
+                .profile
+                .eye_gaze
+                .as_ref()
+                .and_then(|eye_gaze| eye_gaze.aim.as_ref()),
+        ) {
+            let value = self.layer_float(instance, action)?;
+            values.extend(eye_gaze::trigger_values(aim.hand, value));
+        }
+
+        *inner.emulated_inputs.lock() = values;
+        Ok(())
+    }
+
+    /// Computes the components of the virtual devices which combine other inputs
+    fn update_virtual_inputs(&self, instance: &InstanceWrapper) -> Result<(), xr::Result> {
+        let virtual_input = match instance.input_sources.virtual_input() {
+            Some(virtual_input) => virtual_input,
+            None => return Ok(()),
+        };
+
+        if let Some(ring) = instance.injection_ring.get() {
+            match ring.poll() {
+                Ok(records) => {
+                    for (binding, value) in records {
+                        if let Err(err) = virtual_input.inject(&binding, value) {
+                            debug!("Ignored injected value: {}", err);
+                        }
+                    }
+                }
+                Err(err) => warn!("Failed to read the injection ring: {}", err),
+            }
+        }
+
+        let mut computed = HashMap::with_capacity(instance.layer_inputs.virtual_components.len());
+        for component in &instance.layer_inputs.virtual_components {
+            let values = component
+                .inputs
+                .iter()
+                .map(|action| match component.source {
+                    VirtualSource::Combo { .. } => self
+                        .layer_boolean(instance, *action)
+                        .map(|pressed| f32::from(u8::from(pressed))),
+                    _ => self.layer_float(instance, *action),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            computed.insert(
+                component.binding.clone(),
+                virtual_device::evaluate(&component.source, &values),
+            );
+        }
+        virtual_input.update(computed);
+        Ok(())
+    }
+
+    /// Reads one of the layer's own actions, combined with the layer's input sources
+    fn layer_input(
+        &self,
+        instance: &InstanceWrapper,
+        action: xr::Action,
+        action_type: xr::ActionType,
+    ) -> Result<InputValue, xr::Result> {
+        let value =
+            instance
+                .native_actions
+                .read(&self.inner_instance, self.handle, action, action_type)?;
+        let bindings = instance.native_actions.layer_bindings_of(action);
+        Ok(instance
+            .input_sources
+            .combine(value, bindings.iter().map(String::as_str)))
+    }
+
+    fn layer_boolean(
+        &self,
+        instance: &InstanceWrapper,
+        action: xr::Action,
+    ) -> Result<bool, xr::Result> {
+        Ok(self
+            .layer_input(instance, action, xr::ActionType::BOOLEAN_INPUT)?
+            .is_pressed())
+    }
+
+    fn layer_float(
+        &self,
+        instance: &InstanceWrapper,
+        action: xr::Action,
+    ) -> Result<f32, xr::Result> {
+        Ok(self
+            .layer_input(instance, action, xr::ActionType::FLOAT_INPUT)?
+            .as_float())
+    }
+
+    /// Calls `f` with every physical input the action is bound to through `sub_action_path` in the
+    /// current interaction profiles and the hand used in one handed mode, and whether the layer reads it rather than SuInput, as for
+    /// emulated profiles and virtual devices
+    fn for_each_binding(
+        &self,
+        instance: &InstanceWrapper,
+        inner: &InnerSession,
+        handle: xr::Action,
+        sub_action_path: xr::Path,
+        mut f: impl FnMut(&str, bool),
+    ) -> Result<(), xr::Result> {
+        let index = Index::from_bits(handle.into_raw()).ok_or(xr::Result::ERROR_HANDLE_INVALID)?;
+        let one_handed = inner.one_handed.lock();
+        let mut bound = Vec::new();
+        for bindings in instance.suggested_bindings.lock().values() {
+            let emulated = instance.emulates(&bindings.interaction_profile);
+            for binding in &bindings.bindings {
+                if let SuggestedBinding::SimpleBinding {
+                    action,
+                    sub_action_path: path,
+                    binding,
+                    folded,
+                } = binding
+                {
+                    if *action == index
+                        && (sub_action_path == xr::Path::NULL || *path == sub_action_path)
+                        && one_handed.is_enabled(*folded)
+                    {
+                        bound.push((
+                            bindings.interaction_profile.clone(),
+                            binding.clone(),
+                            emulated,
+                        ));
+                    }
+                }
+            }
+        }
+
+        let interaction_profiles = inner.interaction_profiles.lock();
+        for (interaction_profile, binding, emulated) in bound {
+            //Emulated profiles are read whichever controller is held, without a controller the
+            //layer's values stand in for every profile
+            let is_current = emulated
+                || Hand::of_path(&binding)
+                    .and_then(|hand| interaction_profiles.get(&hand))
+                    .map_or(true, |current| *current == interaction_profile);
+            if is_current {
+                f(
+                    &binding,
+                    emulated || instance.profile.virtual_component(&binding).is_some(),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn record_physical_inputs(
+        &self,
+        instance: &InstanceWrapper,
+        time: xr::Time,
+    ) -> Result<(), xr::Result> {
+        let mut values = Vec::with_capacity(instance.layer_inputs.physical.len());
+        for (action, action_type, _) in &instance.layer_inputs.physical {
+            values.push(self.layer_input(instance, *action, *action_type)?);
+        }
+        instance.recorder.record_sync(
+            instance
+                .layer_inputs
+                .physical
+                .iter()
+                .map(|(_, _, binding)| binding.as_str()),
+            values,
+            time,
+        );
+        Ok(())
+    }
+
+    fn update_advanced_bindings(
+        &self,
+        instance: &InstanceWrapper,
+        inner: &InnerSession,
+        active_action_sets: &[xr::ActiveActionSet],
+    ) -> Result<(), xr::Result> {
+        for target in inner.advanced_bindings.lock().iter_mut() {
+            let is_active = active_action_sets
+                .iter()
+                .any(|active_action_set| active_action_set.action_set == target.action_set);
+            if !is_active {
+                target.pressed = false;
+                continue;
+            }
+
+            let inputs = instance.layer_inputs.advanced_bindings[target.index]
+                .iter()
+                .map(|(action, action_type)| {
+                    Ok(match self.layer_input(instance, *action, *action_type)? {
+                        InputValue::Float(value) => BindingInput::Float(value),
+                        InputValue::Vector2f([x, y]) => {
+                            BindingInput::Vector2f(xr::Vector2f { x, y })
+                        }
+                        InputValue::Boolean(pressed) => BindingInput::Boolean(pressed),
+                    })
+                })
+                .collect::<Result<Vec<_>, xr::Result>>()?;
+
+            target.pressed = target.state.update(
+                &instance.profile.advanced_bindings[target.index].kind,
+                &inputs,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Gives SuInput the bindings of the hand switched to in one handed mode, they are read from
+    /// the next sync
+    fn switch_binding_layouts(
+        &self,
+        instance: &InstanceWrapper,
+        inner: &InnerSession,
+        other_hand_active: bool,
+    ) {
+        let all_actions = layer_action::all();
+        for (interaction_profile, bindings) in instance.suggested_bindings.lock().iter() {
+            if instance.emulates(&bindings.interaction_profile) {
+                continue;
+            }
+            match bindings.create_binding_layout(
+                instance,
+                *interaction_profile,
+                &all_actions,
+                other_hand_active,
+            ) {
+                Ok(binding_layout) => inner
+                    .application_instance
+                    .set_binding_layout(&binding_layout),
+                Err(err) => warn!(
+                    "Failed to switch the bindings of `{}`: {}",
+                    bindings.interaction_profile, err
+                ),
+            }
+        }
+    }
+
+    /// Swaps in the bindings pushed over IPC since the last sync. Bindings SuInput rejects are
+    /// logged and the profile keeps its previous ones.
+    fn apply_pending_bindings(&self, instance: &InstanceWrapper, inner: &InnerSession) {
+        let pending = std::mem::take(&mut *instance.pending_bindings.lock());
+        if pending.is_empty() {
+            return;
+        }
+
+        let all_actions = layer_action::all();
+        for (interaction_profile, bindings) in pending {
+            if !instance.emulates(&bindings.interaction_profile) {
+                match bindings.create_binding_layout(
+                    instance,
+                    interaction_profile,
+                    &all_actions,
+                    inner.one_handed.lock().other_hand_active(),
+                ) {
+                    Ok(binding_layout) => inner
+                        .application_instance
+                        .set_binding_layout(&binding_layout),
+                    Err(err) => {
+                        warn!(
+                            "Failed to apply new bindings for `{}`: {}",
+                            bindings.interaction_profile, err
+                        );
+                        continue;
+                    }
+                }
+            }
+            info!(
+                "Applied new bindings for `{}`",
+                bindings.interaction_profile
+            );
+            instance
+                .suggested_bindings
+                .lock()
+                .insert(interaction_profile, bindings);
+        }
+    }
+
+    /// The interaction profile the runtime currently uses for a top level path
+    pub fn current_interaction_profile(
+        &self,
+        top_level_path: xr::Path,
+    ) -> Result<Option<String>, xr::Result> {
+        let mut state = xr::InteractionProfileState {
+            ty: xr::InteractionProfileState::TYPE,
+            next: std::ptr::null_mut(),
+            interaction_profile: xr::Path::NULL,
+        };
+        unsafe {
+            (self.inner_instance.core.get_current_interaction_profile)(
+                self.handle,
+                top_level_path,
+                &mut state,
+            )
+        }
+        .result()?;
+
+        if state.interaction_profile == xr::Path::NULL {
+            Ok(None)
+        } else {
+            self.inner_instance
+                .path_to_string(state.interaction_profile)
+                .map(Some)
+        }
+    }
+
+    fn update_interaction_profiles(&self, inner: &InnerSession) -> Result<(), xr::Result> {
+        let mut interaction_profiles = inner.interaction_profiles.lock();
+
+        for (hand, path) in inner.hand_paths {
+            match self.current_interaction_profile(path)? {
+                Some(interaction_profile) => {
+                    interaction_profiles.insert(hand, interaction_profile);
+                }
+                None => {
+                    interaction_profiles.remove(&hand);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Looks up a per binding setting of a pose action on the controller currently bound to
+    /// `hand`, or to either hand if none is given
+    fn find_pose_setting<T>(
+        &self,
+        action: xr::Action,
+        hand: Option<Hand>,
+        find: impl Fn(&RemapProfile, &str, &str) -> Option<T>,
+    ) -> Option<T> {
+        let inner = self.inner.get()?;
+        let instance = self.instance.upgrade()?;
+        if !instance.profile.uses_current_interaction_profile() {
+            return None;
+        }
+
+        let interaction_profiles = inner.interaction_profiles.lock();
+        let hands = match hand {
+            Some(hand) => vec![hand],
+            None => vec![Hand::Left, Hand::Right],
+        };
+
+        hands.into_iter().find_map(|hand| {
+            let interaction_profile = interaction_profiles.get(&hand)?;
+            let binding =
+                instance
+                    .native_actions
+                    .binding(action, interaction_profile, Some(hand))?;
+            find(&instance.profile, interaction_profile, &binding)
+        })
+    }
+
+    pub fn pose_offset(&self, action: xr::Action, hand: Option<Hand>) -> Option<xr::Posef> {
+        self.find_pose_setting(action, hand, |profile, interaction_profile, binding| {
+            profile
+                .pose_offset(interaction_profile, binding)
+                .map(|offset| offset.to_pose())
+        })
+    }
+
+    /// The pose of a hand aimed with the mouse relative to the head, if the profile aims it
+    pub fn desktop_aim(&self, hand: Hand) -> Option<xr::Posef> {
+        self.instance.upgrade()?.input_sources.desktop()?.aim(hand)
+    }
+
+    /// The pose of a tracked hand standing in for its controller, following the grip or aim pose
+    /// depending on what the action is bound to
+    pub fn tracked_hand_pose(
+        &self,
+        action: xr::Action,
+        hand: Hand,
+        base_space: xr::Space,
+        time: xr::Time,
+    ) -> Result<Option<xr::Posef>, xr::Result> {
+        let instance = match self.instance.upgrade() {
+            Some(instance) => instance,
+            None => return Ok(None),
+        };
+        if !instance
+            .input_sources
+            .hands()
+            .map_or(false, |hands| hands.is_tracked(hand))
+        {
+            return Ok(None);
+        }
+        let tracker = match self
+            .hand_trackers()
+            .iter()
+            .find(|(tracked, _)| *tracked == hand)
+        {
+            Some((_, tracker)) => *tracker,
+            None => return Ok(None),
+        };
+
+        let pose = instance
+            .native_actions
+            .bindings_of(action, hand)
+            .first()
+            .map_or(HandPose::Grip, |(_, binding)| HandPose::of_binding(binding));
+        Ok(self
+            .locate_hand_joints(tracker, base_space, time)?
+            .map(|joints| pose.from_joints(&joints, hand)))
+    }
+
+    /// Locates poses the runtime cannot. Bindings of an emulated hand interaction profile follow
+    /// the grip or aim pose of the controller and palm poses are offset from the grip.
+    pub fn apply_emulated_pose(
+        &self,
+        action: xr::Action,
+        hand: Hand,
+        base_space: xr::Space,
+        time: xr::Time,
+        location: &mut xr::SpaceLocation,
+    ) -> Result<(), xr::Result> {
+        let instance = match self.instance.upgrade() {
+            Some(instance) => instance,
+            None => return Ok(()),
+        };
+        let valid =
+            xr::SpaceLocationFlags::POSITION_VALID | xr::SpaceLocationFlags::ORIENTATION_VALID;
+
+        if location.location_flags.contains(valid) {
+            let is_palm = instance.emulates_palm_pose()
+                && instance
+                    .native_actions
+                    .bindings_of(action, hand)
+                    .iter()
+                    .any(|(interaction_profile, binding)| {
+                        !instance.emulates(interaction_profile)
+                            && hand_interaction::is_palm_pose(binding)
+                    });
+            if is_palm {
+                location.pose = math::pose_mul(location.pose, hand_interaction::palm_offset(hand));
+            }
+            return Ok(());
+        }
+
+        if !instance.emulates(interaction_profiles::HAND_INTERACTION) {
+            return Ok(());
+        }
+        let pose = match instance.native_actions.binding(
+            action,
+            interaction_profiles::HAND_INTERACTION,
+            Some(hand),
+        ) {
+            Some(binding) => HandPose::of_binding(&binding),
+            None => return Ok(()),
+        };
+        let source = match pose {
+            HandPose::Grip | HandPose::Palm => HandPose::Grip,
+            HandPose::Aim | HandPose::Pinch | HandPose::Poke => HandPose::Aim,
+        };
+        let layer_action = match instance
+            .layer_inputs
+            .hand_poses
+            .iter()
+            .find(|(posed, kind, _)| *posed == hand && *kind == source)
+        {
+            Some((_, _, layer_action)) => *layer_action,
+            None => return Ok(()),
+        };
+
+        let space = self.layer_action_space(layer_action)?;
+        if let Some(located) = self.locate_pose(space, base_space, time)? {
+            location.pose = if pose == HandPose::Palm {
+                math::pose_mul(located, hand_interaction::palm_offset(hand))
+            } else {
+                located
+            };
+            location.location_flags |= valid;
+        }
+        Ok(())
+    }
+
+    /// The eye gaze standing in for the aim of a hand while the eyes are tracked, if the action is
+    /// bound to the aim pose of the hand the profile aims with the eyes
+    pub fn gaze_aim(
+        &self,
+        action: xr::Action,
+        hand: Hand,
+        base_space: xr::Space,
+        time: xr::Time,
+    ) -> Result<Option<xr::Posef>, xr::Result> {
+        let instance = match self.instance.upgrade() {
+            Some(instance) => instance,
+            None => return Ok(None),
+        };
+        let gaze_pose = match (
+            instance.layer_inputs.gaze_pose,
+            instance
+                .profile
+                .eye_gaze
+                .as_ref()
+                .and_then(|eye_gaze| eye_gaze.aim.as_ref()),
+        ) {
+            (Some(gaze_pose), Some(aim)) if aim.hand == hand => gaze_pose,
+            _ => return Ok(None),
+        };
+        let is_aim = instance
+            .native_actions
+            .bindings_of(action, hand)
+            .first()
+            .map_or(false, |(_, binding)| {
+                HandPose::of_binding(binding) == HandPose::Aim
+            });
+        if !is_aim {
+            return Ok(None);
+        }
+
+        let space = self.layer_action_space(gaze_pose)?;
+        self.locate_pose(space, base_space, time)
+    }
+
+    /// What stands in for the eye gaze an action is bound to while the runtime cannot locate it.
+    /// Emulated eye gaze falls back to the head unless the profile says otherwise.
+    fn gaze_fallback(
+        &self,
+        instance: &InstanceWrapper,
+        action: xr::Action,
+    ) -> Option<GazeFallback> {
+        let fallback = match &instance.profile.eye_gaze {
+            Some(eye_gaze) => eye_gaze.fallback,
+            None if instance.emulates(interaction_profiles::EYE_GAZE_INTERACTION) => {
+                GazeFallback::Head
+            }
+            None => return None,
+        };
+        if fallback == GazeFallback::Disabled {
+            return None;
+        }
+        let binding = instance.native_actions.binding(
+            action,
+            interaction_profiles::EYE_GAZE_INTERACTION,
+            None,
+        )?;
+        (binding == eye_gaze::GAZE_POSE).then_some(fallback)
+    }
+
+    /// Locates the eye gaze from the head or the aim of a hand when the runtime cannot
+    pub fn apply_gaze_fallback(
+        &self,
+        action: xr::Action,
+        base_space: xr::Space,
+        time: xr::Time,
+        location: &mut xr::SpaceLocation,
+    ) -> Result<(), xr::Result> {
+        let valid =
+            xr::SpaceLocationFlags::POSITION_VALID | xr::SpaceLocationFlags::ORIENTATION_VALID;
+        if location.location_flags.contains(valid) {
+            return Ok(());
+        }
+        let instance = match self.instance.upgrade() {
+            Some(instance) => instance,
+            None => return Ok(()),
+        };
+
+        let located = match self.gaze_fallback(&instance, action) {
+            Some(GazeFallback::Head) => self.locate_view(base_space, time)?,
+            Some(GazeFallback::Aim(hand)) => {
+                match instance
+                    .layer_inputs
+                    .hand_poses
+                    .iter()
+                    .find(|(posed, kind, _)| *posed == hand && *kind == HandPose::Aim)
+                {
+                    Some((_, _, layer_action)) => {
+                        let space = self.layer_action_space(*layer_action)?;
+                        self.locate_pose(space, base_space, time)?
+                    }
+                    None => None,
+                }
+            }
+            Some(GazeFallback::Disabled) | None => None,
+        };
+        if let Some(located) = located {
+            location.pose = located;
+            location.location_flags |= valid;
+        }
+        Ok(())
+    }
+
+    /// Whether an action has been forced inactive over IPC
+    pub fn is_overridden_inactive(&self, action: xr::Action, sub_action_path: xr::Path) -> bool {
+        self.instance.upgrade().map_or(false, |instance| {
+            instance
+                .action_overrides
+                .is_inactive(action, sub_action_path)
+        })
+    }
+
+    pub fn pose_filter(&self, action: xr::Action, hand: Option<Hand>) -> Option<PoseFilterConfig> {
+        self.find_pose_setting(action, hand, |profile, interaction_profile, binding| {
+            profile.pose_filter(interaction_profile, binding).copied()
+        })
+    }
+
+    /// Finds the action and subaction path the runtime knows a pose or haptic action by
+    fn native_action(
+        &self,
+        action: xr::Action,
+        sub_action_path: xr::Path,
+    ) -> Result<(xr::Action, xr::Path), xr::Result> {
+        let instance = self
+            .instance
+            .upgrade()
+            .ok_or(xr::Result::ERROR_HANDLE_INVALID)?;
+
+        let layer_actions = layer_action::all();
+        layer_action::get(&layer_actions, action)?;
+
+        let native_action = instance
+            .native_actions
+            .get(action)
+            .ok_or(xr::Result::ERROR_ACTION_TYPE_MISMATCH)?;
+
+        let sub_action_path = native_action
+            .physical_sub_action_path(sub_action_path)
+            .ok_or(xr::Result::ERROR_PATH_UNSUPPORTED)?;
+
+        Ok((native_action.handle, sub_action_path))
+    }
+
+    pub fn xr_create_action_space(
+        self: &Arc<Self>,
+        create_info: &xr::ActionSpaceCreateInfo,
+        space_out: &mut xr::Space,
+    ) -> Result<xr::Result, xr::Result> {
+        let (action, subaction_path) =
+            self.native_action(create_info.action, create_info.subaction_path)?;
+
+        let instance = self
+            .instance
+            .upgrade()
+            .ok_or(xr::Result::ERROR_HANDLE_INVALID)?;
+
+        let physical_hand = if subaction_path == xr::Path::NULL {
+            None
+        } else {
+            Hand::of_path(&self.inner_instance.path_to_string(subaction_path)?)
+        };
+
+        let derived_pose = match &instance.profile.one_handed {
+            Some(one_handed) if instance.is_folded(create_info.subaction_path)? => {
+                one_handed.derived_pose
+            }
+            _ => None,
+        };
+
+        let native_create_info = xr::ActionSpaceCreateInfo {
+            action,
+            subaction_path,
+            ..*create_info
+        };
+
+        let result = unsafe {
+            (self.inner_instance.core.create_action_space)(
+                self.handle,
+                &native_create_info,
+                space_out,
+            )
+        }
+        .result()?;
+
+        xr::Space::all_wrappers().insert(
+            *space_out,
+            Arc::new(SpaceWrapper {
+                handle: *space_out,
+                session: Arc::downgrade(self),
+                inner_instance: self.inner_instance.clone(),
+                kind: SpaceKind::Action {
+                    action: create_info.action,
+                    sub_action_path: create_info.subaction_path,
+                    physical_hand,
+                    derived_pose,
+                },
+                filters: Default::default(),
+            }),
+        );
+
+        Ok(result)
+    }
+
+    /// Writes an action state returned to the application to the recording, if one is running
+    pub fn record_state(
+        &self,
+        action: xr::Action,
+        sub_action_path: xr::Path,
+        state: RecordedState,
+    ) {
+        if let Some(instance) = self.instance.upgrade() {
+            let time = xr::Time::from_nanos(self.predicted_display_time.load(Ordering::Relaxed));
+            instance
+                .recorder
+                .record_state(&instance, action, sub_action_path, time, state);
+        }
+    }
+
+    pub fn xr_get_action_state_pose(
+        &self,
+        action: xr::Action,
+        sub_action_path: xr::Path,
+        out: &mut xr::ActionStatePose,
+    ) -> Result<xr::Result, xr::Result> {
+        self.inner
+            .get()
+            .ok_or(xr::Result::ERROR_ACTIONSET_NOT_ATTACHED)?;
+
+        let (native_action, subaction_path) = self.native_action(action, sub_action_path)?;
+
+        let get_info = xr::ActionStateGetInfo {
+            ty: xr::ActionStateGetInfo::TYPE,
+            next: std::ptr::null(),
+            action: native_action,
+            subaction_path,
+        };
+
+        let result = unsafe {
+            (self.inner_instance.core.get_action_state_pose)(self.handle, &get_info, out)
+        }
+        .result()?;
+
+        if let Some(instance) = self.instance.upgrade() {
+            //Eye gaze the layer stands in for is active without an eye tracker
+            if !bool::from(out.is_active) && self.gaze_fallback(&instance, action).is_some() {
+                out.is_active = true.into();
+            }
+            if instance
+                .action_overrides
+                .is_inactive(action, sub_action_path)
+            {
+                out.is_active = false.into();
+            }
+        }
+
+        Ok(result)
+    }
+
+    pub fn xr_apply_haptic_feedback(
+        &self,
+        haptic_action_info: &xr::HapticActionInfo,
+        haptic_feedback: *const xr::HapticBaseHeader,
+    ) -> Result<xr::Result, xr::Result> {
+        self.inner
+            .get()
+            .ok_or(xr::Result::ERROR_ACTIONSET_NOT_ATTACHED)?;
+
+        let (action, subaction_path) =
+            self.native_action(haptic_action_info.action, haptic_action_info.subaction_path)?;
+
+        let native_action_info = xr::HapticActionInfo {
+            action,
+            subaction_path,
+            ..*haptic_action_info
+        };
+
+        let result = unsafe {
+            (self.inner_instance.core.apply_haptic_feedback)(
+                self.handle,
+                &native_action_info,
+                haptic_feedback,
+            )
+        }
+        .result()?;
+
+        let vibration = unsafe { haptic_feedback.as_ref() }
+            .filter(|header| header.ty == xr::StructureType::HAPTIC_VIBRATION)
+            .map(|header| unsafe { &*(header as *const _ as *const xr::HapticVibration) });
+        if let Some(vibration) = vibration {
+            self.rumble_gamepad(haptic_action_info.subaction_path, Some(vibration))?;
+        }
+
+        Ok(result)
+    }
+
+    /// Plays haptics on the gamepad as well if the profile asks for it, `None` stops them
+    fn rumble_gamepad(
+        &self,
+        sub_action_path: xr::Path,
+        vibration: Option<&xr::HapticVibration>,
+    ) -> Result<(), xr::Result> {
+        let instance = match self.instance.upgrade() {
+            Some(instance) => instance,
+            None => return Ok(()),
+        };
+        let gamepad = match instance.input_sources.gamepad() {
+            Some(gamepad) => gamepad,
+            None => return Ok(()),
+        };
+
+        match vibration {
+            Some(vibration) => {
+                let hand = if sub_action_path == xr::Path::NULL {
+                    None
+                } else {
+                    Hand::of_path(&instance.inner.path_to_string(sub_action_path)?)
+                };
+                gamepad.apply_vibration(hand, vibration);
+            }
+            None => gamepad.stop_vibration(),
+        }
+        Ok(())
+    }
+
+    pub fn xr_stop_haptic_feedback(
+        &self,
+        haptic_action_info: &xr::HapticActionInfo,
+    ) -> Result<xr::Result, xr::Result> {
+        self.inner
+            .get()
+            .ok_or(xr::Result::ERROR_ACTIONSET_NOT_ATTACHED)?;
+
+        let (action, subaction_path) =
+            self.native_action(haptic_action_info.action, haptic_action_info.subaction_path)?;
+
+        let native_action_info = xr::HapticActionInfo {
+            action,
+            subaction_path,
+            ..*haptic_action_info
+        };
+
+        let result = unsafe {
+            (self.inner_instance.core.stop_haptic_feedback)(self.handle, &native_action_info)
+        }
+        .result()?;
+
+        self.rumble_gamepad(haptic_action_info.subaction_path, None)?;
+        Ok(result)
+    }
+
+    /// Creates a reference space owned by the layer on first use
+    fn layer_reference_space(
+        &self,
+        space: &OnceCell<xr::Space>,
+        reference_space_type: xr::ReferenceSpaceType,
+    ) -> Result<xr::Space, xr::Result> {
+        space
+            .get_or_try_init(|| {
+                let create_info = xr::ReferenceSpaceCreateInfo {
+                    ty: xr::ReferenceSpaceCreateInfo::TYPE,
+                    next: std::ptr::null(),
+                    reference_space_type,
+                    pose_in_reference_space: math::IDENTITY,
+                };
+                let mut space = xr::Space::NULL;
+                unsafe {
+                    (self.inner_instance.core.create_reference_space)(
+                        self.handle,
+                        &create_info,
+                        &mut space,
+                    )
+                }
+                .result()?;
+                Ok(space)
+            })
+            .copied()
+    }
+
+    /// The runtime's LOCAL space, owned by the layer
+    pub fn layer_local_space(&self) -> Result<xr::Space, xr::Result> {
+        self.layer_reference_space(&self.local_space, xr::ReferenceSpaceType::LOCAL)
+    }
+
+    /// Locates a space with the runtime, returning the pose only if it is fully valid
+    fn locate_pose(
+        &self,
+        space: xr::Space,
+        base_space: xr::Space,
+        time: xr::Time,
+    ) -> Result<Option<xr::Posef>, xr::Result> {
+        let mut location = xr::SpaceLocation {
+            ty: xr::SpaceLocation::TYPE,
+            next: std::ptr::null_mut(),
+            location_flags: xr::SpaceLocationFlags::EMPTY,
+            pose: math::IDENTITY,
+        };
+        unsafe { (self.inner_instance.core.locate_space)(space, base_space, time, &mut location) }
+            .result()?;
+
+        let valid =
+            xr::SpaceLocationFlags::POSITION_VALID | xr::SpaceLocationFlags::ORIENTATION_VALID;
+        Ok(if location.location_flags.contains(valid) {
+            Some(location.pose)
+        } else {
+            None
+        })
+    }
+
+    /// Locates the user's head relative to `base_space`
+    pub fn locate_view(
+        &self,
+        base_space: xr::Space,
+        time: xr::Time,
+    ) -> Result<Option<xr::Posef>, xr::Result> {
+        let view_space =
+            self.layer_reference_space(&self.view_space, xr::ReferenceSpaceType::VIEW)?;
+        self.locate_pose(view_space, base_space, time)
+    }
+
+    /// The transform from a reference space as created by the runtime to the one the application
+    /// sees after the playspace has been moved. Only LOCAL and STAGE spaces are moved.
+    pub fn playspace_offset(
+        &self,
+        reference_space_type: xr::ReferenceSpaceType,
+        pose_in_reference_space: xr::Posef,
+        time: xr::Time,
+    ) -> Result<Option<xr::Posef>, xr::Result> {
+        if reference_space_type != xr::ReferenceSpaceType::LOCAL
+            && reference_space_type != xr::ReferenceSpaceType::STAGE
+        {
+            return Ok(None);
+        }
+
+        let offset = self.playspace.lock().offset();
+        if offset == math::IDENTITY {
+            return Ok(None);
+        }
+
+        //The offset is kept in STAGE space so LOCAL and STAGE move together
+        let root = if reference_space_type == xr::ReferenceSpaceType::LOCAL {
+            let stage_space =
+                self.layer_reference_space(&self.stage_space, xr::ReferenceSpaceType::STAGE);
+            let local_space =
+                self.layer_reference_space(&self.local_space, xr::ReferenceSpaceType::LOCAL)?;
+            match stage_space {
+                Ok(stage_space) => self
+                    .locate_pose(local_space, stage_space, time)?
+                    .unwrap_or(math::IDENTITY),
+                Err(_) => math::IDENTITY,
+            }
+        } else {
+            math::IDENTITY
+        };
+
+        let native = math::pose_mul(root, pose_in_reference_space);
+        Ok(Some(math::pose_mul(
+            math::pose_inverse(native),
+            math::pose_mul(offset, native),
+        )))
+    }
+
+    /// An action space of one of the layer's own pose actions
+    fn layer_action_space(&self, action: xr::Action) -> Result<xr::Space, xr::Result> {
+        let mut spaces = self.layer_action_spaces.lock();
+        if let Some(space) = spaces.get(&action) {
+            return Ok(*space);
+        }
+
+        let create_info = xr::ActionSpaceCreateInfo {
+            ty: xr::ActionSpaceCreateInfo::TYPE,
+            next: std::ptr::null(),
+            action,
+            subaction_path: xr::Path::NULL,
+            pose_in_action_space: math::IDENTITY,
+        };
+        let mut space = xr::Space::NULL;
+        unsafe {
+            (self.inner_instance.core.create_action_space)(self.handle, &create_info, &mut space)
+        }
+        .result()?;
+        spaces.insert(action, space);
+        Ok(space)
+    }
+
+    fn hand_trackers(&self) -> &[(Hand, xr::HandTrackerEXT)] {
+        let ext = match self.inner_instance.exts.ext_hand_tracking {
+            Some(ext) => ext,
+            None => return &[],
+        };
+
+        self.hand_trackers.get_or_init(|| {
+            let mut trackers = Vec::with_capacity(2);
+            for (hand, hand_ext) in [
+                (Hand::Left, xr::HandEXT::LEFT),
+                (Hand::Right, xr::HandEXT::RIGHT),
+            ] {
+                let create_info = xr::HandTrackerCreateInfoEXT {
+                    ty: xr::HandTrackerCreateInfoEXT::TYPE,
+                    next: std::ptr::null(),
+                    hand: hand_ext,
+                    hand_joint_set: xr::HandJointSetEXT::DEFAULT,
+                };
+                let mut tracker = xr::HandTrackerEXT::NULL;
+                match unsafe { (ext.create_hand_tracker)(self.handle, &create_info, &mut tracker) }
+                    .result()
+                {
+                    Ok(_) => trackers.push((hand, tracker)),
+                    Err(err) => {
+                        warn!("Failed to create hand tracker: {}", err);
+                        return Vec::new();
+                    }
+                }
+            }
+            trackers
+        })
+    }
+
+    /// Locates the joints of a tracked hand relative to `base_space`
+    fn locate_hand_joints(
+        &self,
+        tracker: xr::HandTrackerEXT,
+        base_space: xr::Space,
+        time: xr::Time,
+    ) -> Result<Option<HandJoints>, xr::Result> {
+        let ext = self
+            .inner_instance
+            .exts
+            .ext_hand_tracking
+            .ok_or(xr::Result::ERROR_EXTENSION_NOT_PRESENT)?;
+
+        let mut joints = [xr::HandJointLocationEXT {
+            location_flags: xr::SpaceLocationFlags::EMPTY,
+            pose: math::IDENTITY,
+            radius: 0.,
+        }; hand_tracking::JOINT_COUNT];
+        let mut locations = xr::HandJointLocationsEXT {
+            ty: xr::HandJointLocationsEXT::TYPE,
+            next: std::ptr::null_mut(),
+            is_active: false.into(),
+            joint_count: hand_tracking::JOINT_COUNT as u32,
+            joint_locations: joints.as_mut_ptr(),
+        };
+        let locate_info = xr::HandJointsLocateInfoEXT {
+            ty: xr::HandJointsLocateInfoEXT::TYPE,
+            next: std::ptr::null(),
+            base_space,
+            time,
+        };
+        unsafe { (ext.locate_hand_joints)(tracker, &locate_info, &mut locations) }.result()?;
+
+        Ok(if locations.is_active.into() {
+            HandJoints::from_locations(&joints)
+        } else {
+            None
+        })
+    }
+
+    /// Derives controller input from the hands which are tracked without holding a controller
+    fn update_tracked_hands(
+        &self,
+        instance: &InstanceWrapper,
+        inner: &InnerSession,
+    ) -> Result<(), xr::Result> {
+        let hands = match instance.input_sources.hands() {
+            Some(hands) => hands,
+            None => return Ok(()),
+        };
+        let time = xr::Time::from_nanos(self.predicted_display_time.load(Ordering::Relaxed));
+        if time.as_nanos() == 0 {
+            return Ok(());
+        }
+
+        //Gestures are read relative to the head
+        let view_space =
+            self.layer_reference_space(&self.view_space, xr::ReferenceSpaceType::VIEW)?;
+        for (hand, path) in inner.hand_paths {
+            let tracker = self
+                .hand_trackers()
+                .iter()
+                .find(|(tracked, _)| *tracked == hand)
+                .map(|(_, tracker)| *tracker);
+            let joints = match tracker {
+                Some(tracker) if self.current_interaction_profile(path)?.is_none() => {
+                    self.locate_hand_joints(tracker, view_space, time)?
+                }
+                _ => None,
+            };
+            hands.update(hand, joints.as_ref());
+        }
+        Ok(())
+    }
+
+    fn update_playspace(
+        &self,
+        instance: &InstanceWrapper,
+        inputs: &PlayspaceMoverInputs,
+        lock_rotation: bool,
+    ) -> Result<(), xr::Result> {
+        let grabbing = self.layer_boolean(instance, inputs.grab)?;
+
+        let time = xr::Time::from_nanos(self.predicted_display_time.load(Ordering::Relaxed));
+        let hand = if grabbing && time.as_nanos() != 0 {
+            let grip_space = *self.grip_space.get_or_try_init(|| {
+                let create_info = xr::ActionSpaceCreateInfo {
+                    ty: xr::ActionSpaceCreateInfo::TYPE,
+                    next: std::ptr::null(),
+                    action: inputs.grip,
+                    subaction_path: xr::Path::NULL,
+                    pose_in_action_space: math::IDENTITY,
+                };
+                let mut space = xr::Space::NULL;
+                unsafe {
+                    (self.inner_instance.core.create_action_space)(
+                        self.handle,
+                        &create_info,
+                        &mut space,
+                    )
+                }
+                .result()?;
+                Ok::<_, xr::Result>(space)
+            })?;
+            let stage_space =
+                self.layer_reference_space(&self.stage_space, xr::ReferenceSpaceType::STAGE)?;
+            self.locate_pose(grip_space, stage_space, time)?
+        } else {
+            None
+        };
+
+        self.playspace.lock().update(grabbing, hand, lock_rotation);
+
+        Ok(())
+    }
+
+    pub fn xr_create_reference_space(
+        self: &Arc<Self>,
+        create_info: &xr::ReferenceSpaceCreateInfo,
+        space_out: &mut xr::Space,
+    ) -> Result<xr::Result, xr::Result> {
+        let result = unsafe {
+            (self.inner_instance.core.create_reference_space)(self.handle, create_info, space_out)
+        }
+        .result()?;
+
+        xr::Space::all_wrappers().insert(
+            *space_out,
+            Arc::new(SpaceWrapper {
+                handle: *space_out,
+                session: Arc::downgrade(self),
+                inner_instance: self.inner_instance.clone(),
+                kind: SpaceKind::Reference {
+                    reference_space_type: create_info.reference_space_type,
+                    pose_in_reference_space: create_info.pose_in_reference_space,
+                },
+                filters: Default::default(),
+            }),
+        );
+
+        Ok(result)
+    }
+
+    pub fn xr_locate_views(
+        &self,
+        view_locate_info: &xr::ViewLocateInfo,
+        view_state: *mut xr::ViewState,
+        view_capacity_input: u32,
+        view_count_output: *mut u32,
+        views: *mut xr::View,
+    ) -> Result<xr::Result, xr::Result> {
+        let result = unsafe {
+            (self.inner_instance.core.locate_views)(
+                self.handle,
+                view_locate_info,
+                view_state,
+                view_capacity_input,
+                view_count_output,
+                views,
+            )
+        }
+        .result()?;
+
+        if view_capacity_input == 0 || views.is_null() {
+            return Ok(result);
+        }
+
+        if let Some(offset) =
+            space::playspace_offset(view_locate_info.space, view_locate_info.display_time)?
+        {
+            let inverse = math::pose_inverse(offset);
+            let views =
+                unsafe { std::slice::from_raw_parts_mut(views, *view_count_output as usize) };
+            for view in views {
+                view.pose = math::pose_mul(inverse, view.pose);
+            }
+        }
+
+        Ok(result)
+    }
+
+    pub fn xr_wait_frame(
+        &self,
+        frame_wait_info: *const xr::FrameWaitInfo,
+        frame_state: &mut xr::FrameState,
+    ) -> Result<xr::Result, xr::Result> {
+        let result = unsafe {
+            (self.inner_instance.core.wait_frame)(self.handle, frame_wait_info, frame_state)
+        }
+        .result()?;
+
+        self.predicted_display_time.store(
+            frame_state.predicted_display_time.as_nanos(),
+            Ordering::Relaxed,
+        );
+
+        Ok(result)
+    }
+
     pub fn xr_get_action_state_boolean(
         self: &Arc<Self>,
         action: xr::Action,
@@ -146,57 +1855,168 @@ impl SessionWrapper {
             .get()
             .ok_or(xr::Result::ERROR_ACTIONSET_NOT_ATTACHED)?;
 
+        let instance = self
+            .instance
+            .upgrade()
+            .ok_or(xr::Result::ERROR_HANDLE_INVALID)?;
         let layer_actions = layer_action::all();
-        let action = layer_action::get(&layer_actions, action)?;
+        let wrapped_action = layer_action::get(&layer_actions, action)?;
+        wrapped_action.sub_actions.get_boolean(sub_action_path)?;
 
-        match &action.sub_actions {
-            layer_action::SubActions::None(action) => {
-                if sub_action_path != xr::Path::NULL {
-                    return Err(xr::Result::ERROR_PATH_INVALID);
-                }
+        if let Some(overridden) = instance.action_overrides.get(action, sub_action_path) {
+            let (is_active, state) = match overridden.state {
+                OverrideState::Boolean { state } => (true, state),
+                _ => (false, false),
+            };
+            out.is_active = is_active.into();
+            out.current_state = state.into();
+            out.changed_since_last_sync = overridden.changed_since_last_sync.into();
+            out.last_change_time = overridden.last_change_time;
+            return Ok(xr::Result::SUCCESS);
+        }
 
-                match action {
-                    SingletonAction::Boolean(action) => {
-                        let action_state = inner
-                            .su_session
-                            .get_action_state(action)
-                            .expect("TODO handle error");
+        if let Some(behavior) = inner.behaviors.lock().get(&(action, sub_action_path)) {
+            out.is_active = true.into();
+            out.current_state = behavior.state().into();
+            out.changed_since_last_sync = behavior.changed_since_last_sync().into();
+            out.last_change_time = behavior.last_change_time();
+            return Ok(xr::Result::SUCCESS);
+        }
 
-                        out.is_active = true.into();
-                        out.current_state = action_state.into();
-                        out.changed_since_last_sync = true.into(); //TODO
-                        out.last_change_time = xr::Time::from_nanos(0); //TODO
-                    }
-                    _ => return Err(xr::Result::ERROR_ACTION_TYPE_MISMATCH),
+        let state = inner
+            .reported_states
+            .get_or_read(action, sub_action_path, || {
+                let action_state =
+                    self.boolean_input(&instance, inner, wrapped_action, action, sub_action_path)?;
+                Ok(InputValue::Boolean(
+                    action_state || inner.advanced_pressed(action, sub_action_path),
+                ))
+            })?;
+
+        out.is_active = true.into();
+        out.current_state = state.value.is_pressed().into();
+        out.changed_since_last_sync = state.changed_since_last_sync.into();
+        out.last_change_time = state.last_change_time;
+
+        Ok(xr::Result::SUCCESS)
+    }
+
+    /// Checks that a float or vector2f action can be read through `sub_action_path` and returns
+    /// its override, if any
+    fn overridden_state(
+        &self,
+        action: xr::Action,
+        sub_action_path: xr::Path,
+        action_type: xr::ActionType,
+    ) -> Result<Option<OverriddenState>, xr::Result> {
+        self.inner
+            .get()
+            .ok_or(xr::Result::ERROR_ACTIONSET_NOT_ATTACHED)?;
+
+        let instance = self
+            .instance
+            .upgrade()
+            .ok_or(xr::Result::ERROR_HANDLE_INVALID)?;
+        let layer_actions = layer_action::all();
+        let wrapped_action = layer_action::get(&layer_actions, action)?;
+        if wrapped_action.action_type != action_type {
+            return Err(xr::Result::ERROR_ACTION_TYPE_MISMATCH);
+        }
+        if sub_action_path != xr::Path::NULL
+            && !wrapped_action.sub_action_paths.contains(&sub_action_path)
+        {
+            return Err(xr::Result::ERROR_PATH_UNSUPPORTED);
+        }
+
+        Ok(instance.action_overrides.get(action, sub_action_path))
+    }
+
+    /// The state of a float or vector2f action produced by its bindings and the remapping
+    fn analog_state(
+        &self,
+        action: xr::Action,
+        sub_action_path: xr::Path,
+    ) -> Result<ReportedState, xr::Result> {
+        let inner = self
+            .inner
+            .get()
+            .ok_or(xr::Result::ERROR_ACTIONSET_NOT_ATTACHED)?;
+        let instance = self
+            .instance
+            .upgrade()
+            .ok_or(xr::Result::ERROR_HANDLE_INVALID)?;
+
+        inner
+            .reported_states
+            .get_or_read(action, sub_action_path, || {
+                let layer_actions = layer_action::all();
+                let wrapped_action = layer_action::get(&layer_actions, action)?;
+                self.analog_input(&instance, inner, wrapped_action, action, sub_action_path)
+            })
+    }
+
+    pub fn xr_get_action_state_float(
+        &self,
+        action: xr::Action,
+        sub_action_path: xr::Path,
+        out: &mut xr::ActionStateFloat,
+    ) -> Result<xr::Result, xr::Result> {
+        let overridden =
+            match self.overridden_state(action, sub_action_path, xr::ActionType::FLOAT_INPUT)? {
+                Some(overridden) => overridden,
+                None => {
+                    let state = self.analog_state(action, sub_action_path)?;
+                    out.is_active = true.into();
+                    out.current_state = state.value.as_float();
+                    out.changed_since_last_sync = state.changed_since_last_sync.into();
+                    out.last_change_time = state.last_change_time;
+                    return Ok(xr::Result::SUCCESS);
                 }
-            }
-            layer_action::SubActions::Some(sub_actions) => {
-                let actions = match sub_actions {
-                    ManySubActions::Boolean(actions) => actions,
-                    _ => return Err(xr::Result::ERROR_ACTION_TYPE_MISMATCH),
-                };
+            };
 
-                for (path, action) in actions {
-                    if *path != sub_action_path {
-                        continue;
-                    }
+        let (is_active, state) = match overridden.state {
+            OverrideState::Float { state } => (true, state),
+            _ => (false, 0.),
+        };
+        out.is_active = is_active.into();
+        out.current_state = state;
+        out.changed_since_last_sync = overridden.changed_since_last_sync.into();
+        out.last_change_time = overridden.last_change_time;
 
-                    let action_state = inner
-                        .su_session
-                        .get_action_state(action)
-                        .expect("TODO handle error");
+        Ok(xr::Result::SUCCESS)
+    }
 
+    pub fn xr_get_action_state_vector2f(
+        &self,
+        action: xr::Action,
+        sub_action_path: xr::Path,
+        out: &mut xr::ActionStateVector2f,
+    ) -> Result<xr::Result, xr::Result> {
+        let overridden =
+            match self.overridden_state(action, sub_action_path, xr::ActionType::VECTOR2F_INPUT)? {
+                Some(overridden) => overridden,
+                None => {
+                    let state = self.analog_state(action, sub_action_path)?;
+                    let [x, y] = match state.value {
+                        InputValue::Vector2f(vector) => vector,
+                        _ => [0., 0.],
+                    };
                     out.is_active = true.into();
-                    out.current_state = action_state.into();
-                    out.changed_since_last_sync = true.into(); //TODO
-                    out.last_change_time = xr::Time::from_nanos(0); //TODO
-                    
+                    out.current_state = xr::Vector2f { x, y };
+                    out.changed_since_last_sync = state.changed_since_last_sync.into();
+                    out.last_change_time = state.last_change_time;
                     return Ok(xr::Result::SUCCESS);
                 }
+            };
 
-                return Err(xr::Result::ERROR_PATH_INVALID);
-            }
-        }
+        let (is_active, x, y) = match overridden.state {
+            OverrideState::Vector2f { x, y } => (true, x, y),
+            _ => (false, 0., 0.),
+        };
+        out.is_active = is_active.into();
+        out.current_state = xr::Vector2f { x, y };
+        out.changed_since_last_sync = overridden.changed_since_last_sync.into();
+        out.last_change_time = overridden.last_change_time;
 
         Ok(xr::Result::SUCCESS)
     }
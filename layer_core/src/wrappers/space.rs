@@ -0,0 +1,350 @@
+use std::{
+    collections::HashMap,
+    sync::{atomic::Ordering, Arc, Weak},
+};
+
+use dashmap::DashMap;
+use openxr::sys as xr;
+use parking_lot::Mutex;
+
+use crate::{
+    input::{
+        pose_filter::{PoseFilterState, Smoothing},
+        user_paths::Hand,
+    },
+    math,
+    profile::DerivedPose,
+    ToResult,
+};
+
+use super::{instance::InnerInstance, session::SessionWrapper, XrHandle, XrWrapper};
+
+pub struct SpaceWrapper {
+    pub handle: xr::Space,
+    pub session: Weak<SessionWrapper>,
+    pub inner_instance: Arc<InnerInstance>,
+    pub kind: SpaceKind,
+    /// Smoothing state for each base space the space has been located in
+    pub filters: Mutex<HashMap<xr::Space, PoseFilterState>>,
+}
+
+pub enum SpaceKind {
+    Action {
+        action: xr::Action,
+        sub_action_path: xr::Path,
+        /// The hand the runtime locates the space on, if the subaction path names one
+        physical_hand: Option<Hand>,
+        /// Set when the pose is derived from the other hand in one handed mode
+        derived_pose: Option<DerivedPose>,
+    },
+    /// LOCAL and STAGE spaces follow the playspace mover
+    Reference {
+        reference_space_type: xr::ReferenceSpaceType,
+        pose_in_reference_space: xr::Posef,
+    },
+}
+
+pub fn playspace_offset(space: xr::Space, time: xr::Time) -> Result<Option<xr::Posef>, xr::Result> {
+    let wrapper = match xr::Space::all_wrappers().get(&space) {
+        Some(wrapper) => wrapper.clone(),
+        None => return Ok(None),
+    };
+    wrapper.playspace_offset(time)
+}
+
+impl SpaceWrapper {
+    pub fn xr_locate_space(
+        &self,
+        base_space: xr::Space,
+        time: xr::Time,
+        location: &mut xr::SpaceLocation,
+    ) -> Result<xr::Result, xr::Result> {
+        let session = self
+            .session
+            .upgrade()
+            .ok_or(xr::Result::ERROR_HANDLE_INVALID)?;
+
+        let result = match &self.kind {
+            SpaceKind::Action {
+                action,
+                physical_hand,
+                derived_pose,
+                ..
+            } => self.locate_action_space(
+                &session,
+                *action,
+                *physical_hand,
+                derived_pose.as_ref(),
+                base_space,
+                time,
+                location,
+            )?,
+            SpaceKind::Reference { .. } => unsafe {
+                (self.inner_instance.core.locate_space)(self.handle, base_space, time, location)
+            }
+            .result()?,
+        };
+
+        if let SpaceKind::Action {
+            action,
+            sub_action_path,
+            ..
+        } = &self.kind
+        {
+            if session.is_overridden_inactive(*action, *sub_action_path) {
+                location.location_flags = xr::SpaceLocationFlags::EMPTY;
+            }
+        }
+
+        apply_playspace_offsets(
+            location,
+            self.playspace_offset(time)?,
+            playspace_offset(base_space, time)?,
+        );
+
+        Ok(result)
+    }
+
+    /// Locates a space the layer did not create in this space. Action spaces are located through
+    /// the layer in a reference space of the runtime and the other space is related to them there.
+    pub fn xr_locate_as_base_space(
+        &self,
+        space: xr::Space,
+        time: xr::Time,
+        location: &mut xr::SpaceLocation,
+    ) -> Result<xr::Result, xr::Result> {
+        let session = match (&self.kind, self.session.upgrade()) {
+            (SpaceKind::Action { .. }, Some(session)) => session,
+            _ => {
+                let result = unsafe {
+                    (self.inner_instance.core.locate_space)(space, self.handle, time, location)
+                }
+                .result()?;
+                apply_playspace_offsets(location, None, self.playspace_offset(time)?);
+                return Ok(result);
+            }
+        };
+
+        let reference_space = session.layer_local_space()?;
+        let mut base_location = xr::SpaceLocation {
+            ty: xr::SpaceLocation::TYPE,
+            next: std::ptr::null_mut(),
+            location_flags: xr::SpaceLocationFlags::EMPTY,
+            pose: math::IDENTITY,
+        };
+        self.xr_locate_space(reference_space, time, &mut base_location)?;
+        let result = unsafe {
+            (self.inner_instance.core.locate_space)(space, reference_space, time, location)
+        }
+        .result()?;
+
+        let valid =
+            xr::SpaceLocationFlags::POSITION_VALID | xr::SpaceLocationFlags::ORIENTATION_VALID;
+        if !base_location.location_flags.contains(valid) {
+            location.location_flags = xr::SpaceLocationFlags::EMPTY;
+            return Ok(result);
+        }
+        //The location is only tracked while both spaces are
+        location.location_flags = location.location_flags & base_location.location_flags;
+        apply_playspace_offsets(location, None, Some(base_location.pose));
+
+        Ok(result)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn locate_action_space(
+        &self,
+        session: &SessionWrapper,
+        action: xr::Action,
+        physical_hand: Option<Hand>,
+        derived_pose: Option<&DerivedPose>,
+        base_space: xr::Space,
+        time: xr::Time,
+        location: &mut xr::SpaceLocation,
+    ) -> Result<xr::Result, xr::Result> {
+        let filter = session.pose_filter(action, physical_hand);
+        let time = match filter {
+            Some(filter) => filter.clamp_time(
+                time,
+                xr::Time::from_nanos(session.predicted_display_time.load(Ordering::Relaxed)),
+            ),
+            None => time,
+        };
+
+        let result = unsafe {
+            (self.inner_instance.core.locate_space)(self.handle, base_space, time, location)
+        }
+        .result()?;
+
+        match physical_hand {
+            Some(hand) => session.apply_emulated_pose(action, hand, base_space, time, location)?,
+            None => session.apply_gaze_fallback(action, base_space, time, location)?,
+        }
+
+        if let Some(aim) = physical_hand.and_then(|hand| session.desktop_aim(hand)) {
+            //Without a tracked headset the hand is aimed from the origin of the base space
+            let head = session
+                .locate_view(base_space, time)?
+                .unwrap_or(math::IDENTITY);
+            location.pose = math::pose_mul(head, aim);
+            location.location_flags |=
+                xr::SpaceLocationFlags::POSITION_VALID | xr::SpaceLocationFlags::ORIENTATION_VALID;
+        } else if let Some(hand) = physical_hand {
+            if let Some(pose) = session.gaze_aim(action, hand, base_space, time)? {
+                location.pose = pose;
+                location.location_flags |= xr::SpaceLocationFlags::POSITION_VALID
+                    | xr::SpaceLocationFlags::ORIENTATION_VALID
+                    | xr::SpaceLocationFlags::POSITION_TRACKED
+                    | xr::SpaceLocationFlags::ORIENTATION_TRACKED;
+            } else if let Some(pose) = session.tracked_hand_pose(action, hand, base_space, time)? {
+                location.pose = pose;
+                location.location_flags |= xr::SpaceLocationFlags::POSITION_VALID
+                    | xr::SpaceLocationFlags::ORIENTATION_VALID
+                    | xr::SpaceLocationFlags::POSITION_TRACKED
+                    | xr::SpaceLocationFlags::ORIENTATION_TRACKED;
+            }
+        }
+
+        if let Some(offset) = session.pose_offset(action, physical_hand) {
+            apply_offset(location, offset);
+        }
+
+        match derived_pose {
+            Some(DerivedPose::FixedOffset { offset }) => apply_offset(location, offset.to_pose()),
+            Some(DerivedPose::Mirrored) => {
+                if let Some(head) = session.locate_view(base_space, time)? {
+                    let relative_to_head = math::pose_mul(math::pose_inverse(head), location.pose);
+                    location.pose = math::pose_mul(head, math::mirror_x(relative_to_head));
+                }
+            }
+            None => {}
+        }
+
+        if let Some(smoothing) = filter.and_then(|filter| filter.smoothing) {
+            self.apply_filter(base_space, time, location, smoothing);
+        }
+
+        Ok(result)
+    }
+
+    /// The transform from the runtime's space to the space the application sees
+    fn playspace_offset(&self, time: xr::Time) -> Result<Option<xr::Posef>, xr::Result> {
+        match &self.kind {
+            SpaceKind::Reference {
+                reference_space_type,
+                pose_in_reference_space,
+            } => match self.session.upgrade() {
+                Some(session) => {
+                    session.playspace_offset(*reference_space_type, *pose_in_reference_space, time)
+                }
+                None => Ok(None),
+            },
+            SpaceKind::Action { .. } => Ok(None),
+        }
+    }
+
+    fn apply_filter(
+        &self,
+        base_space: xr::Space,
+        time: xr::Time,
+        location: &mut xr::SpaceLocation,
+        smoothing: Smoothing,
+    ) {
+        let mut filters = self.filters.lock();
+
+        let valid =
+            xr::SpaceLocationFlags::POSITION_VALID | xr::SpaceLocationFlags::ORIENTATION_VALID;
+        if !location.location_flags.contains(valid) {
+            filters.remove(&base_space);
+            return;
+        }
+
+        let state = filters
+            .entry(base_space)
+            .or_insert_with(|| PoseFilterState::new(smoothing));
+        if state.smoothing() != smoothing {
+            *state = PoseFilterState::new(smoothing);
+        }
+
+        let filtered = state.update(location.pose, time);
+        location.pose = filtered.pose;
+
+        //Keep the velocity consistent with the filtered pose instead of the raw one
+        if let (Some((linear, angular)), Some(velocity)) =
+            (filtered.velocity, unsafe { find_velocity(location) })
+        {
+            velocity.linear_velocity = linear;
+            velocity.angular_velocity = angular;
+            velocity.velocity_flags |=
+                xr::SpaceVelocityFlags::LINEAR_VALID | xr::SpaceVelocityFlags::ANGULAR_VALID;
+        }
+    }
+}
+
+/// Moves a located pose by `offset` in its own frame, keeping a chained `XrSpaceVelocity` consistent
+fn apply_offset(location: &mut xr::SpaceLocation, offset: xr::Posef) {
+    let lever_arm = math::rotate(location.pose.orientation, offset.position);
+    location.pose = math::pose_mul(location.pose, offset);
+
+    if let Some(velocity) = unsafe { find_velocity(location) } {
+        if velocity
+            .velocity_flags
+            .contains(xr::SpaceVelocityFlags::LINEAR_VALID | xr::SpaceVelocityFlags::ANGULAR_VALID)
+        {
+            velocity.linear_velocity = math::add(
+                velocity.linear_velocity,
+                math::cross(velocity.angular_velocity, lever_arm),
+            );
+        }
+    }
+}
+
+/// Turns a location of the runtime's spaces into one of the spaces the application sees
+fn apply_playspace_offsets(
+    location: &mut xr::SpaceLocation,
+    space_offset: Option<xr::Posef>,
+    base_offset: Option<xr::Posef>,
+) {
+    if let Some(space_offset) = space_offset {
+        apply_offset(location, space_offset);
+    }
+
+    if let Some(base_offset) = base_offset {
+        let inverse = math::pose_inverse(base_offset);
+        location.pose = math::pose_mul(inverse, location.pose);
+
+        if let Some(velocity) = unsafe { find_velocity(location) } {
+            velocity.linear_velocity = math::rotate(inverse.orientation, velocity.linear_velocity);
+            velocity.angular_velocity =
+                math::rotate(inverse.orientation, velocity.angular_velocity);
+        }
+    }
+}
+
+unsafe fn find_velocity(location: &mut xr::SpaceLocation) -> Option<&mut xr::SpaceVelocity> {
+    let mut next = location.next as *mut xr::BaseOutStructure;
+    while !next.is_null() {
+        if (*next).ty == xr::StructureType::SPACE_VELOCITY {
+            return Some(&mut *(next as *mut xr::SpaceVelocity));
+        }
+        next = (*next).next;
+    }
+    None
+}
+
+impl XrWrapper for SpaceWrapper {
+    fn inner_instance(&self) -> &Arc<InnerInstance> {
+        &self.inner_instance
+    }
+}
+
+impl XrHandle for xr::Space {
+    type Wrapper = SpaceWrapper;
+
+    fn all_wrappers<'a>() -> &'a DashMap<Self, Arc<Self::Wrapper>>
+    where
+        Self: Sized + std::hash::Hash,
+    {
+        unsafe { super::SPACE_WRAPPERS.get().unwrap() }
+    }
+}
@@ -0,0 +1,40 @@
+use serde_json::Value;
+
+/// Lists the changes between two JSON documents, one line per changed value
+pub fn diff(a: &Value, b: &Value) -> Vec<String> {
+    let mut differences = Vec::new();
+    diff_value("", a, b, &mut differences);
+    differences
+}
+
+fn diff_value(path: &str, a: &Value, b: &Value, differences: &mut Vec<String>) {
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, a_value) in a {
+                let path = format!("{}/{}", path, key);
+                match b.get(key) {
+                    Some(b_value) => diff_value(&path, a_value, b_value, differences),
+                    None => differences.push(format!("- {}: {}", path, a_value)),
+                }
+            }
+            for (key, b_value) in b {
+                if !a.contains_key(key) {
+                    differences.push(format!("+ {}/{}: {}", path, key, b_value));
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            for i in 0..a.len().max(b.len()) {
+                let path = format!("{}/{}", path, i);
+                match (a.get(i), b.get(i)) {
+                    (Some(a), Some(b)) => diff_value(&path, a, b, differences),
+                    (Some(a), None) => differences.push(format!("- {}: {}", path, a)),
+                    (None, Some(b)) => differences.push(format!("+ {}: {}", path, b)),
+                    (None, None) => {}
+                }
+            }
+        }
+        (a, b) if a != b => differences.push(format!("~ {}: {} -> {}", path, a, b)),
+        _ => {}
+    }
+}
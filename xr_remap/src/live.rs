@@ -0,0 +1,343 @@
+use std::{collections::HashMap, error::Error, path::PathBuf};
+
+use layer_core::ipc::{
+    self,
+    protocol::{ActionState, BindingModification, InstanceInfo, OverrideState, Request, Response},
+};
+
+/// Every instance with a reachable control socket, stale sockets are skipped
+pub fn running_instances() -> Vec<(PathBuf, InstanceInfo)> {
+    ipc::socket_paths()
+        .into_iter()
+        .filter_map(|path| {
+            //Sockets are named `<pid>-<instance>.sock`
+            let handle = path
+                .file_stem()?
+                .to_str()?
+                .split_once('-')?
+                .1
+                .parse::<u64>()
+                .ok()?;
+            match ipc::request(&path, Request::ListInstances) {
+                Ok(Response::Instances { instances }) => instances
+                    .into_iter()
+                    .find(|instance| instance.handle == handle)
+                    .map(|instance| (path, instance)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+fn selected_instances(
+    application: Option<&str>,
+) -> Result<Vec<(PathBuf, InstanceInfo)>, Box<dyn Error>> {
+    let instances = running_instances()
+        .into_iter()
+        .filter(|(_, instance)| application.map_or(true, |name| instance.application_name == name))
+        .collect::<Vec<_>>();
+    if instances.is_empty() {
+        return Err("no matching application is running".into());
+    }
+    Ok(instances)
+}
+
+pub fn request(path: &std::path::Path, request: Request) -> Result<Response, Box<dyn Error>> {
+    match ipc::request(path, request)? {
+        Response::Error { message } => Err(message.into()),
+        response => Ok(response),
+    }
+}
+
+pub fn print_manifest(application: Option<&str>) -> Result<(), Box<dyn Error>> {
+    for (path, instance) in selected_instances(application)? {
+        println!("{}", instance.application_name);
+        if let Response::ActionSets { action_sets } = request(&path, Request::ListActionSets)? {
+            for action_set in action_sets {
+                println!("  {}", action_set.name);
+                for action in action_set.actions {
+                    println!(
+                        "    {} ({:?}) {}",
+                        action.name,
+                        action.action_type,
+                        action.sub_action_paths.join(" ")
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn record(
+    application: Option<&str>,
+    path: Option<PathBuf>,
+    stop: bool,
+) -> Result<(), Box<dyn Error>> {
+    let instances = selected_instances(application)?;
+    if path.is_some() && instances.len() > 1 {
+        return Err("several applications are running, choose one to record".into());
+    }
+
+    for (socket, instance) in instances {
+        let request_message = if stop {
+            Request::StopRecording
+        } else {
+            Request::StartRecording {
+                path: path.as_ref().map(|path| path.display().to_string()),
+            }
+        };
+        match request(&socket, request_message)? {
+            Response::RecordingStarted { path } => {
+                println!("{}: recording to {}", instance.application_name, path)
+            }
+            Response::RecordingStopped { path } => {
+                println!("{}: recorded {}", instance.application_name, path)
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+pub fn print_export(application: Option<&str>, json: bool) -> Result<(), Box<dyn Error>> {
+    for (path, instance) in selected_instances(application)? {
+        let bindings = match request(&path, Request::ExportBindings)? {
+            Response::ExportedBindings { bindings } => bindings,
+            _ => continue,
+        };
+        if json {
+            println!("{}", serde_json::to_string_pretty(&bindings)?);
+            continue;
+        }
+
+        println!("{}", instance.application_name);
+        for binding in bindings {
+            let modifications = binding
+                .modifications
+                .iter()
+                .map(|modification| match modification {
+                    BindingModification::AnalogThreshold {
+                        on_threshold,
+                        off_threshold,
+                    } => format!("analog threshold {}/{}", on_threshold, off_threshold),
+                    BindingModification::DpadBinding {
+                        center_region,
+                        wedge_angle,
+                        is_sticky,
+                        activator,
+                    } => format!(
+                        "dpad center={} wedge={}{}{}",
+                        center_region,
+                        wedge_angle,
+                        if *is_sticky { " sticky" } else { "" },
+                        activator
+                            .as_ref()
+                            .map(|activator| format!(" while {}", activator))
+                            .unwrap_or_default()
+                    ),
+                    BindingModification::Modifier { modifier } => format!("while {}", modifier),
+                    BindingModification::SubactionPath { sub_action_path } => {
+                        format!("through {}", sub_action_path)
+                    }
+                })
+                .collect::<Vec<_>>();
+            println!(
+                "  {} {}/{} {}{}",
+                binding.interaction_profile,
+                binding.action_set,
+                binding.action,
+                binding.binding,
+                if modifications.is_empty() {
+                    String::new()
+                } else {
+                    format!(" ({})", modifications.join(", "))
+                }
+            );
+        }
+    }
+    Ok(())
+}
+
+pub fn inject(
+    application: Option<&str>,
+    source: &str,
+    values: &[String],
+) -> Result<(), Box<dyn Error>> {
+    let values = values
+        .iter()
+        .map(|pair| {
+            let (component, value) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("`{}` is not `<component>=<value>`", pair))?;
+            let value = value
+                .parse::<f32>()
+                .map_err(|err| format!("invalid value in `{}`: {}", pair, err))?;
+            Ok((component.to_owned(), value))
+        })
+        .collect::<Result<HashMap<_, _>, String>>()?;
+
+    for (path, instance) in selected_instances(application)? {
+        request(
+            &path,
+            Request::InjectInput {
+                source: source.to_owned(),
+                values: values.clone(),
+            },
+        )?;
+        println!("{}: injected", instance.application_name);
+    }
+    Ok(())
+}
+
+fn parse_override_state(state: &str) -> Result<OverrideState, String> {
+    let number = |value: &str| {
+        value
+            .trim()
+            .parse::<f32>()
+            .map_err(|err| format!("invalid state `{}`: {}", state, err))
+    };
+    Ok(match state {
+        "true" => OverrideState::Boolean { state: true },
+        "false" => OverrideState::Boolean { state: false },
+        "inactive" => OverrideState::Inactive,
+        _ => match state.split_once(',') {
+            Some((x, y)) => OverrideState::Vector2f {
+                x: number(x)?,
+                y: number(y)?,
+            },
+            None => OverrideState::Float {
+                state: number(state)?,
+            },
+        },
+    })
+}
+
+pub fn override_action(
+    application: Option<&str>,
+    action: &str,
+    state: &str,
+    sub_action_path: Option<String>,
+    frames: Option<u32>,
+) -> Result<(), Box<dyn Error>> {
+    let (action_set, action) = action
+        .split_once('/')
+        .ok_or_else(|| format!("`{}` is not `<action set>/<action>`", action))?;
+    let state = parse_override_state(state)?;
+
+    for (path, instance) in selected_instances(application)? {
+        request(
+            &path,
+            Request::OverrideAction {
+                action_set: action_set.to_owned(),
+                action: action.to_owned(),
+                sub_action_path: sub_action_path.clone(),
+                state,
+                frames,
+            },
+        )?;
+        println!("{}: overridden", instance.application_name);
+    }
+    Ok(())
+}
+
+pub fn clear_overrides(
+    application: Option<&str>,
+    action: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let (action_set, action) = match action.map(|action| action.split_once('/')) {
+        Some(Some((action_set, action))) => (Some(action_set), Some(action)),
+        Some(None) => (action, None),
+        None => (None, None),
+    };
+
+    for (path, instance) in selected_instances(application)? {
+        if let Response::OverridesCleared { count } = request(
+            &path,
+            Request::ClearOverrides {
+                action_set: action_set.map(str::to_owned),
+                action: action.map(str::to_owned),
+            },
+        )? {
+            println!("{}: cleared {} overrides", instance.application_name, count);
+        }
+    }
+    Ok(())
+}
+
+pub fn print_report(report: &BindingReport) {
+    println!("  {}", report.interaction_profile);
+    for action in &report.unbound_actions {
+        println!("    unbound: {}", action);
+    }
+    for conflict in &report.conflicts {
+        println!(
+            "    conflict: {} -> {} (priority {})",
+            conflict.binding,
+            conflict.actions.join(", "),
+            conflict.priority
+        );
+    }
+    for input in &report.unused_inputs {
+        println!("    unused: {}", input);
+    }
+}
+
+pub fn analyze(
+    application: Option<&str>,
+    interaction_profile: Option<String>,
+) -> Result<(), Box<dyn Error>> {
+    for (path, instance) in selected_instances(application)? {
+        println!("{}", instance.application_name);
+        if let Response::BindingReports { reports } = request(
+            &path,
+            Request::AnalyzeBindings {
+                interaction_profile: interaction_profile.clone(),
+            },
+        )? {
+            for report in &reports {
+                print_report(report);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn print_states(application: Option<&str>, watch: Option<u64>) -> Result<(), Box<dyn Error>> {
+    let instances = selected_instances(application)?;
+    loop {
+        for (path, instance) in &instances {
+            println!("{}", instance.application_name);
+            if let Response::ActionStates { states } = request(path, Request::GetActionStates)? {
+                for state in states {
+                    let value = match state.state {
+                        ActionState::Boolean { state } => state.to_string(),
+                        ActionState::Float { state } => format!("{:.3}", state),
+                        ActionState::Vector2f { x, y } => format!("({:.3}, {:.3})", x, y),
+                        ActionState::Pose => "pose".to_owned(),
+                    };
+                    println!(
+                        "  {}/{}{} {} {}",
+                        state.action_set,
+                        state.action,
+                        state
+                            .sub_action_path
+                            .map(|path| format!("[{}]", path))
+                            .unwrap_or_default(),
+                        if state.is_active {
+                            "active"
+                        } else {
+                            "inactive"
+                        },
+                        value
+                    );
+                }
+            }
+        }
+
+        match watch {
+            Some(interval) => std::thread::sleep(std::time::Duration::from_millis(interval)),
+            None => return Ok(()),
+        }
+    }
+}
@@ -0,0 +1,404 @@
+mod diff;
+mod live;
+mod rebind;
+
+use std::error::Error;
+
+use clap::{Parser, Subcommand};
+use layer_core::{
+    manifest::{self, ActionManifest},
+    profile::{self, RemapProfile},
+    recording, steamvr,
+};
+
+#[derive(Parser)]
+#[clap(
+    name = "xr-remap",
+    about = "Manage the remap profiles of the SuInput OpenXR layer"
+)]
+struct Cli {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List applications with profiles and running applications
+    Apps,
+    /// List the profiles of an application
+    Profiles { application: String },
+    /// Create a new profile
+    Create {
+        application: String,
+        profile: String,
+        /// Copy an existing profile instead of starting empty
+        #[clap(long)]
+        from: Option<String>,
+        /// Also make the new profile the active one
+        #[clap(long)]
+        activate: bool,
+    },
+    /// Check a profile for problems, defaults to the active profile
+    Validate {
+        application: String,
+        profile: Option<String>,
+    },
+    /// Show the differences between two profiles
+    Diff {
+        application: String,
+        a: String,
+        b: String,
+    },
+    /// Select the profile an application uses the next time it starts
+    Switch {
+        application: String,
+        profile: String,
+    },
+    /// Convert a SteamVR Input binding file into a new profile
+    ImportSteamvr {
+        application: String,
+        file: std::path::PathBuf,
+        profile: String,
+        /// Replace the profile if it already exists
+        #[clap(long)]
+        force: bool,
+        /// Also make the new profile the active one
+        #[clap(long)]
+        activate: bool,
+    },
+    /// Print the action sets and actions of running applications
+    Manifest { application: Option<String> },
+    /// List the application versions with a saved action manifest
+    Versions { application: String },
+    /// Show how the actions changed between two versions and which remaps of the active profile
+    /// they broke, defaults to the two newest versions
+    ManifestDiff {
+        application: String,
+        old: Option<u32>,
+        new: Option<u32>,
+    },
+    /// Print the effective bindings of running applications as they would be suggested
+    Export {
+        application: Option<String>,
+        /// Print JSON instead of one line per binding
+        #[clap(long)]
+        json: bool,
+    },
+    /// Start or stop recording the input of running applications, to reproduce problems
+    Record {
+        application: Option<String>,
+        /// The file to write, defaults to the layer's data directory
+        #[clap(long, conflicts_with = "stop")]
+        path: Option<std::path::PathBuf>,
+        #[clap(long)]
+        stop: bool,
+    },
+    /// Compare the action states of a replay against the recording it replayed, frame by frame.
+    /// Run the application with `SUINPUT_LAYER_REPLAY` and `SUINPUT_LAYER_RECORD` to create one.
+    CompareRecordings {
+        expected: std::path::PathBuf,
+        actual: std::path::PathBuf,
+    },
+    /// Print the live action states of running applications
+    States {
+        application: Option<String>,
+        /// Keep printing every given number of milliseconds
+        #[clap(long)]
+        watch: Option<u64>,
+    },
+    /// Interactively rebind the actions of a running application
+    Tui { application: Option<String> },
+    /// Set external inputs of a virtual device of running applications, e.g.
+    /// `inject /user/virtual/pedal /input/pedal/value=0.5`
+    Inject {
+        source: String,
+        /// `<component>=<value>` pairs
+        #[clap(required = true)]
+        values: Vec<String>,
+        #[clap(long)]
+        application: Option<String>,
+    },
+    /// Force an action of running applications to a state for debugging, e.g.
+    /// `override gameplay/jump true`, `override gameplay/move 0,1` or
+    /// `override gameplay/hand_pose inactive --sub-action-path /user/hand/left`
+    Override {
+        /// `<action set>/<action>`
+        action: String,
+        /// `true` or `false`, a number, `<x>,<y>` or `inactive`
+        state: String,
+        #[clap(long)]
+        sub_action_path: Option<String>,
+        /// Only override the given number of frames
+        #[clap(long)]
+        frames: Option<u32>,
+        #[clap(long)]
+        application: Option<String>,
+    },
+    /// Remove the overrides of an action set or `<action set>/<action>`, or all of them
+    ClearOverrides {
+        action: Option<String>,
+        #[clap(long)]
+        application: Option<String>,
+    },
+    /// Report unbound actions, inputs bound to several actions and unused inputs of running
+    /// applications
+    Analyze {
+        application: Option<String>,
+        /// Only check this interaction profile instead of the ones currently in use
+        #[clap(long)]
+        interaction_profile: Option<String>,
+        /// Check the newest saved manifest of the application with a profile instead, defaults to
+        /// the active profile
+        #[clap(long, requires = "application")]
+        saved: bool,
+        #[clap(long, requires = "saved")]
+        profile: Option<String>,
+    },
+}
+
+fn main() {
+    if let Err(err) = run(Cli::parse().command) {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run(command: Command) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Apps => {
+            for application in profile::list_applications() {
+                println!(
+                    "{} (active profile: {})",
+                    application,
+                    profile::active_profile_name(&application)
+                );
+            }
+            for (_, instance) in live::running_instances() {
+                println!(
+                    "{} (running, version {}, {})",
+                    instance.application_name, instance.application_version, instance.runtime
+                );
+            }
+        }
+        Command::Profiles { application } => {
+            let active = profile::active_profile_name(&application);
+            for name in profile::list_profiles(&application) {
+                let marker = if name == active { "*" } else { " " };
+                println!("{} {}", marker, name);
+            }
+        }
+        Command::Create {
+            application,
+            profile: name,
+            from,
+            activate,
+        } => {
+            let path = profile::profile_path(&application, &name);
+            if path.exists() {
+                return Err(format!("`{}` already exists", path.display()).into());
+            }
+            let remap_profile = match from {
+                Some(from) => RemapProfile::load(&profile::profile_path(&application, &from))?,
+                None => RemapProfile::default(),
+            };
+            remap_profile.save(&path)?;
+            println!("Created `{}`", path.display());
+            if activate {
+                profile::set_active_profile(&application, &name)?;
+            }
+        }
+        Command::Validate {
+            application,
+            profile: name,
+        } => {
+            let name = name.unwrap_or_else(|| profile::active_profile_name(&application));
+            let path = profile::profile_path(&application, &name);
+            let issues = RemapProfile::load(&path)?.validate();
+            if !issues.is_empty() {
+                for issue in &issues {
+                    println!("{}", issue);
+                }
+                return Err(format!("`{}` has {} problem(s)", path.display(), issues.len()).into());
+            }
+            println!("`{}` is valid", path.display());
+        }
+        Command::Diff { application, a, b } => {
+            let a = RemapProfile::load(&profile::profile_path(&application, &a))?;
+            let b = RemapProfile::load(&profile::profile_path(&application, &b))?;
+            let differences = diff::diff(&serde_json::to_value(&a)?, &serde_json::to_value(&b)?);
+            if differences.is_empty() {
+                println!("The profiles are identical");
+            }
+            for difference in differences {
+                println!("{}", difference);
+            }
+        }
+        Command::Switch {
+            application,
+            profile: name,
+        } => {
+            let path = profile::profile_path(&application, &name);
+            if !path.exists() {
+                return Err(format!("`{}` does not exist", path.display()).into());
+            }
+            profile::set_active_profile(&application, &name)?;
+            println!(
+                "`{}` will use `{}` the next time it starts",
+                application, name
+            );
+        }
+        Command::ImportSteamvr {
+            application,
+            file,
+            profile: name,
+            force,
+            activate,
+        } => {
+            let path = profile::profile_path(&application, &name);
+            if path.exists() && !force {
+                return Err(format!("`{}` already exists", path.display()).into());
+            }
+
+            let import = steamvr::import(&std::fs::read_to_string(&file)?)?;
+            for issue in &import.issues {
+                println!("{}", issue);
+            }
+            for issue in import.profile.validate() {
+                println!("{}", issue);
+            }
+            if let Some(version) = manifest::list_versions(&application).last() {
+                let manifest =
+                    ActionManifest::load(&manifest::manifest_path(&application, *version))?;
+                for broken in manifest.broken_remaps(&import.profile) {
+                    println!("{}", broken);
+                }
+            }
+
+            import.profile.save(&path)?;
+            println!("Created `{}`", path.display());
+            if activate {
+                profile::set_active_profile(&application, &name)?;
+            }
+        }
+        Command::Manifest { application } => live::print_manifest(application.as_deref())?,
+        Command::Versions { application } => {
+            for version in manifest::list_versions(&application) {
+                println!("{}", version);
+            }
+        }
+        Command::ManifestDiff {
+            application,
+            old,
+            new,
+        } => {
+            let versions = manifest::list_versions(&application);
+            let (old, new) = match (old, new) {
+                (Some(old), Some(new)) => (old, new),
+                (Some(old), None) => (old, *versions.last().ok_or("no saved manifests")?),
+                (None, _) if versions.len() >= 2 => {
+                    (versions[versions.len() - 2], versions[versions.len() - 1])
+                }
+                (None, _) => return Err("at least two versions need a saved manifest".into()),
+            };
+            let old = ActionManifest::load(&manifest::manifest_path(&application, old))?;
+            let new = ActionManifest::load(&manifest::manifest_path(&application, new))?;
+
+            let differences = old.diff(&new);
+            if differences.is_empty() {
+                println!("The actions are identical");
+            }
+            for difference in differences {
+                println!("{}", difference);
+            }
+
+            let name = profile::active_profile_name(&application);
+            let path = profile::profile_path(&application, &name);
+            if path.exists() {
+                for broken in new.broken_remaps(&RemapProfile::load(&path)?) {
+                    println!("{}: {}", name, broken);
+                }
+            }
+        }
+        Command::Export { application, json } => live::print_export(application.as_deref(), json)?,
+        Command::Record {
+            application,
+            path,
+            stop,
+        } => live::record(application.as_deref(), path, stop)?,
+        Command::CompareRecordings { expected, actual } => {
+            let differences =
+                recording::compare_states(&recording::read(&expected)?, &recording::read(&actual)?);
+            if differences.is_empty() {
+                println!("The action states are identical");
+            } else {
+                for difference in &differences {
+                    println!("{}", difference);
+                }
+                return Err(format!("{} action states differ", differences.len()).into());
+            }
+        }
+        Command::States { application, watch } => {
+            live::print_states(application.as_deref(), watch)?
+        }
+        Command::Tui { application } => rebind::run(application.as_deref())?,
+        Command::Inject {
+            source,
+            values,
+            application,
+        } => live::inject(application.as_deref(), &source, &values)?,
+        Command::Override {
+            action,
+            state,
+            sub_action_path,
+            frames,
+            application,
+        } => live::override_action(
+            application.as_deref(),
+            &action,
+            &state,
+            sub_action_path,
+            frames,
+        )?,
+        Command::ClearOverrides {
+            action,
+            application,
+        } => live::clear_overrides(application.as_deref(), action.as_deref())?,
+        Command::Analyze {
+            application,
+            interaction_profile,
+            saved: true,
+            profile: name,
+        } => {
+            let application = application.ok_or("no application given")?;
+            let version = *manifest::list_versions(&application)
+                .last()
+                .ok_or("no saved manifests")?;
+            let manifest = ActionManifest::load(&manifest::manifest_path(&application, version))?;
+            let name = name.unwrap_or_else(|| profile::active_profile_name(&application));
+            let path = profile::profile_path(&application, &name);
+            let profile = if path.exists() {
+                RemapProfile::load(&path)?
+            } else {
+                RemapProfile::default()
+            };
+            println!("{} {} ({})", application, version, name);
+            for report in manifest.binding_reports(&profile) {
+                if interaction_profile
+                    .as_ref()
+                    .map_or(true, |interaction_profile| {
+                        *interaction_profile == report.interaction_profile
+                    })
+                {
+                    live::print_report(&report);
+                }
+            }
+        }
+        Command::Analyze {
+            application,
+            interaction_profile,
+            ..
+        } => live::analyze(application.as_deref(), interaction_profile)?,
+    }
+
+    Ok(())
+}
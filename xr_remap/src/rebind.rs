@@ -0,0 +1,433 @@
+use std::{
+    error::Error,
+    io,
+    path::PathBuf,
+    sync::mpsc::{self, Receiver},
+    time::Duration,
+};
+
+use crossterm::{
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, MouseButton},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use layer_core::{
+    ipc::protocol::{
+        ActionSetInfo, ActionType, Binding, InstanceInfo, InteractionProfileBindings, Request,
+        Response,
+    },
+    profile::{self, ProfileError, Rebind, RemapProfile},
+};
+use tui::{
+    backend::{Backend, CrosstermBackend},
+    layout::{Constraint, Direction, Layout},
+    style::{Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Frame, Terminal,
+};
+
+use crate::live;
+
+const CAPTURE_TIMEOUT_MS: u64 = 10_000;
+
+enum Row {
+    ActionSet(usize),
+    Action(usize, usize),
+}
+
+struct App {
+    socket: PathBuf,
+    instance: InstanceInfo,
+    action_sets: Vec<ActionSetInfo>,
+    interaction_profiles: Vec<InteractionProfileBindings>,
+    interaction_profile: usize,
+    rows: Vec<Row>,
+    list_state: ListState,
+    //Mirrors the scroll offset of the list, which tui does not expose
+    list_offset: usize,
+    status: String,
+    capture: Option<(usize, usize, Receiver<Result<Response, String>>)>,
+}
+
+pub fn run(application: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let (socket, instance) = live::running_instances()
+        .into_iter()
+        .find(|(_, instance)| application.map_or(true, |name| instance.application_name == name))
+        .ok_or("no matching application is running")?;
+
+    let mut app = App {
+        socket,
+        instance,
+        action_sets: Vec::new(),
+        interaction_profiles: Vec::new(),
+        interaction_profile: 0,
+        rows: Vec::new(),
+        list_state: ListState::default(),
+        list_offset: 0,
+        status: "Enter or click a selected action to rebind, Tab to switch controllers, q to quit"
+            .to_owned(),
+        capture: None,
+    };
+    app.refresh()?;
+
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let result = event_loop(&mut terminal, &mut app);
+
+    disable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )?;
+    terminal.show_cursor()?;
+
+    result
+}
+
+fn event_loop<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<(), Box<dyn Error>> {
+    loop {
+        terminal.draw(|frame| draw(frame, app))?;
+        app.poll_capture();
+
+        if !event::poll(Duration::from_millis(100))? {
+            continue;
+        }
+
+        match event::read()? {
+            Event::Key(key) => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Up | KeyCode::Char('k') => app.select(-1),
+                KeyCode::Down | KeyCode::Char('j') => app.select(1),
+                KeyCode::Tab => app.next_interaction_profile(),
+                KeyCode::Enter | KeyCode::Char('r') => app.start_capture(),
+                KeyCode::Char('R') => {
+                    if let Err(err) = app.refresh() {
+                        app.status = err.to_string();
+                    }
+                }
+                _ => {}
+            },
+            Event::Mouse(mouse) if mouse.kind == event::MouseEventKind::Down(MouseButton::Left) => {
+                //The list starts below the header block and its border
+                let row = (mouse.row as usize).checked_sub(4);
+                let row = row.map(|row| row + app.list_offset);
+                if let Some(row) = row.filter(|row| *row < app.rows.len()) {
+                    if app.list_state.selected() == Some(row) {
+                        app.start_capture();
+                    } else {
+                        app.list_state.select(Some(row));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn draw<B: Backend>(frame: &mut Frame<B>, app: &mut App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(3),
+            Constraint::Length(3),
+        ])
+        .split(frame.size());
+
+    let interaction_profile = app
+        .current_interaction_profile()
+        .map(|profile| profile.interaction_profile.as_str())
+        .unwrap_or("no bindings suggested");
+    let header = Paragraph::new(Spans::from(vec![
+        Span::styled(
+            app.instance.application_name.clone(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(format!("  {}", interaction_profile)),
+    ]))
+    .block(Block::default().borders(Borders::ALL).title("xr-remap"));
+    frame.render_widget(header, chunks[0]);
+
+    let items = app
+        .rows
+        .iter()
+        .map(|row| match row {
+            Row::ActionSet(set) => {
+                let action_set = &app.action_sets[*set];
+                ListItem::new(Span::styled(
+                    format!("{} ({})", action_set.localized_name, action_set.name),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ))
+            }
+            Row::Action(set, action) => {
+                let action_set = &app.action_sets[*set];
+                let action = &action_set.actions[*action];
+                let bindings = app.bindings_of(&action_set.name, &action.name);
+                ListItem::new(format!(
+                    "  {:<32} {:<10} {}",
+                    action.localized_name,
+                    format!("{:?}", action.action_type).to_lowercase(),
+                    if bindings.is_empty() {
+                        "unbound".to_owned()
+                    } else {
+                        bindings.join(", ")
+                    }
+                ))
+            }
+        })
+        .collect::<Vec<_>>();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("Actions"))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(list, chunks[1], &mut app.list_state);
+
+    let height = chunks[1].height.saturating_sub(2) as usize;
+    if let Some(selected) = app.list_state.selected() {
+        if selected < app.list_offset {
+            app.list_offset = selected;
+        } else if height > 0 && selected >= app.list_offset + height {
+            app.list_offset = selected + 1 - height;
+        }
+    }
+
+    let status = Paragraph::new(app.status.clone()).block(Block::default().borders(Borders::ALL));
+    frame.render_widget(status, chunks[2]);
+}
+
+impl App {
+    fn refresh(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Response::ActionSets { action_sets } =
+            live::request(&self.socket, Request::ListActionSets)?
+        {
+            self.action_sets = action_sets;
+        }
+        if let Response::SuggestedBindings {
+            interaction_profiles,
+        } = live::request(&self.socket, Request::ListSuggestedBindings)?
+        {
+            self.interaction_profiles = interaction_profiles;
+        }
+        self.interaction_profile = self
+            .interaction_profile
+            .min(self.interaction_profiles.len().saturating_sub(1));
+
+        self.rows.clear();
+        for (i, action_set) in self.action_sets.iter().enumerate() {
+            self.rows.push(Row::ActionSet(i));
+            for j in 0..action_set.actions.len() {
+                self.rows.push(Row::Action(i, j));
+            }
+        }
+        if self
+            .list_state
+            .selected()
+            .map_or(true, |row| row >= self.rows.len())
+        {
+            self.list_state
+                .select(if self.rows.is_empty() { None } else { Some(0) });
+        }
+
+        Ok(())
+    }
+
+    fn current_interaction_profile(&self) -> Option<&InteractionProfileBindings> {
+        self.interaction_profiles.get(self.interaction_profile)
+    }
+
+    fn bindings_of(&self, action_set: &str, action: &str) -> Vec<String> {
+        self.current_interaction_profile()
+            .map(|profile| {
+                profile
+                    .bindings
+                    .iter()
+                    .filter(|binding| binding.action_set == action_set && binding.action == action)
+                    .map(|binding| binding.binding.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn select(&mut self, delta: isize) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let selected = self.list_state.selected().unwrap_or(0) as isize + delta;
+        self.list_state.select(Some(
+            selected.clamp(0, self.rows.len() as isize - 1) as usize
+        ));
+    }
+
+    fn next_interaction_profile(&mut self) {
+        if !self.interaction_profiles.is_empty() {
+            self.interaction_profile =
+                (self.interaction_profile + 1) % self.interaction_profiles.len();
+        }
+    }
+
+    fn start_capture(&mut self) {
+        if self.capture.is_some() {
+            return;
+        }
+        let (set, action) = match self
+            .list_state
+            .selected()
+            .and_then(|row| self.rows.get(row))
+        {
+            Some(Row::Action(set, action)) => (*set, *action),
+            _ => return,
+        };
+
+        let action_info = &self.action_sets[set].actions[action];
+        if !matches!(
+            action_info.action_type,
+            ActionType::Boolean | ActionType::Float
+        ) {
+            self.status = format!(
+                "{} actions cannot be rebound by pressing an input",
+                format!("{:?}", action_info.action_type).to_lowercase()
+            );
+            return;
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let socket = self.socket.clone();
+        std::thread::spawn(move || {
+            let result = live::request(
+                &socket,
+                Request::CaptureInput {
+                    timeout_ms: CAPTURE_TIMEOUT_MS,
+                },
+            )
+            .map_err(|err| err.to_string());
+            let _ = sender.send(result);
+        });
+
+        self.status = format!(
+            "Press the input to bind to `{}`...",
+            action_info.localized_name
+        );
+        self.capture = Some((set, action, receiver));
+    }
+
+    fn poll_capture(&mut self) {
+        let result = match &self.capture {
+            Some((_, _, receiver)) => match receiver.try_recv() {
+                Ok(result) => result,
+                Err(mpsc::TryRecvError::Empty) => return,
+                Err(mpsc::TryRecvError::Disconnected) => Err("capture failed".to_owned()),
+            },
+            None => return,
+        };
+        let (set, action, _) = self.capture.take().unwrap();
+
+        self.status = match result.and_then(|response| match response {
+            Response::CapturedInput {
+                binding,
+                interaction_profile,
+            } => self
+                .rebind(set, action, binding, interaction_profile)
+                .map_err(|err| err.to_string()),
+            _ => Err("unexpected response".to_owned()),
+        }) {
+            Ok(status) => status,
+            Err(err) => err,
+        };
+    }
+
+    /// Pushes the new binding to the running application and stores it in its active profile
+    fn rebind(
+        &mut self,
+        set: usize,
+        action: usize,
+        binding: String,
+        interaction_profile: Option<String>,
+    ) -> Result<String, Box<dyn Error>> {
+        let interaction_profile = match interaction_profile {
+            Some(interaction_profile) => interaction_profile,
+            None => self
+                .current_interaction_profile()
+                .ok_or("no interaction profile is active")?
+                .interaction_profile
+                .clone(),
+        };
+        let action_set = &self.action_sets[set];
+        let action_info = &action_set.actions[action];
+
+        //Pose and haptic actions keep the bindings the runtime was given
+        let rebindable = |binding: &Binding| {
+            self.action_sets
+                .iter()
+                .find(|set| set.name == binding.action_set)
+                .and_then(|set| {
+                    set.actions
+                        .iter()
+                        .find(|action| action.name == binding.action)
+                })
+                .map_or(false, |action| {
+                    !matches!(action.action_type, ActionType::Pose | ActionType::Vibration)
+                })
+        };
+
+        let mut bindings = self
+            .interaction_profiles
+            .iter()
+            .find(|profile| profile.interaction_profile == interaction_profile)
+            .map(|profile| {
+                profile
+                    .bindings
+                    .iter()
+                    .filter(|binding| rebindable(binding))
+                    .filter(|other| {
+                        other.action_set != action_set.name || other.action != action_info.name
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        bindings.push(Binding {
+            action_set: action_set.name.clone(),
+            action: action_info.name.clone(),
+            binding: binding.clone(),
+            sub_action_path: None,
+        });
+
+        live::request(
+            &self.socket,
+            Request::SetBindings {
+                interaction_profile: interaction_profile.clone(),
+                bindings,
+            },
+        )?;
+
+        let application = &self.instance.application_name;
+        let path = profile::profile_path(application, &profile::active_profile_name(application));
+        let mut remap_profile = match RemapProfile::load(&path) {
+            Ok(remap_profile) => remap_profile,
+            Err(ProfileError::Io(err)) if err.kind() == io::ErrorKind::NotFound => {
+                RemapProfile::default()
+            }
+            Err(err) => return Err(err.into()),
+        };
+        remap_profile.set_rebind(Rebind {
+            interaction_profile,
+            action_set: action_set.name.clone(),
+            action: action_info.name.clone(),
+            bindings: vec![binding.clone()],
+        });
+        remap_profile.save(&path)?;
+
+        let status = format!(
+            "Bound `{}` to `{}` and saved `{}`",
+            action_info.localized_name,
+            binding,
+            path.display()
+        );
+        self.refresh()?;
+        Ok(status)
+    }
+}